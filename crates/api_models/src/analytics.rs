@@ -4,10 +4,12 @@ use common_utils::events::ApiEventMetric;
 use time::PrimitiveDateTime;
 
 use self::{
+    disputes::{DisputeDimensions, DisputeMetrics},
     payments::{PaymentDimensions, PaymentMetrics},
     refunds::{RefundDimensions, RefundMetrics},
 };
 
+pub mod disputes;
 pub mod payments;
 pub mod refunds;
 
@@ -36,9 +38,36 @@ pub struct TimeRange {
     pub end_time: Option<PrimitiveDateTime>,
 }
 
+/// Time range relative to the time it is resolved at, e.g. the last 24 hours
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeTimeRange {
+    /// Ends at the time of resolution and starts the duration before it
+    Last(time::Duration),
+}
+
+impl RelativeTimeRange {
+    /// Resolves the range against the current UTC time
+    pub fn resolve(&self) -> TimeRange {
+        self.resolve_at(common_utils::date_time::now())
+    }
+
+    pub fn resolve_at(&self, now: PrimitiveDateTime) -> TimeRange {
+        match self {
+            Self::Last(duration) => TimeRange {
+                start_time: now - *duration,
+                end_time: Some(now),
+            },
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, serde::Deserialize, masking::Serialize)]
 pub struct TimeSeries {
     pub granularity: Granularity,
+    /// Keeps the granularity as requested, even when it splits the time range into more buckets
+    /// than can be rendered
+    #[serde(default)]
+    pub exact_granularity: bool,
 }
 
 #[derive(Clone, Copy, Debug, serde::Deserialize, masking::Serialize)]
@@ -69,6 +98,8 @@ pub struct GetPaymentMetricRequest {
     pub metrics: HashSet<PaymentMetrics>,
     #[serde(default)]
     pub delta: bool,
+    #[serde(default)]
+    pub metric_params: payments::PaymentMetricParams,
 }
 
 impl ApiEventMetric for GetPaymentMetricRequest {}
@@ -89,6 +120,22 @@ pub struct GetRefundMetricRequest {
 
 impl ApiEventMetric for GetRefundMetricRequest {}
 
+#[derive(Clone, Debug, serde::Deserialize, masking::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetDisputeMetricRequest {
+    pub time_series: Option<TimeSeries>,
+    pub time_range: TimeRange,
+    #[serde(default)]
+    pub group_by_names: Vec<DisputeDimensions>,
+    #[serde(default)]
+    pub filters: disputes::DisputeFilters,
+    pub metrics: HashSet<DisputeMetrics>,
+    #[serde(default)]
+    pub delta: bool,
+}
+
+impl ApiEventMetric for GetDisputeMetricRequest {}
+
 #[derive(Debug, serde::Serialize)]
 pub struct AnalyticsMetadata {
     pub current_time_range: TimeRange,
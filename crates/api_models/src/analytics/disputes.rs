@@ -0,0 +1,142 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use common_enums::enums::DisputeStage;
+use common_utils::events::ApiEventMetric;
+
+use super::{NameDescription, TimeRange};
+use crate::analytics::MetricsResponse;
+
+#[derive(Clone, Debug, Default, serde::Deserialize, masking::Serialize)]
+pub struct DisputeFilters {
+    #[serde(default)]
+    pub connector: Vec<String>,
+    #[serde(default)]
+    pub dispute_stage: Vec<DisputeStage>,
+}
+
+#[derive(
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::AsRefStr,
+    PartialEq,
+    PartialOrd,
+    Eq,
+    Ord,
+    strum::Display,
+    strum::EnumIter,
+    Clone,
+    Copy,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum DisputeDimensions {
+    Connector,
+    DisputeStage,
+}
+
+#[derive(
+    Clone,
+    Debug,
+    Hash,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+    strum::AsRefStr,
+)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum DisputeMetrics {
+    DisputeWinRate,
+    ChargebackRatio,
+}
+
+pub mod metric_behaviour {
+    pub struct DisputeWinRate;
+    pub struct ChargebackRatio;
+}
+
+impl From<DisputeMetrics> for NameDescription {
+    fn from(value: DisputeMetrics) -> Self {
+        Self {
+            name: value.to_string(),
+            desc: String::new(),
+        }
+    }
+}
+
+impl From<DisputeDimensions> for NameDescription {
+    fn from(value: DisputeDimensions) -> Self {
+        Self {
+            name: value.to_string(),
+            desc: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize, Eq)]
+pub struct DisputeMetricsBucketIdentifier {
+    pub connector: Option<String>,
+    pub dispute_stage: Option<DisputeStage>,
+    #[serde(rename = "time_range")]
+    pub time_bucket: TimeRange,
+    #[serde(rename = "time_bucket")]
+    #[serde(with = "common_utils::custom_serde::iso8601custom")]
+    pub start_time: time::PrimitiveDateTime,
+}
+
+impl Hash for DisputeMetricsBucketIdentifier {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.connector.hash(state);
+        self.dispute_stage.hash(state);
+        self.time_bucket.hash(state);
+    }
+}
+
+impl PartialEq for DisputeMetricsBucketIdentifier {
+    fn eq(&self, other: &Self) -> bool {
+        let mut left = DefaultHasher::new();
+        self.hash(&mut left);
+        let mut right = DefaultHasher::new();
+        other.hash(&mut right);
+        left.finish() == right.finish()
+    }
+}
+
+impl DisputeMetricsBucketIdentifier {
+    pub fn new(
+        connector: Option<String>,
+        dispute_stage: Option<DisputeStage>,
+        normalized_time_range: TimeRange,
+    ) -> Self {
+        Self {
+            connector,
+            dispute_stage,
+            time_bucket: normalized_time_range,
+            start_time: normalized_time_range.start_time,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct DisputeMetricsBucketValue {
+    pub dispute_win_rate: Option<f64>,
+    pub chargeback_ratio: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct DisputeMetricsBucketResponse {
+    #[serde(flatten)]
+    pub values: DisputeMetricsBucketValue,
+    #[serde(flatten)]
+    pub dimensions: DisputeMetricsBucketIdentifier,
+}
+
+impl ApiEventMetric for DisputeMetricsBucketResponse {}
+impl ApiEventMetric for MetricsResponse<DisputeMetricsBucketResponse> {}
@@ -1,9 +1,12 @@
 use std::{
-    collections::hash_map::DefaultHasher,
+    collections::{hash_map::DefaultHasher, HashMap},
     hash::{Hash, Hasher},
 };
 
-use common_enums::enums::{AttemptStatus, AuthenticationType, Currency, PaymentMethod};
+use common_enums::enums::{
+    AttemptStatus, AuthenticationType, CardNetwork, CountryAlpha2, Currency, PaymentMethod,
+    PaymentMethodType,
+};
 use common_utils::events::ApiEventMetric;
 
 use super::{NameDescription, TimeRange};
@@ -21,6 +24,66 @@ pub struct PaymentFilters {
     pub auth_type: Vec<AuthenticationType>,
     #[serde(default)]
     pub payment_method: Vec<PaymentMethod>,
+    #[serde(default)]
+    pub profile_id: Vec<String>,
+    #[serde(default)]
+    pub payment_method_type: Vec<PaymentMethodType>,
+    #[serde(default)]
+    pub card_network: Vec<CardNetwork>,
+    #[serde(default)]
+    pub day_of_week: Vec<u8>,
+    #[serde(default)]
+    pub hour_of_day: Vec<u8>,
+    #[serde(default)]
+    pub billing_country: Vec<CountryAlpha2>,
+    #[serde(default)]
+    pub card_bin: Vec<String>,
+    #[serde(default)]
+    pub issuer_country: Vec<String>,
+}
+
+/// Inputs of the payment metrics which can't be derived from the stored payments alone
+#[derive(Clone, Debug, Default, serde::Deserialize, masking::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentMetricParams {
+    #[serde(default)]
+    pub customer_segment_thresholds: CustomerSegmentThresholds,
+    #[serde(default)]
+    pub exchange_rates: ExchangeRates,
+}
+
+/// Lifetime value thresholds, in minor units, from which a customer falls in the medium and high
+/// value segments. Customers below `medium` are in the low value segment.
+#[derive(Clone, Copy, Debug, serde::Deserialize, masking::Serialize)]
+pub struct CustomerSegmentThresholds {
+    pub medium: u64,
+    pub high: u64,
+}
+
+impl Default for CustomerSegmentThresholds {
+    fn default() -> Self {
+        Self {
+            medium: 10_000,
+            high: 100_000,
+        }
+    }
+}
+
+/// Rates converting one major unit of a currency into major units of the base currency
+#[derive(Clone, Debug, serde::Deserialize, masking::Serialize)]
+pub struct ExchangeRates {
+    pub base: Currency,
+    #[serde(default)]
+    pub rates: Vec<(Currency, bigdecimal::BigDecimal)>,
+}
+
+impl Default for ExchangeRates {
+    fn default() -> Self {
+        Self {
+            base: Currency::USD,
+            rates: Vec::new(),
+        }
+    }
 }
 
 #[derive(
@@ -51,6 +114,14 @@ pub enum PaymentDimensions {
     #[strum(serialize = "status")]
     #[serde(rename = "status")]
     PaymentStatus,
+    ProfileId,
+    PaymentMethodType,
+    CardNetwork,
+    DayOfWeek,
+    HourOfDay,
+    BillingCountry,
+    CardBin,
+    IssuerCountry,
 }
 
 #[derive(
@@ -73,6 +144,43 @@ pub enum PaymentMetrics {
     PaymentSuccessCount,
     PaymentProcessedAmount,
     AvgTicketSize,
+    NewVsReturningCustomers,
+    AuthorizedCaptureFailureCount,
+    ConnectorLatencyP95,
+    RetryRatio,
+    WalletTypeBreakdown,
+    CardNetworkBreakdown,
+    SmartRetrySuccessRate,
+    AvgCaptureGap,
+    TopFailingConnectors,
+    PaymentFunnel,
+    AvgRetriesBeforeSuccess,
+    DailyGmv,
+    PaymentMethodSuccessRanking,
+    VolumeHeatmap,
+    RecoveryRate,
+    AvgValueByCustomerSegment,
+    DeclineCategoryBreakdown,
+    ExchangeAdjustedTicketSize,
+    LifecycleSpread,
+    SuccessRateByCountry,
+    RoutingFallbackDepth,
+    ZeroAmountCount,
+    TimeToFirstPayment,
+    SurchargeAdoptionRate,
+    EffectiveFeeRate,
+    ConnectorErrorRate,
+    MandateSuccessRate,
+    AvgDiscount,
+    PaymentMethodUsage,
+    AvgLineItems,
+    SuccessRateByBin,
+    AuthenticationLatency,
+    AovTrend,
+    CustomerLifetimeValue,
+    DeclineRecoverySplit,
+    PendingStateDuration,
+    ApprovalRateByIssuerCountry,
 }
 
 pub mod metric_behaviour {
@@ -81,6 +189,43 @@ pub mod metric_behaviour {
     pub struct PaymentSuccessCount;
     pub struct PaymentProcessedAmount;
     pub struct AvgTicketSize;
+    pub struct NewVsReturningCustomers;
+    pub struct AuthorizedCaptureFailureCount;
+    pub struct ConnectorLatencyP95;
+    pub struct RetryRatio;
+    pub struct WalletTypeBreakdown;
+    pub struct CardNetworkBreakdown;
+    pub struct SmartRetrySuccessRate;
+    pub struct AvgCaptureGap;
+    pub struct TopFailingConnectors;
+    pub struct PaymentFunnel;
+    pub struct AvgRetriesBeforeSuccess;
+    pub struct DailyGmv;
+    pub struct PaymentMethodSuccessRanking;
+    pub struct VolumeHeatmap;
+    pub struct RecoveryRate;
+    pub struct AvgValueByCustomerSegment;
+    pub struct DeclineCategoryBreakdown;
+    pub struct ExchangeAdjustedTicketSize;
+    pub struct LifecycleSpread;
+    pub struct SuccessRateByCountry;
+    pub struct RoutingFallbackDepth;
+    pub struct ZeroAmountCount;
+    pub struct TimeToFirstPayment;
+    pub struct SurchargeAdoptionRate;
+    pub struct EffectiveFeeRate;
+    pub struct ConnectorErrorRate;
+    pub struct MandateSuccessRate;
+    pub struct AvgDiscount;
+    pub struct PaymentMethodUsage;
+    pub struct AvgLineItems;
+    pub struct SuccessRateByBin;
+    pub struct AuthenticationLatency;
+    pub struct AovTrend;
+    pub struct CustomerLifetimeValue;
+    pub struct DeclineRecoverySplit;
+    pub struct PendingStateDuration;
+    pub struct ApprovalRateByIssuerCountry;
 }
 
 impl From<PaymentMetrics> for NameDescription {
@@ -109,6 +254,14 @@ pub struct PaymentMetricsBucketIdentifier {
     #[serde(rename = "authentication_type")]
     pub auth_type: Option<AuthenticationType>,
     pub payment_method: Option<String>,
+    pub profile_id: Option<String>,
+    pub payment_method_type: Option<String>,
+    pub card_network: Option<String>,
+    pub day_of_week: Option<u8>,
+    pub hour_of_day: Option<u8>,
+    pub billing_country: Option<CountryAlpha2>,
+    pub card_bin: Option<String>,
+    pub issuer_country: Option<String>,
     #[serde(rename = "time_range")]
     pub time_bucket: TimeRange,
     // Coz FE sucks
@@ -118,20 +271,23 @@ pub struct PaymentMetricsBucketIdentifier {
 }
 
 impl PaymentMetricsBucketIdentifier {
-    pub fn new(
-        currency: Option<Currency>,
-        status: Option<AttemptStatus>,
-        connector: Option<String>,
-        auth_type: Option<AuthenticationType>,
-        payment_method: Option<String>,
-        normalized_time_range: TimeRange,
-    ) -> Self {
+    /// Identifier of the bucket spanning `normalized_time_range`, with no dimension set.
+    /// Dimensions are set through struct update syntax on top of it.
+    pub fn new(normalized_time_range: TimeRange) -> Self {
         Self {
-            currency,
-            status,
-            connector,
-            auth_type,
-            payment_method,
+            currency: None,
+            status: None,
+            connector: None,
+            auth_type: None,
+            payment_method: None,
+            profile_id: None,
+            payment_method_type: None,
+            card_network: None,
+            day_of_week: None,
+            hour_of_day: None,
+            billing_country: None,
+            card_bin: None,
+            issuer_country: None,
             time_bucket: normalized_time_range,
             start_time: normalized_time_range.start_time,
         }
@@ -145,6 +301,14 @@ impl Hash for PaymentMetricsBucketIdentifier {
         self.connector.hash(state);
         self.auth_type.map(|i| i.to_string()).hash(state);
         self.payment_method.hash(state);
+        self.profile_id.hash(state);
+        self.payment_method_type.hash(state);
+        self.card_network.hash(state);
+        self.day_of_week.hash(state);
+        self.hour_of_day.hash(state);
+        self.billing_country.hash(state);
+        self.card_bin.hash(state);
+        self.issuer_country.hash(state);
         self.time_bucket.hash(state);
     }
 }
@@ -165,7 +329,65 @@ pub struct PaymentMetricsBucketValue {
     pub payment_count: Option<u64>,
     pub payment_success_count: Option<u64>,
     pub payment_processed_amount: Option<u64>,
+    pub payment_processed_amount_in_major_unit: Option<f64>,
     pub avg_ticket_size: Option<f64>,
+    pub new_customer_count: Option<u64>,
+    pub returning_customer_count: Option<u64>,
+    pub authorized_capture_failure_count: Option<u64>,
+    pub connector_latency_p95: Option<f64>,
+    pub retry_ratio: Option<f64>,
+    pub wallet_payment_count: Option<u64>,
+    pub card_network_payment_count: Option<u64>,
+    pub smart_retry_success_rate: Option<f64>,
+    pub avg_capture_gap: Option<f64>,
+    pub connector_failure_count: Option<u64>,
+    pub connector_failure_rank: Option<u64>,
+    pub funnel_created_count: Option<u64>,
+    pub funnel_authenticated_count: Option<u64>,
+    pub funnel_authorized_count: Option<u64>,
+    pub funnel_captured_count: Option<u64>,
+    pub avg_retries_before_success: Option<f64>,
+    pub daily_gmv: Option<u64>,
+    pub payment_method_success_rate: Option<f64>,
+    pub heatmap_payment_count: Option<u64>,
+    pub payment_recovery_rate: Option<f64>,
+    pub avg_value_low_segment: Option<f64>,
+    pub avg_value_medium_segment: Option<f64>,
+    pub avg_value_high_segment: Option<f64>,
+    pub decline_category_count: Option<HashMap<String, u64>>,
+    pub avg_ticket_size_in_base_currency: Option<f64>,
+    pub created_to_authorized_min_seconds: Option<f64>,
+    pub created_to_authorized_max_seconds: Option<f64>,
+    pub created_to_authorized_avg_seconds: Option<f64>,
+    pub created_to_captured_min_seconds: Option<f64>,
+    pub created_to_captured_max_seconds: Option<f64>,
+    pub created_to_captured_avg_seconds: Option<f64>,
+    pub billing_country_success_rate: Option<f64>,
+    pub avg_connectors_per_routing_rule: Option<HashMap<String, f64>>,
+    pub zero_amount_count: Option<u64>,
+    pub non_zero_amount_count: Option<u64>,
+    pub avg_time_to_first_payment_seconds: Option<f64>,
+    pub surcharge_adoption_rate: Option<f64>,
+    pub effective_fee_rate: Option<f64>,
+    pub connector_error_rate: Option<f64>,
+    pub mandate_success_rate: Option<f64>,
+    pub avg_discount_amount: Option<f64>,
+    pub discounted_volume: Option<u64>,
+    pub payment_method_offered_count: Option<u64>,
+    pub payment_method_used_count: Option<u64>,
+    pub payment_method_usage_rate: Option<f64>,
+    pub avg_line_items_per_payment: Option<f64>,
+    pub card_bin_success_rate: Option<f64>,
+    pub challenged_authorization_latency_avg_seconds: Option<f64>,
+    pub frictionless_authorization_latency_avg_seconds: Option<f64>,
+    pub avg_order_value: Option<f64>,
+    pub avg_order_value_change_percent: Option<f64>,
+    pub cohort_customer_count: Option<u64>,
+    pub avg_customer_lifetime_value: Option<f64>,
+    pub declined_retried_count: Option<HashMap<String, u64>>,
+    pub declined_abandoned_count: Option<HashMap<String, u64>>,
+    pub avg_pending_state_duration_seconds: Option<f64>,
+    pub issuer_country_approval_rate: Option<f64>,
 }
 
 #[derive(Debug, serde::Serialize)]
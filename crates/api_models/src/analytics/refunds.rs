@@ -41,6 +41,8 @@ pub struct RefundFilters {
     pub connector: Vec<String>,
     #[serde(default)]
     pub refund_type: Vec<RefundType>,
+    #[serde(default)]
+    pub refund_reason: Vec<String>,
 }
 
 #[derive(
@@ -64,6 +66,7 @@ pub enum RefundDimensions {
     RefundStatus,
     Connector,
     RefundType,
+    RefundReason,
 }
 
 #[derive(
@@ -85,6 +88,9 @@ pub enum RefundMetrics {
     RefundCount,
     RefundSuccessCount,
     RefundProcessedAmount,
+    RefundAmountRatio,
+    RefundSettlementPercentiles,
+    RefundReasonDistribution,
 }
 
 pub mod metric_behaviour {
@@ -92,6 +98,9 @@ pub mod metric_behaviour {
     pub struct RefundCount;
     pub struct RefundSuccessCount;
     pub struct RefundProcessedAmount;
+    pub struct RefundAmountRatio;
+    pub struct RefundSettlementPercentiles;
+    pub struct RefundReasonDistribution;
 }
 
 impl From<RefundMetrics> for NameDescription {
@@ -118,6 +127,7 @@ pub struct RefundMetricsBucketIdentifier {
     pub refund_status: Option<RefundStatus>,
     pub connector: Option<String>,
     pub refund_type: Option<String>,
+    pub refund_reason: Option<String>,
     #[serde(rename = "time_range")]
     pub time_bucket: TimeRange,
     #[serde(rename = "time_bucket")]
@@ -131,6 +141,7 @@ impl Hash for RefundMetricsBucketIdentifier {
         self.refund_status.map(|i| i.to_string()).hash(state);
         self.connector.hash(state);
         self.refund_type.hash(state);
+        self.refund_reason.hash(state);
         self.time_bucket.hash(state);
     }
 }
@@ -150,6 +161,7 @@ impl RefundMetricsBucketIdentifier {
         refund_status: Option<RefundStatus>,
         connector: Option<String>,
         refund_type: Option<String>,
+        refund_reason: Option<String>,
         normalized_time_range: TimeRange,
     ) -> Self {
         Self {
@@ -157,6 +169,7 @@ impl RefundMetricsBucketIdentifier {
             refund_status,
             connector,
             refund_type,
+            refund_reason,
             time_bucket: normalized_time_range,
             start_time: normalized_time_range.start_time,
         }
@@ -169,6 +182,12 @@ pub struct RefundMetricsBucketValue {
     pub refund_count: Option<u64>,
     pub refund_success_count: Option<u64>,
     pub refund_processed_amount: Option<u64>,
+    pub refund_amount_ratio: Option<f64>,
+    pub refund_settlement_p50_seconds: Option<f64>,
+    pub refund_settlement_p90_seconds: Option<f64>,
+    pub refund_settlement_p99_seconds: Option<f64>,
+    pub refund_reason_count: Option<u64>,
+    pub refund_reason_share: Option<f64>,
 }
 
 #[derive(Debug, serde::Serialize)]
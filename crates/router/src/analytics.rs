@@ -1,4 +1,5 @@
 mod core;
+mod disputes;
 mod errors;
 pub mod metrics;
 mod payments;
@@ -11,14 +12,16 @@ mod types;
 mod utils;
 
 use api_models::analytics::{
-    payments::{PaymentDimensions, PaymentFilters, PaymentMetrics, PaymentMetricsBucketIdentifier},
+    disputes::{DisputeDimensions, DisputeFilters, DisputeMetrics, DisputeMetricsBucketIdentifier},
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
     refunds::{RefundDimensions, RefundFilters, RefundMetrics, RefundMetricsBucketIdentifier},
     Granularity, TimeRange,
 };
 use router_env::{instrument, tracing};
 
 use self::{
-    payments::metrics::{PaymentMetric, PaymentMetricRow},
+    disputes::metrics::{DisputeMetric, DisputeMetricRow},
+    payments::metrics::{PaymentMetric, PaymentMetricRow, RequestedPaymentMetric},
     refunds::metrics::{RefundMetric, RefundMetricRow},
     sqlx::SqlxClient,
 };
@@ -39,7 +42,7 @@ impl AnalyticsProvider {
     #[instrument(skip_all)]
     pub async fn get_payment_metrics(
         &self,
-        metric: &PaymentMetrics,
+        metric: &RequestedPaymentMetric<'_>,
         dimensions: &[PaymentDimensions],
         merchant_id: &str,
         filters: &PaymentFilters,
@@ -65,7 +68,7 @@ impl AnalyticsProvider {
                 }
             },
             &metrics::METRIC_FETCH_TIME,
-            metric,
+            metric.metric,
             self,
         )
         .await
@@ -96,14 +99,45 @@ impl AnalyticsProvider {
         }
     }
 
+    pub async fn get_dispute_metrics(
+        &self,
+        metric: &DisputeMetrics,
+        dimensions: &[DisputeDimensions],
+        merchant_id: &str,
+        filters: &DisputeFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+    ) -> types::MetricsResult<Vec<(DisputeMetricsBucketIdentifier, DisputeMetricRow)>> {
+        match self {
+            Self::Sqlx(pool) => {
+                metric
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+        }
+    }
+
     pub async fn from_conf(
         config: &AnalyticsConfig,
         #[cfg(feature = "kms")] kms_client: &external_services::kms::KmsClient,
     ) -> Self {
         match config {
-            AnalyticsConfig::Sqlx { sqlx } => Self::Sqlx(
+            AnalyticsConfig::Sqlx {
+                sqlx,
+                storage_timezone,
+                schema,
+            } => Self::Sqlx(
                 SqlxClient::from_conf(
                     sqlx,
+                    *storage_timezone,
+                    schema.clone(),
                     #[cfg(feature = "kms")]
                     kms_client,
                 )
@@ -117,13 +151,21 @@ impl AnalyticsProvider {
 #[serde(tag = "source")]
 #[serde(rename_all = "lowercase")]
 pub enum AnalyticsConfig {
-    Sqlx { sqlx: Database },
+    Sqlx {
+        sqlx: Database,
+        #[serde(default)]
+        storage_timezone: types::StorageTimezone,
+        #[serde(default)]
+        schema: Option<types::StorageSchema>,
+    },
 }
 
 impl Default for AnalyticsConfig {
     fn default() -> Self {
         Self::Sqlx {
             sqlx: Database::default(),
+            storage_timezone: Default::default(),
+            schema: None,
         }
     }
 }
@@ -28,6 +28,11 @@ pub async fn get_domain_info(domain: AnalyticsDomain) -> AnalyticsApiResponse<Ge
             download_dimensions: None,
             dimensions: utils::get_refund_dimensions(),
         },
+        AnalyticsDomain::Disputes => GetInfoResponse {
+            metrics: utils::get_dispute_metrics_info(),
+            download_dimensions: None,
+            dimensions: utils::get_dispute_dimensions(),
+        },
     };
     Ok(ApplicationResponse::Json(info))
 }
@@ -54,6 +59,16 @@ pub async fn payment_filters_core(
             PaymentDimensions::Connector => fil.connector,
             PaymentDimensions::AuthType => fil.authentication_type.map(|i| i.as_ref().to_string()),
             PaymentDimensions::PaymentMethod => fil.payment_method,
+            PaymentDimensions::ProfileId => fil.profile_id,
+            PaymentDimensions::PaymentMethodType => fil.payment_method_type,
+            PaymentDimensions::CardNetwork => fil.card_network,
+            PaymentDimensions::DayOfWeek => fil.day_of_week.map(|d| d.to_string()),
+            PaymentDimensions::HourOfDay => fil.hour_of_day.map(|h| h.to_string()),
+            PaymentDimensions::BillingCountry => {
+                fil.billing_country.map(|i| i.as_ref().to_string())
+            }
+            PaymentDimensions::CardBin => fil.card_bin,
+            PaymentDimensions::IssuerCountry => fil.issuer_country,
         })
         .collect::<Vec<String>>();
         res.query_data.push(FilterValue {
@@ -85,6 +100,7 @@ pub async fn refund_filter_core(
             RefundDimensions::RefundStatus => fil.refund_status.map(|i| i.as_ref().to_string()),
             RefundDimensions::Connector => fil.connector,
             RefundDimensions::RefundType => fil.refund_type.map(|i| i.as_ref().to_string()),
+            RefundDimensions::RefundReason => fil.refund_reason,
         })
         .collect::<Vec<String>>();
         res.query_data.push(RefundFilterValue {
@@ -0,0 +1,9 @@
+pub mod accumulator;
+mod core;
+
+pub mod metrics;
+pub mod types;
+pub use accumulator::{DisputeMetricAccumulator, DisputeMetricsAccumulator};
+
+pub trait DisputeAnalytics: metrics::DisputeMetricAnalytics {}
+pub use self::core::get_metrics;
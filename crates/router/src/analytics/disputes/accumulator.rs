@@ -0,0 +1,67 @@
+use api_models::analytics::disputes::DisputeMetricsBucketValue;
+
+use super::metrics::DisputeMetricRow;
+use crate::analytics::utils::percentage;
+
+#[derive(Debug, Default)]
+pub struct DisputeMetricsAccumulator {
+    pub dispute_win_rate: WinRateAccumulator,
+    pub chargeback_ratio: ChargebackRatioAccumulator,
+}
+
+/// Share of the decided disputes which were won, disputes still open being left out
+#[derive(Debug, Default)]
+pub struct WinRateAccumulator {
+    pub won: i64,
+    pub lost: i64,
+}
+
+/// Share of the sales which were charged back
+#[derive(Debug, Default)]
+pub struct ChargebackRatioAccumulator {
+    pub chargebacks: i64,
+    pub sales: i64,
+}
+
+pub trait DisputeMetricAccumulator {
+    type MetricOutput;
+
+    fn add_metrics_bucket(&mut self, metrics: &DisputeMetricRow);
+
+    fn collect(self) -> Self::MetricOutput;
+}
+
+impl DisputeMetricAccumulator for WinRateAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &DisputeMetricRow) {
+        self.won += metrics.won_count.unwrap_or_default();
+        self.lost += metrics.lost_count.unwrap_or_default();
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        percentage(self.won, self.won + self.lost)
+    }
+}
+
+impl DisputeMetricAccumulator for ChargebackRatioAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &DisputeMetricRow) {
+        self.chargebacks += metrics.chargeback_count.unwrap_or_default();
+        self.sales += metrics.sales_count.unwrap_or_default();
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        percentage(self.chargebacks, self.sales)
+    }
+}
+
+impl DisputeMetricsAccumulator {
+    pub fn collect(self) -> DisputeMetricsBucketValue {
+        DisputeMetricsBucketValue {
+            dispute_win_rate: self.dispute_win_rate.collect(),
+            chargeback_ratio: self.chargeback_ratio.collect(),
+        }
+    }
+}
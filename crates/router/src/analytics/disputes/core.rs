@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use api_models::analytics::{
+    disputes::{DisputeMetrics, DisputeMetricsBucketIdentifier, DisputeMetricsBucketResponse},
+    AnalyticsMetadata, GetDisputeMetricRequest, MetricsResponse,
+};
+use error_stack::{IntoReport, ResultExt};
+use router_env::{
+    logger,
+    tracing::{self, Instrument},
+};
+
+use super::DisputeMetricsAccumulator;
+use crate::{
+    analytics::{
+        core::AnalyticsApiResponse, disputes::DisputeMetricAccumulator, errors::AnalyticsError,
+        query::resolve_granularity, AnalyticsProvider,
+    },
+    services::ApplicationResponse,
+    types::domain,
+};
+
+pub async fn get_metrics(
+    pool: AnalyticsProvider,
+    merchant_account: domain::MerchantAccount,
+    req: GetDisputeMetricRequest,
+) -> AnalyticsApiResponse<MetricsResponse<DisputeMetricsBucketResponse>> {
+    let mut metrics_accumulator: HashMap<
+        DisputeMetricsBucketIdentifier,
+        DisputeMetricsAccumulator,
+    > = HashMap::new();
+    let mut set = tokio::task::JoinSet::new();
+    for metric_type in req.metrics.iter().cloned() {
+        let req = req.clone();
+        let merchant_id = merchant_account.merchant_id.clone();
+        let pool = pool.clone();
+        let task_span = tracing::debug_span!(
+            "analytics_dispute_query",
+            dispute_metric = metric_type.as_ref()
+        );
+        set.spawn(
+            async move {
+                let data = pool
+                    .get_dispute_metrics(
+                        &metric_type,
+                        &req.group_by_names.clone(),
+                        &merchant_id,
+                        &req.filters,
+                        &resolve_granularity(&req.time_series, &req.time_range),
+                        &req.time_range,
+                    )
+                    .await
+                    .change_context(AnalyticsError::UnknownError);
+                (metric_type, data)
+            }
+            .instrument(task_span),
+        );
+    }
+
+    while let Some((metric, data)) = set
+        .join_next()
+        .await
+        .transpose()
+        .into_report()
+        .change_context(AnalyticsError::UnknownError)?
+    {
+        for (id, value) in data? {
+            logger::debug!(bucket_id=?id, bucket_value=?value, "Bucket row for metric {metric}");
+            let metrics_builder = metrics_accumulator.entry(id).or_default();
+            match metric {
+                DisputeMetrics::DisputeWinRate => {
+                    metrics_builder.dispute_win_rate.add_metrics_bucket(&value)
+                }
+                DisputeMetrics::ChargebackRatio => {
+                    metrics_builder.chargeback_ratio.add_metrics_bucket(&value)
+                }
+            }
+        }
+
+        logger::debug!(
+            "Analytics Accumulated Results: metric: {}, results: {:#?}",
+            metric,
+            metrics_accumulator
+        );
+    }
+    let query_data: Vec<DisputeMetricsBucketResponse> = metrics_accumulator
+        .into_iter()
+        .map(|(id, val)| DisputeMetricsBucketResponse {
+            values: val.collect(),
+            dimensions: id,
+        })
+        .collect();
+
+    Ok(ApplicationResponse::Json(MetricsResponse {
+        query_data,
+        meta_data: [AnalyticsMetadata {
+            current_time_range: req.time_range,
+        }],
+    }))
+}
@@ -0,0 +1,188 @@
+use api_models::analytics::{
+    disputes::{DisputeDimensions, DisputeFilters, DisputeMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::DisputeMetricRow;
+use crate::analytics::{
+    query::{
+        month_of, storage_time_to_utc, Aggregate, BaseFilters, GroupByClause, PostProcessingError,
+        QueryBuilder, QueryResult, ToSql,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsError, MetricsResult},
+};
+
+/// Sales are the attempts which were charged
+const SALE_CONDITION: &str = "status = 'charged'";
+
+/// Attempts a chargeback was raised against, see [`AnalyticsCollection::PaymentWithChargeback`]
+const CHARGEBACK_CONDITION: &str = "charged_back";
+
+/// Card network thresholds apply to calendar months, the ratio is always grouped by the month of
+/// the sales
+const SALE_MONTH: &str = "date_trunc('month', created_at)";
+
+/// Selects the number of sales as `sales_count` and of those charged back as `chargeback_count`
+fn add_chargeback_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    builder
+        .add_select_column(Aggregate::CountIf {
+            condition: SALE_CONDITION,
+            alias: Some("sales_count"),
+        })
+        .attach_printable("Error adding sales count")?;
+    builder
+        .add_select_column(Aggregate::CountIf {
+            condition: CHARGEBACK_CONDITION,
+            alias: Some("chargeback_count"),
+        })
+        .attach_printable("Error adding chargeback count")
+}
+
+#[derive(Default)]
+pub(super) struct ChargebackRatio {}
+
+#[async_trait::async_trait]
+impl<T> super::DisputeMetric<T> for ChargebackRatio
+where
+    T: AnalyticsDataSource + super::DisputeMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        _dimensions: &[DisputeDimensions],
+        merchant_id: &str,
+        filters: &DisputeFilters,
+        _granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(DisputeMetricsBucketIdentifier, DisputeMetricRow)>>
+    where
+        T: AnalyticsDataSource + super::DisputeMetricAnalytics,
+    {
+        // Sales only exist as payment attempts, which are joined with their chargebacks. The ratio
+        // is always per connector and month, the other dimensions and the dispute stage filter
+        // don't apply to sales.
+        let mut query_builder = QueryBuilder::new(AnalyticsCollection::PaymentWithChargeback);
+        query_builder.set_storage_timezone(pool.storage_timezone());
+
+        query_builder
+            .add_select_column(DisputeDimensions::Connector)
+            .switch()?;
+        add_chargeback_columns(&mut query_builder).switch()?;
+        query_builder
+            .add_select_column(Aggregate::Min {
+                field: "created_at",
+                alias: Some("start_bucket"),
+            })
+            .switch()?;
+        query_builder
+            .add_select_column(Aggregate::Max {
+                field: "created_at",
+                alias: Some("end_bucket"),
+            })
+            .switch()?;
+
+        if !filters.connector.is_empty() {
+            query_builder
+                .add_filter_in_range_clause(DisputeDimensions::Connector, &filters.connector)
+                .switch()?;
+        }
+
+        BaseFilters {
+            merchant_id,
+            time_range,
+        }
+        .apply_to(&mut query_builder)
+        .switch()?;
+
+        query_builder
+            .add_group_by_clause(DisputeDimensions::Connector)
+            .switch()?;
+        query_builder.add_group_by_clause(SALE_MONTH).switch()?;
+
+        query_builder
+            .execute_query::<DisputeMetricRow, _>(pool)
+            .await
+            .change_context(MetricsError::QueryBuildingError)?
+            .change_context(MetricsError::QueryExecutionFailure)?
+            .into_iter()
+            .map(|i| {
+                Ok((
+                    DisputeMetricsBucketIdentifier::new(
+                        i.connector.clone(),
+                        i.dispute_stage.as_ref().map(|i| i.0),
+                        match i.start_bucket {
+                            Some(st) => {
+                                let (start, end) = month_of(st)?;
+                                TimeRange {
+                                    start_time: storage_time_to_utc(
+                                        start,
+                                        pool.storage_timezone(),
+                                    )?,
+                                    end_time: Some(storage_time_to_utc(
+                                        end,
+                                        pool.storage_timezone(),
+                                    )?),
+                                }
+                            }
+                            None => *time_range,
+                        },
+                    ),
+                    i,
+                ))
+            })
+            .collect::<error_stack::Result<
+                Vec<(DisputeMetricsBucketIdentifier, DisputeMetricRow)>,
+                PostProcessingError,
+            >>()
+            .change_context(MetricsError::PostProcessingFailure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_chargeback_ratio_query() {
+        let mut builder: QueryBuilder<SqlxClient> =
+            QueryBuilder::new(AnalyticsCollection::PaymentWithChargeback);
+        builder
+            .add_select_column(DisputeDimensions::Connector)
+            .unwrap();
+        add_chargeback_columns(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(DisputeDimensions::Connector)
+            .unwrap();
+        builder.add_group_by_clause(SALE_MONTH).unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, \
+             count(*) FILTER (WHERE status = 'charged') as sales_count, \
+             count(*) FILTER (WHERE charged_back) as chargeback_count \
+             FROM (SELECT payment_attempt.merchant_id, payment_attempt.connector, \
+             payment_attempt.status, payment_attempt.created_at, payment_attempt.modified_at, \
+             chargeback.attempt_id IS NOT NULL AS charged_back FROM payment_attempt \
+             LEFT JOIN (SELECT DISTINCT merchant_id, attempt_id FROM dispute \
+             WHERE dispute_stage = 'dispute') AS chargeback \
+             ON chargeback.attempt_id = payment_attempt.attempt_id \
+             AND chargeback.merchant_id = payment_attempt.merchant_id) \
+             AS payment_with_chargeback \
+             GROUP BY connector, date_trunc('month', created_at)"
+        );
+    }
+}
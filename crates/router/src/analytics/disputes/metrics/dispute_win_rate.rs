@@ -0,0 +1,179 @@
+use api_models::analytics::{
+    disputes::{DisputeDimensions, DisputeFilters, DisputeMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::DisputeMetricRow;
+use crate::analytics::{
+    query::{
+        storage_time_to_utc, Aggregate, BaseFilters, GroupByClause, QueryBuilder, QueryFilter,
+        QueryResult, SeriesBucket, ToSql,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsError, MetricsResult},
+};
+
+const WON_CONDITION: &str = "dispute_status = 'dispute_won'";
+const LOST_CONDITION: &str = "dispute_status = 'dispute_lost'";
+
+/// Selects the number of won disputes as `won_count` and of lost ones as `lost_count`
+fn add_outcome_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    builder
+        .add_select_column(Aggregate::CountIf {
+            condition: WON_CONDITION,
+            alias: Some("won_count"),
+        })
+        .attach_printable("Error adding won dispute count")?;
+    builder
+        .add_select_column(Aggregate::CountIf {
+            condition: LOST_CONDITION,
+            alias: Some("lost_count"),
+        })
+        .attach_printable("Error adding lost dispute count")
+}
+
+#[derive(Default)]
+pub(super) struct DisputeWinRate {}
+
+#[async_trait::async_trait]
+impl<T> super::DisputeMetric<T> for DisputeWinRate
+where
+    T: AnalyticsDataSource + super::DisputeMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[DisputeDimensions],
+        merchant_id: &str,
+        filters: &DisputeFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(DisputeMetricsBucketIdentifier, DisputeMetricRow)>>
+    where
+        T: AnalyticsDataSource + super::DisputeMetricAnalytics,
+    {
+        let mut query_builder = QueryBuilder::new(AnalyticsCollection::Dispute);
+        query_builder.set_storage_timezone(pool.storage_timezone());
+        let mut dimensions = dimensions.to_vec();
+
+        if !dimensions.contains(&DisputeDimensions::Connector) {
+            dimensions.push(DisputeDimensions::Connector);
+        }
+
+        for dim in dimensions.iter() {
+            query_builder.add_select_column(dim).switch()?;
+        }
+
+        add_outcome_columns(&mut query_builder).switch()?;
+        query_builder
+            .add_select_column(Aggregate::Min {
+                field: "created_at",
+                alias: Some("start_bucket"),
+            })
+            .switch()?;
+        query_builder
+            .add_select_column(Aggregate::Max {
+                field: "created_at",
+                alias: Some("end_bucket"),
+            })
+            .switch()?;
+
+        filters.set_filter_clause(&mut query_builder).switch()?;
+
+        BaseFilters {
+            merchant_id,
+            time_range,
+        }
+        .apply_to(&mut query_builder)
+        .switch()?;
+
+        for dim in dimensions.iter() {
+            query_builder.add_group_by_clause(dim).switch()?;
+        }
+
+        if let Some(granularity) = granularity.as_ref() {
+            granularity
+                .set_group_by_clause(&mut query_builder)
+                .switch()?;
+        }
+
+        query_builder
+            .execute_query::<DisputeMetricRow, _>(pool)
+            .await
+            .change_context(MetricsError::QueryBuildingError)?
+            .change_context(MetricsError::QueryExecutionFailure)?
+            .into_iter()
+            .map(|i| {
+                Ok((
+                    DisputeMetricsBucketIdentifier::new(
+                        i.connector.clone(),
+                        i.dispute_stage.as_ref().map(|i| i.0),
+                        TimeRange {
+                            start_time: match (granularity, i.start_bucket) {
+                                (Some(g), Some(st)) => g.clip_to_start(storage_time_to_utc(
+                                    st,
+                                    pool.storage_timezone(),
+                                )?)?,
+                                _ => time_range.start_time,
+                            },
+                            end_time: granularity.as_ref().map_or_else(
+                                || Ok(time_range.end_time),
+                                |g| {
+                                    i.end_bucket
+                                        .map(|et| {
+                                            storage_time_to_utc(et, pool.storage_timezone())
+                                                .and_then(|et| g.clip_to_end(et))
+                                        })
+                                        .transpose()
+                                },
+                            )?,
+                        },
+                    ),
+                    i,
+                ))
+            })
+            .collect::<error_stack::Result<
+                Vec<(DisputeMetricsBucketIdentifier, DisputeMetricRow)>,
+                crate::analytics::query::PostProcessingError,
+            >>()
+            .change_context(MetricsError::PostProcessingFailure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_dispute_win_rate_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Dispute);
+        builder
+            .add_select_column(DisputeDimensions::Connector)
+            .unwrap();
+        add_outcome_columns(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(DisputeDimensions::Connector)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, \
+             count(*) FILTER (WHERE dispute_status = 'dispute_won') as won_count, \
+             count(*) FILTER (WHERE dispute_status = 'dispute_lost') as lost_count \
+             FROM dispute GROUP BY connector"
+        );
+    }
+}
@@ -1,16 +1,58 @@
-use api_models::analytics::payments::PaymentMetricsBucketValue;
+use std::collections::{HashMap, HashSet};
+
+use api_models::analytics::payments::{
+    PaymentMetrics, PaymentMetricsBucketIdentifier, PaymentMetricsBucketValue,
+};
 use common_enums::enums as storage_enums;
 use router_env::logger;
 
 use super::metrics::PaymentMetricRow;
+use crate::analytics::utils::{currency_decimal_places, percentage};
 
 #[derive(Debug, Default)]
 pub struct PaymentMetricsAccumulator {
     pub payment_success_rate: SuccessRateAccumulator,
     pub payment_count: CountAccumulator,
     pub payment_success: CountAccumulator,
-    pub processed_amount: SumAccumulator,
+    pub processed_amount: ProcessedAmountAccumulator,
     pub avg_ticket_size: AverageAccumulator,
+    pub new_vs_returning_customers: CustomerTypeAccumulator,
+    pub authorized_capture_failure: CountAccumulator,
+    pub connector_latency_p95: PercentileAccumulator,
+    pub retry_ratio: RetryRatioAccumulator,
+    pub wallet_type_breakdown: CountAccumulator,
+    pub card_network_breakdown: CountAccumulator,
+    pub smart_retry_success_rate: SuccessRateAccumulator,
+    pub avg_capture_gap: AverageAccumulator,
+    pub top_failing_connectors: FailureRankAccumulator,
+    pub payment_funnel: FunnelAccumulator,
+    pub avg_retries_before_success: RetriesBeforeSuccessAccumulator,
+    pub daily_gmv: SumAccumulator,
+    pub payment_method_success_ranking: RankedSuccessRateAccumulator,
+    pub volume_heatmap: CountAccumulator,
+    pub recovery_rate: RecoveryRateAccumulator,
+    pub avg_value_by_customer_segment: CustomerSegmentAccumulator,
+    pub decline_category_breakdown: DeclineCategoryAccumulator,
+    pub exchange_adjusted_ticket_size: ExchangeAdjustedAverageAccumulator,
+    pub lifecycle_spread: LifecycleSpreadAccumulator,
+    pub billing_country_success_rate: RankedSuccessRateAccumulator,
+    pub routing_fallback_depth: RoutingFallbackDepthAccumulator,
+    pub zero_amount_count: ZeroAmountAccumulator,
+    pub time_to_first_payment: TimeToFirstPaymentAccumulator,
+    pub surcharge_adoption_rate: SurchargeAdoptionAccumulator,
+    pub effective_fee_rate: EffectiveFeeRateAccumulator,
+    pub connector_error_rate: ConnectorErrorRateAccumulator,
+    pub mandate_success_rate: RankedSuccessRateAccumulator,
+    pub avg_discount: DiscountAccumulator,
+    pub payment_method_usage: PaymentMethodUsageAccumulator,
+    pub avg_line_items: AverageAccumulator,
+    pub card_bin_success_rate: RankedSuccessRateAccumulator,
+    pub authentication_latency: AuthenticationLatencyAccumulator,
+    pub aov_trend: AovTrendAccumulator,
+    pub customer_lifetime_value: CohortLifetimeValueAccumulator,
+    pub decline_recovery_split: DeclineRecoveryAccumulator,
+    pub pending_state_duration: PendingStateDurationAccumulator,
+    pub issuer_country_approval_rate: RankedSuccessRateAccumulator,
 }
 
 #[derive(Debug, Default)]
@@ -31,12 +73,231 @@ pub struct SumAccumulator {
     pub total: Option<i64>,
 }
 
+/// Sums amounts in minor units, and additionally converts the sum to major units if all rows
+/// belong to the same currency
+#[derive(Debug, Default)]
+pub struct ProcessedAmountAccumulator {
+    pub total: SumAccumulator,
+    pub currencies: HashSet<Option<storage_enums::Currency>>,
+}
+
 #[derive(Debug, Default)]
 pub struct AverageAccumulator {
     pub total: u32,
     pub count: u32,
 }
 
+#[derive(Debug, Default)]
+pub struct CustomerTypeAccumulator {
+    pub new: Option<i64>,
+    pub returning: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+#[repr(transparent)]
+pub struct PercentileAccumulator {
+    pub value: Option<f64>,
+}
+
+/// Averages the number of attempts made until a payment succeeded, reported as the number of
+/// retries, i.e. excluding the successful attempt itself
+#[derive(Debug, Default)]
+#[repr(transparent)]
+pub struct RetriesBeforeSuccessAccumulator {
+    pub attempts: AverageAccumulator,
+}
+
+/// Success rate over rows which carry both the attempt count and the successful attempt count,
+/// instead of one row per status
+#[derive(Debug, Default)]
+pub struct RankedSuccessRateAccumulator {
+    pub success: i64,
+    pub total: i64,
+}
+
+/// Attempts split between zero amount authorizations and attempts moving funds
+#[derive(Debug, Default)]
+pub struct ZeroAmountAccumulator {
+    pub zero_amount: i64,
+    pub total: i64,
+}
+
+/// Average time new merchants took to make their first payment, weighted by the number of
+/// merchants of every bucket
+#[derive(Debug, Default)]
+pub struct TimeToFirstPaymentAccumulator {
+    pub weighted_total: f64,
+    pub count: u32,
+}
+
+/// Share of attempts on which a surcharge was applied
+#[derive(Debug, Default)]
+pub struct SurchargeAdoptionAccumulator {
+    pub surcharged: i64,
+    pub total: i64,
+}
+
+/// Fees and amounts of the buckets which processed any amount
+#[derive(Debug, Default)]
+pub struct EffectiveFeeRateAccumulator {
+    pub fee_total: bigdecimal::BigDecimal,
+    pub total: bigdecimal::BigDecimal,
+}
+
+/// Share of attempts which failed for technical reasons
+#[derive(Debug, Default)]
+pub struct ConnectorErrorRateAccumulator {
+    pub errors: i64,
+    pub total: i64,
+}
+
+/// Discounts applied to attempts and the amounts of the discounted attempts
+#[derive(Debug, Default)]
+pub struct DiscountAccumulator {
+    pub discount_total: bigdecimal::BigDecimal,
+    pub volume: i64,
+    pub count: i64,
+}
+
+/// Average order value of the bucket and its change from the previous bucket with the same
+/// dimensions, in percent
+#[derive(Debug, Default)]
+pub struct AovTrendAccumulator {
+    pub current: ExchangeAdjustedAverageAccumulator,
+    pub previous: ExchangeAdjustedAverageAccumulator,
+}
+
+/// Payments a payment method type was offered to and attempts made with it
+#[derive(Debug, Default)]
+pub struct PaymentMethodUsageAccumulator {
+    pub offered: i64,
+    pub used: i64,
+}
+
+/// Share of initially failed payments which were charged on a later attempt
+#[derive(Debug, Default)]
+pub struct RecoveryRateAccumulator {
+    pub recovered: i64,
+    pub failed: i64,
+}
+
+/// Average charged amount per customer value segment
+#[derive(Debug, Default)]
+pub struct CustomerSegmentAccumulator {
+    pub low: AverageAccumulator,
+    pub medium: AverageAccumulator,
+    pub high: AverageAccumulator,
+}
+
+/// Number of declined attempts per decline category
+#[derive(Debug, Default)]
+pub struct DeclineCategoryAccumulator {
+    pub counts: HashMap<String, i64>,
+}
+
+/// Declined payments which were retried afterwards and the ones abandoned, per decline category
+#[derive(Debug, Default)]
+pub struct DeclineRecoveryAccumulator {
+    pub retried: DeclineCategoryAccumulator,
+    pub abandoned: DeclineCategoryAccumulator,
+}
+
+/// Average number of connectors tried per payment, for every primary routing rule
+#[derive(Debug, Default)]
+pub struct RoutingFallbackDepthAccumulator {
+    pub depths: HashMap<String, AverageAccumulator>,
+}
+
+/// Average of amounts already converted to a common currency, which are no longer integers
+#[derive(Debug, Default)]
+pub struct ExchangeAdjustedAverageAccumulator {
+    pub total: bigdecimal::BigDecimal,
+    pub count: i64,
+}
+
+/// Customers of a cohort and their average lifetime value
+#[derive(Debug, Default)]
+pub struct CohortLifetimeValueAccumulator {
+    pub customers: CountAccumulator,
+    pub lifetime_value: ExchangeAdjustedAverageAccumulator,
+}
+
+/// Spread of the time attempts took to go through a lifecycle transition. The average is
+/// weighted by the number of attempts of every bucket.
+#[derive(Debug, Default)]
+pub struct TransitionSpreadAccumulator {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub weighted_total: f64,
+    pub count: u32,
+}
+
+impl TransitionSpreadAccumulator {
+    fn add(&mut self, min: Option<f64>, max: Option<f64>, avg: Option<f64>, count: Option<i64>) {
+        self.min = match (self.min, min) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.max = match (self.max, max) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        if let (Some(avg), Some(count)) = (avg, count.and_then(|i| u32::try_from(i).ok())) {
+            self.weighted_total += avg * f64::from(count);
+            self.count += count;
+        }
+    }
+
+    fn collect(self) -> (Option<f64>, Option<f64>, Option<f64>) {
+        let avg = (self.count > 0).then(|| self.weighted_total / f64::from(self.count));
+        (self.min, self.max, avg)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LifecycleSpreadAccumulator {
+    pub created_to_authorized: TransitionSpreadAccumulator,
+    pub created_to_captured: TransitionSpreadAccumulator,
+}
+
+/// Average latency of the attempts which went through a 3DS challenge and of the frictionless
+/// ones, only the averages of the spreads being reported
+#[derive(Debug, Default)]
+pub struct AuthenticationLatencyAccumulator {
+    pub challenged: TransitionSpreadAccumulator,
+    pub frictionless: TransitionSpreadAccumulator,
+}
+
+/// Average time attempts have spent in their pending status, weighted by the number of attempts
+/// of every bucket
+#[derive(Debug, Default)]
+#[repr(transparent)]
+pub struct PendingStateDurationAccumulator {
+    pub duration: TransitionSpreadAccumulator,
+}
+
+/// Failed attempts of a connector, along with its rank among the connectors of its bucket by
+/// failed attempts, 1 being the most failing one
+#[derive(Debug, Default)]
+pub struct FailureRankAccumulator {
+    pub failures: CountAccumulator,
+    pub rank: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct FunnelAccumulator {
+    pub created: CountAccumulator,
+    pub authenticated: Option<i64>,
+    pub authorized: Option<i64>,
+    pub captured: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct RetryRatioAccumulator {
+    pub attempts: i64,
+    pub payments: i64,
+}
+
 pub trait PaymentMetricAccumulator {
     type MetricOutput;
 
@@ -58,14 +319,7 @@ impl PaymentMetricAccumulator for SuccessRateAccumulator {
     }
 
     fn collect(self) -> Self::MetricOutput {
-        if self.total <= 0 {
-            None
-        } else {
-            Some(
-                f64::from(u32::try_from(self.success).ok()?) * 100.0
-                    / f64::from(u32::try_from(self.total).ok()?),
-            )
-        }
+        percentage(self.success, self.total)
     }
 }
 
@@ -107,6 +361,36 @@ impl PaymentMetricAccumulator for SumAccumulator {
     }
 }
 
+impl PaymentMetricAccumulator for ProcessedAmountAccumulator {
+    type MetricOutput = (Option<u64>, Option<f64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.total.add_metrics_bucket(metrics);
+        self.currencies
+            .insert(metrics.currency.as_ref().map(|currency| currency.0));
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        let minor_unit_total = self.total.collect();
+        let currency = if self.currencies.len() == 1 {
+            self.currencies.into_iter().next().flatten()
+        } else {
+            None
+        };
+        let major_unit_total = currency
+            .zip(minor_unit_total)
+            .and_then(|(currency, total)| to_major_unit(total, currency));
+        (minor_unit_total, major_unit_total)
+    }
+}
+
+fn to_major_unit(minor_unit_amount: u64, currency: storage_enums::Currency) -> Option<f64> {
+    bigdecimal::ToPrimitive::to_f64(
+        &(bigdecimal::BigDecimal::from(minor_unit_amount)
+            / bigdecimal::BigDecimal::from(10_u32.pow(currency_decimal_places(currency)))),
+    )
+}
+
 impl PaymentMetricAccumulator for AverageAccumulator {
     type MetricOutput = Option<f64>;
 
@@ -137,14 +421,888 @@ impl PaymentMetricAccumulator for AverageAccumulator {
     }
 }
 
+impl PaymentMetricAccumulator for CustomerTypeAccumulator {
+    type MetricOutput = (Option<u64>, Option<u64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        let class_count = match metrics.customer_type.as_deref() {
+            Some("new") => &mut self.new,
+            Some("returning") => &mut self.returning,
+            _ => {
+                logger::error!(message="Dropping metrics for customer type accumulator", metric=?metrics);
+                return;
+            }
+        };
+        *class_count = match (*class_count, metrics.count) {
+            (None, None) => None,
+            (None, i @ Some(_)) | (i @ Some(_), None) => i,
+            (Some(a), Some(b)) => Some(a + b),
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        (
+            self.new.and_then(|i| u64::try_from(i).ok()),
+            self.returning.and_then(|i| u64::try_from(i).ok()),
+        )
+    }
+}
+
+impl PaymentMetricAccumulator for CustomerSegmentAccumulator {
+    type MetricOutput = (Option<f64>, Option<f64>, Option<f64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        match metrics.customer_segment.as_deref() {
+            Some("low") => self.low.add_metrics_bucket(metrics),
+            Some("medium") => self.medium.add_metrics_bucket(metrics),
+            Some("high") => self.high.add_metrics_bucket(metrics),
+            _ => {
+                logger::error!(message="Dropping metrics for customer segment accumulator", metric=?metrics);
+            }
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        (
+            self.low.collect(),
+            self.medium.collect(),
+            self.high.collect(),
+        )
+    }
+}
+
+impl PaymentMetricAccumulator for DeclineCategoryAccumulator {
+    type MetricOutput = Option<HashMap<String, u64>>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        match (metrics.decline_category.as_ref(), metrics.count) {
+            (Some(category), Some(count)) => {
+                *self.counts.entry(category.clone()).or_default() += count;
+            }
+            _ => {
+                logger::error!(message="Dropping metrics for decline category accumulator", metric=?metrics);
+            }
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        if self.counts.is_empty() {
+            None
+        } else {
+            self.counts
+                .into_iter()
+                .map(|(category, count)| Some((category, u64::try_from(count).ok()?)))
+                .collect()
+        }
+    }
+}
+
+impl PaymentMetricAccumulator for DeclineRecoveryAccumulator {
+    type MetricOutput = (Option<HashMap<String, u64>>, Option<HashMap<String, u64>>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        match (
+            metrics.decline_category.as_ref(),
+            metrics.count,
+            metrics.retried_count,
+        ) {
+            (Some(category), Some(count), Some(retried_count)) => {
+                *self.retried.counts.entry(category.clone()).or_default() += retried_count;
+                *self.abandoned.counts.entry(category.clone()).or_default() +=
+                    count - retried_count;
+            }
+            _ => {
+                logger::error!(message="Dropping metrics for decline recovery accumulator", metric=?metrics);
+            }
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        (self.retried.collect(), self.abandoned.collect())
+    }
+}
+
+impl PaymentMetricAccumulator for RoutingFallbackDepthAccumulator {
+    type MetricOutput = Option<HashMap<String, f64>>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        match metrics.routing_rule.as_ref() {
+            Some(routing_rule) => self
+                .depths
+                .entry(routing_rule.clone())
+                .or_default()
+                .add_metrics_bucket(metrics),
+            None => {
+                logger::error!(message="Dropping metrics for routing fallback depth accumulator", metric=?metrics);
+            }
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        if self.depths.is_empty() {
+            None
+        } else {
+            self.depths
+                .into_iter()
+                .map(|(routing_rule, depth)| Some((routing_rule, depth.collect()?)))
+                .collect()
+        }
+    }
+}
+
+impl PaymentMetricAccumulator for ExchangeAdjustedAverageAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        match (metrics.total.as_ref(), metrics.count) {
+            (Some(total), Some(count)) => {
+                self.total += total;
+                self.count += count;
+            }
+            _ => {
+                logger::error!(message="Dropping metrics for exchange adjusted average accumulator", metric=?metrics);
+            }
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        if self.count <= 0 {
+            None
+        } else {
+            bigdecimal::ToPrimitive::to_f64(
+                &(self.total / bigdecimal::BigDecimal::from(self.count)),
+            )
+        }
+    }
+}
+
+impl PaymentMetricAccumulator for CohortLifetimeValueAccumulator {
+    type MetricOutput = (Option<u64>, Option<f64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.customers.add_metrics_bucket(metrics);
+        self.lifetime_value.add_metrics_bucket(metrics);
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        (self.customers.collect(), self.lifetime_value.collect())
+    }
+}
+
+impl PaymentMetricAccumulator for LifecycleSpreadAccumulator {
+    type MetricOutput = (
+        (Option<f64>, Option<f64>, Option<f64>),
+        (Option<f64>, Option<f64>, Option<f64>),
+    );
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.created_to_authorized.add(
+            metrics.created_to_authorized_min,
+            metrics.created_to_authorized_max,
+            metrics.created_to_authorized_avg,
+            metrics.authorized_count,
+        );
+        self.created_to_captured.add(
+            metrics.created_to_captured_min,
+            metrics.created_to_captured_max,
+            metrics.created_to_captured_avg,
+            metrics.captured_count,
+        );
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        (
+            self.created_to_authorized.collect(),
+            self.created_to_captured.collect(),
+        )
+    }
+}
+
+impl PaymentMetricAccumulator for AuthenticationLatencyAccumulator {
+    type MetricOutput = (Option<f64>, Option<f64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.challenged.add(
+            None,
+            None,
+            metrics.challenged_latency_avg,
+            metrics.challenged_count,
+        );
+        self.frictionless.add(
+            None,
+            None,
+            metrics.frictionless_latency_avg,
+            metrics.frictionless_count,
+        );
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        let (_, _, challenged) = self.challenged.collect();
+        let (_, _, frictionless) = self.frictionless.collect();
+        (challenged, frictionless)
+    }
+}
+
+impl PaymentMetricAccumulator for PendingStateDurationAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.duration
+            .add(None, None, metrics.pending_duration_avg, metrics.count);
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        let (_, _, avg) = self.duration.collect();
+        avg
+    }
+}
+
+impl PaymentMetricAccumulator for FailureRankAccumulator {
+    type MetricOutput = (Option<u64>, Option<u64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.failures.add_metrics_bucket(metrics);
+        self.rank = match (self.rank, metrics.failure_rank) {
+            (None, None) => None,
+            (None, i @ Some(_)) | (i @ Some(_), None) => i,
+            (Some(a), Some(b)) => Some(a.min(b)),
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        (
+            self.failures.collect(),
+            self.rank.and_then(|i| u64::try_from(i).ok()),
+        )
+    }
+}
+
+impl PaymentMetricAccumulator for PercentileAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        // Percentiles of separate rows cannot be combined, the highest one is reported instead
+        self.value = match (self.value, metrics.percentile) {
+            (None, None) => None,
+            (None, i @ Some(_)) | (i @ Some(_), None) => i,
+            (Some(a), Some(b)) => Some(a.max(b)),
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        self.value
+    }
+}
+
+impl PaymentMetricAccumulator for RetriesBeforeSuccessAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.attempts.add_metrics_bucket(metrics)
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        self.attempts.collect().map(|attempts| attempts - 1.0)
+    }
+}
+
+impl PaymentMetricAccumulator for RankedSuccessRateAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.success += metrics.success_count.unwrap_or_default();
+        self.total += metrics.count.unwrap_or_default();
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        percentage(self.success, self.total)
+    }
+}
+
+impl PaymentMetricAccumulator for ZeroAmountAccumulator {
+    type MetricOutput = (Option<u64>, Option<u64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.zero_amount += metrics.zero_amount_count.unwrap_or_default();
+        self.total += metrics.count.unwrap_or_default();
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        (
+            u64::try_from(self.zero_amount).ok(),
+            u64::try_from(self.total - self.zero_amount).ok(),
+        )
+    }
+}
+
+impl PaymentMetricAccumulator for TimeToFirstPaymentAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        let count = metrics.count.and_then(|count| u32::try_from(count).ok());
+        match (metrics.time_to_first_payment, count) {
+            (Some(avg), Some(count)) => {
+                self.weighted_total += avg * f64::from(count);
+                self.count += count;
+            }
+            _ => {
+                logger::error!(message="Dropping metrics for time to first payment accumulator", metric=?metrics);
+            }
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.weighted_total / f64::from(self.count))
+        }
+    }
+}
+
+impl PaymentMetricAccumulator for SurchargeAdoptionAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.surcharged += metrics.surcharged_count.unwrap_or_default();
+        self.total += metrics.count.unwrap_or_default();
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        percentage(self.surcharged, self.total)
+    }
+}
+
+impl PaymentMetricAccumulator for EffectiveFeeRateAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        // The rate is null for buckets without any amount, which are left out
+        if metrics.effective_fee_rate.is_none() {
+            return;
+        }
+        if let Some(fee_total) = &metrics.fee_total {
+            self.fee_total += fee_total;
+        }
+        if let Some(total) = &metrics.total {
+            self.total += total;
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        if self.total == bigdecimal::BigDecimal::from(0) {
+            None
+        } else {
+            bigdecimal::ToPrimitive::to_f64(
+                &(self.fee_total * bigdecimal::BigDecimal::from(100) / self.total),
+            )
+        }
+    }
+}
+
+impl PaymentMetricAccumulator for ConnectorErrorRateAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.errors += metrics.error_count.unwrap_or_default();
+        self.total += metrics.count.unwrap_or_default();
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        percentage(self.errors, self.total)
+    }
+}
+
+impl PaymentMetricAccumulator for DiscountAccumulator {
+    type MetricOutput = (Option<f64>, Option<u64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        if let Some(discount_total) = &metrics.discount_total {
+            self.discount_total += discount_total;
+        }
+        self.volume += metrics
+            .total
+            .as_ref()
+            .and_then(bigdecimal::ToPrimitive::to_i64)
+            .unwrap_or_default();
+        self.count += metrics.count.unwrap_or_default();
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        let avg_discount = if self.count <= 0 {
+            None
+        } else {
+            bigdecimal::ToPrimitive::to_f64(
+                &(self.discount_total / bigdecimal::BigDecimal::from(self.count)),
+            )
+        };
+        (avg_discount, u64::try_from(self.volume).ok())
+    }
+}
+
+impl PaymentMetricAccumulator for AovTrendAccumulator {
+    type MetricOutput = (Option<f64>, Option<f64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.current.add_metrics_bucket(metrics);
+        if let (Some(total), Some(count)) = (&metrics.previous_total, metrics.previous_count) {
+            self.previous.total += total;
+            self.previous.count += count;
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        let current = self.current.collect();
+        let change = match (current, self.previous.collect()) {
+            (Some(current), Some(previous)) if previous > 0.0 => {
+                Some((current - previous) * 100.0 / previous)
+            }
+            _ => None,
+        };
+        (current, change)
+    }
+}
+
+impl PaymentMetricAccumulator for PaymentMethodUsageAccumulator {
+    type MetricOutput = (Option<u64>, Option<u64>, Option<f64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.offered += metrics.offered_count.unwrap_or_default();
+        self.used += metrics.used_count.unwrap_or_default();
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        (
+            u64::try_from(self.offered).ok(),
+            u64::try_from(self.used).ok(),
+            percentage(self.used, self.offered),
+        )
+    }
+}
+
+impl PaymentMetricAccumulator for RecoveryRateAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.recovered += metrics.recovered_count.unwrap_or_default();
+        self.failed += metrics.count.unwrap_or_default();
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        percentage(self.recovered, self.failed)
+    }
+}
+
+impl PaymentMetricAccumulator for FunnelAccumulator {
+    type MetricOutput = (Option<u64>, Option<u64>, Option<u64>, Option<u64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        let add = |acc: Option<i64>, value: Option<i64>| match (acc, value) {
+            (None, None) => None,
+            (None, i @ Some(_)) | (i @ Some(_), None) => i,
+            (Some(a), Some(b)) => Some(a + b),
+        };
+        self.created.add_metrics_bucket(metrics);
+        self.authenticated = add(self.authenticated, metrics.authenticated_count);
+        self.authorized = add(self.authorized, metrics.authorized_count);
+        self.captured = add(self.captured, metrics.captured_count);
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        (
+            self.created.collect(),
+            self.authenticated.and_then(|i| u64::try_from(i).ok()),
+            self.authorized.and_then(|i| u64::try_from(i).ok()),
+            self.captured.and_then(|i| u64::try_from(i).ok()),
+        )
+    }
+}
+
+impl PaymentMetricAccumulator for RetryRatioAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &PaymentMetricRow) {
+        self.attempts += metrics.count.unwrap_or_default();
+        self.payments += metrics.distinct_count.unwrap_or_default();
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        if self.payments <= 0 {
+            None
+        } else {
+            Some(
+                f64::from(u32::try_from(self.attempts).ok()?)
+                    / f64::from(u32::try_from(self.payments).ok()?),
+            )
+        }
+    }
+}
+
 impl PaymentMetricsAccumulator {
+    /// Adds a bucket row loaded for `metric` to the accumulator of that metric
+    pub fn add_metrics_bucket(&mut self, metric: &PaymentMetrics, metrics: &PaymentMetricRow) {
+        match metric {
+            PaymentMetrics::PaymentSuccessRate => {
+                self.payment_success_rate.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::PaymentCount => self.payment_count.add_metrics_bucket(metrics),
+            PaymentMetrics::PaymentSuccessCount => self.payment_success.add_metrics_bucket(metrics),
+            PaymentMetrics::PaymentProcessedAmount => {
+                self.processed_amount.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::AvgTicketSize => self.avg_ticket_size.add_metrics_bucket(metrics),
+            PaymentMetrics::NewVsReturningCustomers => {
+                self.new_vs_returning_customers.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::AuthorizedCaptureFailureCount => {
+                self.authorized_capture_failure.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::ConnectorLatencyP95 => {
+                self.connector_latency_p95.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::RetryRatio => self.retry_ratio.add_metrics_bucket(metrics),
+            PaymentMetrics::WalletTypeBreakdown => {
+                self.wallet_type_breakdown.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::CardNetworkBreakdown => {
+                self.card_network_breakdown.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::SmartRetrySuccessRate => {
+                self.smart_retry_success_rate.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::AvgCaptureGap => self.avg_capture_gap.add_metrics_bucket(metrics),
+            PaymentMetrics::TopFailingConnectors => {
+                self.top_failing_connectors.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::PaymentFunnel => self.payment_funnel.add_metrics_bucket(metrics),
+            PaymentMetrics::AvgRetriesBeforeSuccess => {
+                self.avg_retries_before_success.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::DailyGmv => self.daily_gmv.add_metrics_bucket(metrics),
+            PaymentMetrics::PaymentMethodSuccessRanking => self
+                .payment_method_success_ranking
+                .add_metrics_bucket(metrics),
+            PaymentMetrics::VolumeHeatmap => self.volume_heatmap.add_metrics_bucket(metrics),
+            PaymentMetrics::RecoveryRate => self.recovery_rate.add_metrics_bucket(metrics),
+            PaymentMetrics::AvgValueByCustomerSegment => self
+                .avg_value_by_customer_segment
+                .add_metrics_bucket(metrics),
+            PaymentMetrics::DeclineCategoryBreakdown => {
+                self.decline_category_breakdown.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::ExchangeAdjustedTicketSize => self
+                .exchange_adjusted_ticket_size
+                .add_metrics_bucket(metrics),
+            PaymentMetrics::LifecycleSpread => self.lifecycle_spread.add_metrics_bucket(metrics),
+            PaymentMetrics::SuccessRateByCountry => self
+                .billing_country_success_rate
+                .add_metrics_bucket(metrics),
+            PaymentMetrics::RoutingFallbackDepth => {
+                self.routing_fallback_depth.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::ZeroAmountCount => self.zero_amount_count.add_metrics_bucket(metrics),
+            PaymentMetrics::TimeToFirstPayment => {
+                self.time_to_first_payment.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::SurchargeAdoptionRate => {
+                self.surcharge_adoption_rate.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::EffectiveFeeRate => self.effective_fee_rate.add_metrics_bucket(metrics),
+            PaymentMetrics::ConnectorErrorRate => {
+                self.connector_error_rate.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::MandateSuccessRate => {
+                self.mandate_success_rate.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::AvgDiscount => self.avg_discount.add_metrics_bucket(metrics),
+            PaymentMetrics::PaymentMethodUsage => {
+                self.payment_method_usage.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::AvgLineItems => self.avg_line_items.add_metrics_bucket(metrics),
+            PaymentMetrics::SuccessRateByBin => {
+                self.card_bin_success_rate.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::AuthenticationLatency => {
+                self.authentication_latency.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::AovTrend => self.aov_trend.add_metrics_bucket(metrics),
+            PaymentMetrics::CustomerLifetimeValue => {
+                self.customer_lifetime_value.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::DeclineRecoverySplit => {
+                self.decline_recovery_split.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::PendingStateDuration => {
+                self.pending_state_duration.add_metrics_bucket(metrics)
+            }
+            PaymentMetrics::ApprovalRateByIssuerCountry => self
+                .issuer_country_approval_rate
+                .add_metrics_bucket(metrics),
+        }
+    }
+
     pub fn collect(self) -> PaymentMetricsBucketValue {
+        let (new_customer_count, returning_customer_count) =
+            self.new_vs_returning_customers.collect();
+        let (payment_processed_amount, payment_processed_amount_in_major_unit) =
+            self.processed_amount.collect();
+        let (
+            funnel_created_count,
+            funnel_authenticated_count,
+            funnel_authorized_count,
+            funnel_captured_count,
+        ) = self.payment_funnel.collect();
+        let (avg_value_low_segment, avg_value_medium_segment, avg_value_high_segment) =
+            self.avg_value_by_customer_segment.collect();
+        let (
+            (
+                created_to_authorized_min_seconds,
+                created_to_authorized_max_seconds,
+                created_to_authorized_avg_seconds,
+            ),
+            (
+                created_to_captured_min_seconds,
+                created_to_captured_max_seconds,
+                created_to_captured_avg_seconds,
+            ),
+        ) = self.lifecycle_spread.collect();
+        let (zero_amount_count, non_zero_amount_count) = self.zero_amount_count.collect();
+        let (avg_discount_amount, discounted_volume) = self.avg_discount.collect();
+        let (payment_method_offered_count, payment_method_used_count, payment_method_usage_rate) =
+            self.payment_method_usage.collect();
+        let (
+            challenged_authorization_latency_avg_seconds,
+            frictionless_authorization_latency_avg_seconds,
+        ) = self.authentication_latency.collect();
+        let (avg_order_value, avg_order_value_change_percent) = self.aov_trend.collect();
+        let (cohort_customer_count, avg_customer_lifetime_value) =
+            self.customer_lifetime_value.collect();
+        let (declined_retried_count, declined_abandoned_count) =
+            self.decline_recovery_split.collect();
+        let (connector_failure_count, connector_failure_rank) =
+            self.top_failing_connectors.collect();
         PaymentMetricsBucketValue {
             payment_success_rate: self.payment_success_rate.collect(),
             payment_count: self.payment_count.collect(),
             payment_success_count: self.payment_success.collect(),
-            payment_processed_amount: self.processed_amount.collect(),
+            payment_processed_amount,
+            payment_processed_amount_in_major_unit,
             avg_ticket_size: self.avg_ticket_size.collect(),
+            new_customer_count,
+            returning_customer_count,
+            authorized_capture_failure_count: self.authorized_capture_failure.collect(),
+            connector_latency_p95: self.connector_latency_p95.collect(),
+            retry_ratio: self.retry_ratio.collect(),
+            wallet_payment_count: self.wallet_type_breakdown.collect(),
+            card_network_payment_count: self.card_network_breakdown.collect(),
+            smart_retry_success_rate: self.smart_retry_success_rate.collect(),
+            avg_capture_gap: self.avg_capture_gap.collect(),
+            connector_failure_count,
+            connector_failure_rank,
+            funnel_created_count,
+            funnel_authenticated_count,
+            funnel_authorized_count,
+            funnel_captured_count,
+            avg_retries_before_success: self.avg_retries_before_success.collect(),
+            daily_gmv: self.daily_gmv.collect(),
+            payment_method_success_rate: self.payment_method_success_ranking.collect(),
+            heatmap_payment_count: self.volume_heatmap.collect(),
+            payment_recovery_rate: self.recovery_rate.collect(),
+            avg_value_low_segment,
+            avg_value_medium_segment,
+            avg_value_high_segment,
+            decline_category_count: self.decline_category_breakdown.collect(),
+            avg_ticket_size_in_base_currency: self.exchange_adjusted_ticket_size.collect(),
+            created_to_authorized_min_seconds,
+            created_to_authorized_max_seconds,
+            created_to_authorized_avg_seconds,
+            created_to_captured_min_seconds,
+            created_to_captured_max_seconds,
+            created_to_captured_avg_seconds,
+            billing_country_success_rate: self.billing_country_success_rate.collect(),
+            avg_connectors_per_routing_rule: self.routing_fallback_depth.collect(),
+            zero_amount_count,
+            non_zero_amount_count,
+            avg_time_to_first_payment_seconds: self.time_to_first_payment.collect(),
+            surcharge_adoption_rate: self.surcharge_adoption_rate.collect(),
+            effective_fee_rate: self.effective_fee_rate.collect(),
+            connector_error_rate: self.connector_error_rate.collect(),
+            mandate_success_rate: self.mandate_success_rate.collect(),
+            avg_discount_amount,
+            discounted_volume,
+            payment_method_offered_count,
+            payment_method_used_count,
+            payment_method_usage_rate,
+            avg_line_items_per_payment: self.avg_line_items.collect(),
+            card_bin_success_rate: self.card_bin_success_rate.collect(),
+            challenged_authorization_latency_avg_seconds,
+            frictionless_authorization_latency_avg_seconds,
+            avg_order_value,
+            avg_order_value_change_percent,
+            cohort_customer_count,
+            avg_customer_lifetime_value,
+            declined_retried_count,
+            declined_abandoned_count,
+            avg_pending_state_duration_seconds: self.pending_state_duration.collect(),
+            issuer_country_approval_rate: self.issuer_country_approval_rate.collect(),
+        }
+    }
+}
+
+/// Merges the bucket rows loaded for several metrics into one accumulator per bucket identifier,
+/// combining the values of all metrics for the same bucket.
+#[allow(dead_code)]
+pub fn merge_metrics_rows<I>(
+    results: I,
+) -> HashMap<PaymentMetricsBucketIdentifier, PaymentMetricsAccumulator>
+where
+    I: IntoIterator<
+        Item = (
+            PaymentMetrics,
+            Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>,
+        ),
+    >,
+{
+    let mut merged: HashMap<PaymentMetricsBucketIdentifier, PaymentMetricsAccumulator> =
+        HashMap::new();
+    for (metric, rows) in results {
+        for (id, row) in rows {
+            merged
+                .entry(id)
+                .or_default()
+                .add_metrics_bucket(&metric, &row);
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use api_models::analytics::TimeRange;
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::analytics::types::DBEnumWrapper;
+
+    #[test]
+    fn test_processed_amount_in_minor_and_major_units() {
+        let mut accumulator = ProcessedAmountAccumulator::default();
+        accumulator.add_metrics_bucket(&PaymentMetricRow {
+            currency: Some(DBEnumWrapper(storage_enums::Currency::USD)),
+            total: Some(bigdecimal::BigDecimal::from(12345)),
+            ..Default::default()
+        });
+        assert_eq!(accumulator.collect(), (Some(12345), Some(123.45)));
+
+        let mut accumulator = ProcessedAmountAccumulator::default();
+        for currency in [storage_enums::Currency::USD, storage_enums::Currency::EUR] {
+            accumulator.add_metrics_bucket(&PaymentMetricRow {
+                currency: Some(DBEnumWrapper(currency)),
+                total: Some(bigdecimal::BigDecimal::from(100)),
+                ..Default::default()
+            });
+        }
+        assert_eq!(accumulator.collect(), (Some(200), None));
+    }
+
+    #[test]
+    fn test_success_rate_of_empty_bucket() {
+        let mut accumulator = SuccessRateAccumulator::default();
+        accumulator.add_metrics_bucket(&PaymentMetricRow {
+            status: Some(DBEnumWrapper(storage_enums::AttemptStatus::Charged)),
+            count: Some(0),
+            ..Default::default()
+        });
+        assert_eq!(accumulator.collect(), None);
+
+        let mut accumulator = SuccessRateAccumulator::default();
+        accumulator.add_metrics_bucket(&PaymentMetricRow {
+            status: Some(DBEnumWrapper(storage_enums::AttemptStatus::Failure)),
+            count: Some(2),
+            ..Default::default()
+        });
+        assert_eq!(accumulator.collect(), Some(0.0));
+    }
+
+    #[test]
+    fn test_failure_rank_kept_with_failures() {
+        let mut accumulator = FailureRankAccumulator::default();
+        accumulator.add_metrics_bucket(&PaymentMetricRow {
+            count: Some(7),
+            failure_rank: Some(2),
+            ..Default::default()
+        });
+        assert_eq!(accumulator.collect(), (Some(7), Some(2)));
+
+        assert_eq!(FailureRankAccumulator::default().collect(), (None, None));
+    }
+
+    #[test]
+    fn test_decline_recovery_split() {
+        let mut accumulator = DeclineRecoveryAccumulator::default();
+        for (category, count, retried_count) in [("fraud", 5, 1), ("fraud", 3, 3), ("other", 2, 0)]
+        {
+            accumulator.add_metrics_bucket(&PaymentMetricRow {
+                decline_category: Some(category.to_string()),
+                count: Some(count),
+                retried_count: Some(retried_count),
+                ..Default::default()
+            });
         }
+        let (retried, abandoned) = accumulator.collect();
+        assert_eq!(
+            retried,
+            Some(HashMap::from([
+                ("fraud".to_string(), 4),
+                ("other".to_string(), 0)
+            ]))
+        );
+        assert_eq!(
+            abandoned,
+            Some(HashMap::from([
+                ("fraud".to_string(), 4),
+                ("other".to_string(), 2)
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_merge_metrics_rows() {
+        let bucket_id = || PaymentMetricsBucketIdentifier {
+            connector: Some("stripe".to_string()),
+            ..PaymentMetricsBucketIdentifier::new(TimeRange {
+                start_time: datetime!(2023-11-01 00:00:00),
+                end_time: Some(datetime!(2023-11-01 01:00:00)),
+            })
+        };
+        let merged = merge_metrics_rows([
+            (
+                PaymentMetrics::PaymentCount,
+                vec![(
+                    bucket_id(),
+                    PaymentMetricRow {
+                        count: Some(4),
+                        ..Default::default()
+                    },
+                )],
+            ),
+            (
+                PaymentMetrics::PaymentSuccessCount,
+                vec![(
+                    bucket_id(),
+                    PaymentMetricRow {
+                        status: Some(DBEnumWrapper(storage_enums::AttemptStatus::Charged)),
+                        count: Some(3),
+                        ..Default::default()
+                    },
+                )],
+            ),
+        ]);
+
+        assert_eq!(merged.len(), 1);
+        let value = merged.into_values().next().unwrap().collect();
+        assert_eq!(value.payment_count, Some(4));
+        assert_eq!(value.payment_success_count, Some(3));
     }
 }
@@ -10,11 +10,11 @@ use router_env::{
     tracing::{self, Instrument},
 };
 
-use super::PaymentMetricsAccumulator;
+use super::{metrics::RequestedPaymentMetric, PaymentMetricsAccumulator};
 use crate::{
     analytics::{
-        core::AnalyticsApiResponse, errors::AnalyticsError, metrics,
-        payments::PaymentMetricAccumulator, AnalyticsProvider,
+        core::AnalyticsApiResponse, errors::AnalyticsError, metrics, query::resolve_granularity,
+        AnalyticsProvider,
     },
     services::ApplicationResponse,
     types::domain,
@@ -44,11 +44,14 @@ pub async fn get_metrics(
             async move {
                 let data = pool
                     .get_payment_metrics(
-                        &metric_type,
+                        &RequestedPaymentMetric {
+                            metric: &metric_type,
+                            params: &req.metric_params,
+                        },
                         &req.group_by_names.clone(),
                         &merchant_id,
                         &req.filters,
-                        &req.time_series.map(|t| t.granularity),
+                        &resolve_granularity(&req.time_series, &req.time_range),
                         &req.time_range,
                     )
                     .await
@@ -85,24 +88,10 @@ pub async fn get_metrics(
 
         for (id, value) in data {
             logger::debug!(bucket_id=?id, bucket_value=?value, "Bucket row for metric {metric}");
-            let metrics_builder = metrics_accumulator.entry(id).or_default();
-            match metric {
-                PaymentMetrics::PaymentSuccessRate => metrics_builder
-                    .payment_success_rate
-                    .add_metrics_bucket(&value),
-                PaymentMetrics::PaymentCount => {
-                    metrics_builder.payment_count.add_metrics_bucket(&value)
-                }
-                PaymentMetrics::PaymentSuccessCount => {
-                    metrics_builder.payment_success.add_metrics_bucket(&value)
-                }
-                PaymentMetrics::PaymentProcessedAmount => {
-                    metrics_builder.processed_amount.add_metrics_bucket(&value)
-                }
-                PaymentMetrics::AvgTicketSize => {
-                    metrics_builder.avg_ticket_size.add_metrics_bucket(&value)
-                }
-            }
+            metrics_accumulator
+                .entry(id)
+                .or_default()
+                .add_metrics_bucket(&metric, &value);
         }
 
         logger::debug!(
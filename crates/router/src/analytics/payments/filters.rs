@@ -1,11 +1,11 @@
 use api_models::analytics::{payments::PaymentDimensions, Granularity, TimeRange};
-use common_enums::enums::{AttemptStatus, AuthenticationType, Currency};
+use common_enums::enums::{AttemptStatus, AuthenticationType, CountryAlpha2, Currency};
 use common_utils::errors::ReportSwitchExt;
 use error_stack::ResultExt;
 use time::PrimitiveDateTime;
 
 use crate::analytics::{
-    query::{Aggregate, GroupByClause, QueryBuilder, QueryFilter, ToSql},
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryFilter, ToSql},
     types::{
         AnalyticsCollection, AnalyticsDataSource, DBEnumWrapper, FiltersError, FiltersResult,
         LoadRow,
@@ -28,6 +28,7 @@ where
     Aggregate<&'static str>: ToSql<T>,
 {
     let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+    query_builder.set_storage_timezone(pool.storage_timezone());
 
     query_builder.add_select_column(dimension).switch()?;
     time_range
@@ -36,7 +37,7 @@ where
         .switch()?;
 
     query_builder
-        .add_filter_clause("merchant_id", merchant)
+        .add_filter_clause(Column::MerchantId, merchant)
         .switch()?;
 
     query_builder.set_distinct();
@@ -55,4 +56,12 @@ pub struct FilterRow {
     pub connector: Option<String>,
     pub authentication_type: Option<DBEnumWrapper<AuthenticationType>>,
     pub payment_method: Option<String>,
+    pub profile_id: Option<String>,
+    pub payment_method_type: Option<String>,
+    pub card_network: Option<String>,
+    pub day_of_week: Option<u8>,
+    pub hour_of_day: Option<u8>,
+    pub billing_country: Option<DBEnumWrapper<CountryAlpha2>>,
+    pub card_bin: Option<String>,
+    pub issuer_country: Option<String>,
 }
@@ -1,40 +1,364 @@
 use api_models::analytics::{
-    payments::{PaymentDimensions, PaymentFilters, PaymentMetrics, PaymentMetricsBucketIdentifier},
+    payments::{
+        PaymentDimensions, PaymentFilters, PaymentMetricParams, PaymentMetrics,
+        PaymentMetricsBucketIdentifier,
+    },
     Granularity, TimeRange,
 };
 use common_enums::enums as storage_enums;
+use error_stack::ResultExt;
 use time::PrimitiveDateTime;
 
 use crate::analytics::{
-    query::{Aggregate, GroupByClause, ToSql},
-    types::{AnalyticsCollection, AnalyticsDataSource, DBEnumWrapper, LoadRow, MetricsResult},
+    query::{
+        storage_time_to_utc, Aggregate, BaseFilters, Column, GroupByClause, PostProcessingError,
+        QueryBuilder, QueryFilter, QueryResult, SeriesBucket, ToSql,
+    },
+    types::{
+        AnalyticsCollection, AnalyticsDataSource, DBEnumWrapper, LoadRow, MetricsError,
+        MetricsResult,
+    },
 };
 
+mod aov_trend;
+mod approval_rate_by_issuer_country;
+mod authentication_latency;
+mod authorized_capture_failure_count;
+mod avg_capture_gap;
+mod avg_discount;
+mod avg_line_items;
+mod avg_retries_before_success;
 mod avg_ticket_size;
+mod avg_value_by_customer_segment;
+mod card_network_breakdown;
+mod connector_error_rate;
+mod connector_latency_p95;
+mod customer_lifetime_value;
+mod daily_gmv;
+mod decline_category_breakdown;
+mod decline_recovery_split;
+mod effective_fee_rate;
+mod exchange_adjusted_ticket_size;
+mod lifecycle_spread;
+mod mandate_success_rate;
+mod new_vs_returning_customers;
 mod payment_count;
+mod payment_funnel;
+mod payment_method_success_ranking;
+mod payment_method_usage;
 mod payment_processed_amount;
 mod payment_success_count;
+mod pending_state_duration;
+mod recovery_rate;
+mod retry_ratio;
+mod routing_fallback_depth;
+mod smart_retry_success_rate;
 mod success_rate;
+mod success_rate_by_bin;
+mod success_rate_by_country;
+mod surcharge_adoption_rate;
+mod time_to_first_payment;
+mod top_failing_connectors;
+mod volume_heatmap;
+mod wallet_type_breakdown;
+mod zero_amount_count;
 
+use aov_trend::AovTrend;
+use approval_rate_by_issuer_country::ApprovalRateByIssuerCountry;
+use authentication_latency::AuthenticationLatency;
+use authorized_capture_failure_count::AuthorizedCaptureFailureCount;
+use avg_capture_gap::AvgCaptureGap;
+use avg_discount::AvgDiscount;
+use avg_line_items::AvgLineItems;
+use avg_retries_before_success::AvgRetriesBeforeSuccess;
 use avg_ticket_size::AvgTicketSize;
+use avg_value_by_customer_segment::AvgValueByCustomerSegment;
+use card_network_breakdown::CardNetworkBreakdown;
+use connector_error_rate::ConnectorErrorRate;
+use connector_latency_p95::ConnectorLatencyP95;
+use customer_lifetime_value::CustomerLifetimeValue;
+use daily_gmv::DailyGmv;
+use decline_category_breakdown::DeclineCategoryBreakdown;
+use decline_recovery_split::DeclineRecoverySplit;
+use effective_fee_rate::EffectiveFeeRate;
+use exchange_adjusted_ticket_size::ExchangeAdjustedTicketSize;
+use lifecycle_spread::LifecycleSpread;
+use mandate_success_rate::MandateSuccessRate;
+use new_vs_returning_customers::NewVsReturningCustomers;
 use payment_count::PaymentCount;
+use payment_funnel::PaymentFunnel;
+use payment_method_success_ranking::PaymentMethodSuccessRanking;
+use payment_method_usage::PaymentMethodUsage;
 use payment_processed_amount::PaymentProcessedAmount;
 use payment_success_count::PaymentSuccessCount;
+use pending_state_duration::PendingStateDuration;
+use recovery_rate::RecoveryRate;
+use retry_ratio::RetryRatio;
+use routing_fallback_depth::RoutingFallbackDepth;
+use smart_retry_success_rate::SmartRetrySuccessRate;
 use success_rate::PaymentSuccessRate;
+use success_rate_by_bin::SuccessRateByBin;
+use success_rate_by_country::SuccessRateByCountry;
+use surcharge_adoption_rate::SurchargeAdoptionRate;
+use time_to_first_payment::TimeToFirstPayment;
+use top_failing_connectors::TopFailingConnectors;
+use volume_heatmap::VolumeHeatmap;
+use wallet_type_breakdown::WalletTypeBreakdown;
+use zero_amount_count::ZeroAmountCount;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Default, PartialEq)]
 pub struct PaymentMetricRow {
     pub currency: Option<DBEnumWrapper<storage_enums::Currency>>,
     pub status: Option<DBEnumWrapper<storage_enums::AttemptStatus>>,
     pub connector: Option<String>,
     pub authentication_type: Option<DBEnumWrapper<storage_enums::AuthenticationType>>,
     pub payment_method: Option<String>,
+    pub profile_id: Option<String>,
+    pub payment_method_type: Option<String>,
+    pub card_network: Option<String>,
+    pub day_of_week: Option<u8>,
+    pub hour_of_day: Option<u8>,
+    pub billing_country: Option<DBEnumWrapper<storage_enums::CountryAlpha2>>,
+    pub card_bin: Option<String>,
+    pub issuer_country: Option<String>,
+    pub customer_type: Option<String>,
+    pub percentile: Option<f64>,
+    pub distinct_count: Option<i64>,
+    pub authenticated_count: Option<i64>,
+    pub authorized_count: Option<i64>,
+    pub captured_count: Option<i64>,
+    pub success_count: Option<i64>,
+    pub recovered_count: Option<i64>,
+    pub customer_segment: Option<String>,
+    pub decline_category: Option<String>,
+    pub created_to_authorized_min: Option<f64>,
+    pub created_to_authorized_max: Option<f64>,
+    pub created_to_authorized_avg: Option<f64>,
+    pub created_to_captured_min: Option<f64>,
+    pub created_to_captured_max: Option<f64>,
+    pub created_to_captured_avg: Option<f64>,
+    pub routing_rule: Option<String>,
+    pub zero_amount_count: Option<i64>,
+    pub time_to_first_payment: Option<f64>,
+    pub surcharged_count: Option<i64>,
+    pub fee_total: Option<bigdecimal::BigDecimal>,
+    pub effective_fee_rate: Option<bigdecimal::BigDecimal>,
+    pub error_count: Option<i64>,
+    pub discount_total: Option<bigdecimal::BigDecimal>,
+    pub offered_count: Option<i64>,
+    pub used_count: Option<i64>,
+    pub challenged_count: Option<i64>,
+    pub challenged_latency_avg: Option<f64>,
+    pub frictionless_count: Option<i64>,
+    pub frictionless_latency_avg: Option<f64>,
+    pub previous_total: Option<bigdecimal::BigDecimal>,
+    pub previous_count: Option<i64>,
+    pub retried_count: Option<i64>,
+    pub pending_duration_avg: Option<f64>,
+    pub failure_rank: Option<i64>,
     pub total: Option<bigdecimal::BigDecimal>,
     pub count: Option<i64>,
     pub start_bucket: Option<PrimitiveDateTime>,
     pub end_bucket: Option<PrimitiveDateTime>,
 }
 
+impl PaymentMetricRow {
+    /// Column names read when loading a [`PaymentMetricRow`], any other selected column is
+    /// ignored by the loader.
+    pub const COLUMNS: &'static [&'static str] = &[
+        "currency",
+        "status",
+        "connector",
+        "authentication_type",
+        "payment_method",
+        "profile_id",
+        "payment_method_type",
+        "card_network",
+        "day_of_week",
+        "hour_of_day",
+        "billing_country",
+        "card_bin",
+        "issuer_country",
+        "customer_type",
+        "percentile",
+        "distinct_count",
+        "authenticated_count",
+        "authorized_count",
+        "captured_count",
+        "success_count",
+        "recovered_count",
+        "customer_segment",
+        "decline_category",
+        "created_to_authorized_min",
+        "created_to_authorized_max",
+        "created_to_authorized_avg",
+        "created_to_captured_min",
+        "created_to_captured_max",
+        "created_to_captured_avg",
+        "routing_rule",
+        "zero_amount_count",
+        "time_to_first_payment",
+        "surcharged_count",
+        "fee_total",
+        "effective_fee_rate",
+        "error_count",
+        "discount_total",
+        "offered_count",
+        "used_count",
+        "challenged_count",
+        "challenged_latency_avg",
+        "frictionless_count",
+        "frictionless_latency_avg",
+        "previous_total",
+        "previous_count",
+        "retried_count",
+        "pending_duration_avg",
+        "failure_rank",
+        "total",
+        "count",
+        "start_bucket",
+        "end_bucket",
+    ];
+
+    pub fn unmapped_columns<'a>(columns: &[&'a str]) -> Vec<&'a str> {
+        columns
+            .iter()
+            .filter(|column| !Self::COLUMNS.contains(column))
+            .copied()
+            .collect()
+    }
+
+    /// Bucket the row falls in, i.e. the dimensions it was grouped by and its time range: the
+    /// requested time range without granularity, the bounds of its granularity bucket otherwise
+    fn bucket_identifier(
+        &self,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        storage_timezone: time::UtcOffset,
+    ) -> error_stack::Result<PaymentMetricsBucketIdentifier, PostProcessingError> {
+        let time_bucket = TimeRange {
+            start_time: match (granularity, self.start_bucket) {
+                (Some(g), Some(st)) => {
+                    g.clip_to_start(storage_time_to_utc(st, storage_timezone)?)?
+                }
+                _ => time_range.start_time,
+            },
+            end_time: granularity.as_ref().map_or_else(
+                || Ok(time_range.end_time),
+                |g| {
+                    self.end_bucket
+                        .map(|et| {
+                            storage_time_to_utc(et, storage_timezone)
+                                .and_then(|et| g.clip_to_end(et))
+                        })
+                        .transpose()
+                },
+            )?,
+        };
+        Ok(PaymentMetricsBucketIdentifier {
+            currency: self.currency.as_ref().map(|i| i.0),
+            status: self.status.as_ref().map(|i| i.0),
+            connector: self.connector.clone(),
+            auth_type: self.authentication_type.as_ref().map(|i| i.0),
+            payment_method: self.payment_method.clone(),
+            profile_id: self.profile_id.clone(),
+            payment_method_type: self.payment_method_type.clone(),
+            card_network: self.card_network.clone(),
+            day_of_week: self.day_of_week,
+            hour_of_day: self.hour_of_day,
+            billing_country: self.billing_country.as_ref().map(|i| i.0),
+            card_bin: self.card_bin.clone(),
+            issuer_country: self.issuer_country.clone(),
+            ..PaymentMetricsBucketIdentifier::new(time_bucket)
+        })
+    }
+}
+
+/// Request a payment metric query is built for. [`Self::apply_to`] adds the parts of the query
+/// shared by every payment metric, metrics only add their own columns and filters on top.
+#[derive(Debug, Clone, Copy)]
+pub struct MetricQuery<'a> {
+    pub dimensions: &'a [PaymentDimensions],
+    pub merchant_id: &'a str,
+    pub filters: &'a PaymentFilters,
+    pub granularity: &'a Option<Granularity>,
+    pub time_range: &'a TimeRange,
+}
+
+impl MetricQuery<'_> {
+    /// Selects the dimensions and the bounds of the time buckets, applies the request filters
+    /// and time range, and groups rows by the dimensions and the granularity. The storage
+    /// timezone and bucket column of the builder need to be set beforehand.
+    pub fn apply_to<T>(&self, builder: &mut QueryBuilder<T>) -> QueryResult<()>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        for dim in self.dimensions.iter() {
+            builder.add_select_column(dim)?;
+        }
+        builder.add_select_column(Aggregate::Min {
+            field: Column::CreatedAt,
+            alias: Some("start_bucket"),
+        })?;
+        builder.add_select_column(Aggregate::Max {
+            field: Column::CreatedAt,
+            alias: Some("end_bucket"),
+        })?;
+
+        self.filters.set_filter_clause(builder)?;
+
+        BaseFilters {
+            merchant_id: self.merchant_id,
+            time_range: self.time_range,
+        }
+        .apply_to(builder)?;
+
+        for dim in self.dimensions.iter() {
+            builder
+                .add_group_by_clause(dim)
+                .attach_printable("Error grouping by dimensions")?;
+        }
+
+        if let Some(granularity) = self.granularity.as_ref() {
+            granularity
+                .set_group_by_clause(builder)
+                .attach_printable("Error adding granularity")?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the query of a payment metric, keying every row by the bucket it falls in. Rows are
+/// bucketed by `granularity` within `time_range`, as requested from the metric.
+pub(super) async fn load_metric_rows<T>(
+    query_builder: &mut QueryBuilder<T>,
+    granularity: &Option<Granularity>,
+    time_range: &TimeRange,
+    pool: &T,
+) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>>
+where
+    T: AnalyticsDataSource + PaymentMetricAnalytics,
+    AnalyticsCollection: ToSql<T>,
+{
+    query_builder
+        .execute_query::<PaymentMetricRow, _>(pool)
+        .await
+        .change_context(MetricsError::QueryBuildingError)?
+        .change_context(MetricsError::QueryExecutionFailure)?
+        .into_iter()
+        .map(|row| {
+            let identifier =
+                row.bucket_identifier(granularity, time_range, pool.storage_timezone())?;
+            Ok((identifier, row))
+        })
+        .collect::<error_stack::Result<Vec<_>, PostProcessingError>>()
+        .change_context(MetricsError::PostProcessingFailure)
+}
+
 pub trait PaymentMetricAnalytics: LoadRow<PaymentMetricRow> {}
 
 #[async_trait::async_trait]
@@ -53,14 +377,22 @@ where
     ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>>;
 }
 
+/// Metric requested along with the request inputs it is computed with
+pub struct RequestedPaymentMetric<'a> {
+    pub metric: &'a PaymentMetrics,
+    pub params: &'a PaymentMetricParams,
+}
+
 #[async_trait::async_trait]
-impl<T> PaymentMetric<T> for PaymentMetrics
+impl<T> PaymentMetric<T> for RequestedPaymentMetric<'_>
 where
     T: AnalyticsDataSource + PaymentMetricAnalytics,
     PrimitiveDateTime: ToSql<T>,
     AnalyticsCollection: ToSql<T>,
     Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
     Aggregate<&'static str>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
 {
     async fn load_metrics(
         &self,
@@ -71,8 +403,8 @@ where
         time_range: &TimeRange,
         pool: &T,
     ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
-        match self {
-            Self::PaymentSuccessRate => {
+        match self.metric {
+            PaymentMetrics::PaymentSuccessRate => {
                 PaymentSuccessRate
                     .load_metrics(
                         dimensions,
@@ -84,7 +416,7 @@ where
                     )
                     .await
             }
-            Self::PaymentCount => {
+            PaymentMetrics::PaymentCount => {
                 PaymentCount
                     .load_metrics(
                         dimensions,
@@ -96,7 +428,7 @@ where
                     )
                     .await
             }
-            Self::PaymentSuccessCount => {
+            PaymentMetrics::PaymentSuccessCount => {
                 PaymentSuccessCount
                     .load_metrics(
                         dimensions,
@@ -108,7 +440,7 @@ where
                     )
                     .await
             }
-            Self::PaymentProcessedAmount => {
+            PaymentMetrics::PaymentProcessedAmount => {
                 PaymentProcessedAmount
                     .load_metrics(
                         dimensions,
@@ -120,7 +452,7 @@ where
                     )
                     .await
             }
-            Self::AvgTicketSize => {
+            PaymentMetrics::AvgTicketSize => {
                 AvgTicketSize
                     .load_metrics(
                         dimensions,
@@ -132,6 +464,763 @@ where
                     )
                     .await
             }
+            PaymentMetrics::NewVsReturningCustomers => {
+                NewVsReturningCustomers
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::AuthorizedCaptureFailureCount => {
+                AuthorizedCaptureFailureCount
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::ConnectorLatencyP95 => {
+                ConnectorLatencyP95
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::RetryRatio => {
+                RetryRatio
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::WalletTypeBreakdown => {
+                WalletTypeBreakdown
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::CardNetworkBreakdown => {
+                CardNetworkBreakdown
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::SmartRetrySuccessRate => {
+                SmartRetrySuccessRate
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::AvgCaptureGap => {
+                AvgCaptureGap
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::TopFailingConnectors => {
+                TopFailingConnectors
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::PaymentFunnel => {
+                PaymentFunnel
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::AvgRetriesBeforeSuccess => {
+                AvgRetriesBeforeSuccess
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::DailyGmv => {
+                DailyGmv
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::PaymentMethodSuccessRanking => {
+                PaymentMethodSuccessRanking
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::VolumeHeatmap => {
+                VolumeHeatmap
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::RecoveryRate => {
+                RecoveryRate
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::AvgValueByCustomerSegment => {
+                AvgValueByCustomerSegment {
+                    thresholds: self.params.customer_segment_thresholds,
+                }
+                .load_metrics(
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                    pool,
+                )
+                .await
+            }
+            PaymentMetrics::DeclineCategoryBreakdown => {
+                DeclineCategoryBreakdown::default()
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::ExchangeAdjustedTicketSize => {
+                ExchangeAdjustedTicketSize {
+                    rates: self.params.exchange_rates.clone(),
+                }
+                .load_metrics(
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                    pool,
+                )
+                .await
+            }
+            PaymentMetrics::LifecycleSpread => {
+                LifecycleSpread
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::SuccessRateByCountry => {
+                SuccessRateByCountry
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::RoutingFallbackDepth => {
+                RoutingFallbackDepth
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::ZeroAmountCount => {
+                ZeroAmountCount
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::TimeToFirstPayment => {
+                TimeToFirstPayment
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::SurchargeAdoptionRate => {
+                SurchargeAdoptionRate
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::EffectiveFeeRate => {
+                EffectiveFeeRate
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::ConnectorErrorRate => {
+                ConnectorErrorRate
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::MandateSuccessRate => {
+                MandateSuccessRate
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::AvgDiscount => {
+                AvgDiscount
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::PaymentMethodUsage => {
+                PaymentMethodUsage
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::AvgLineItems => {
+                AvgLineItems
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::SuccessRateByBin => {
+                SuccessRateByBin
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::AuthenticationLatency => {
+                AuthenticationLatency
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::AovTrend => {
+                AovTrend
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::CustomerLifetimeValue => {
+                CustomerLifetimeValue
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::DeclineRecoverySplit => {
+                DeclineRecoverySplit
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::PendingStateDuration => {
+                PendingStateDuration
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            PaymentMetrics::ApprovalRateByIssuerCountry => {
+                ApprovalRateByIssuerCountry
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use common_utils::errors::{CustomResult, ParsingError};
+    use strum::IntoEnumIterator;
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::analytics::{
+        query::snapshot::assert_query_snapshot, types::QueryExecutionError,
+        utils::get_populated_payment_dimensions, SqlxClient,
+    };
+
+    /// Data source answering every query with a single row, in which the fields of the given
+    /// dimensions are populated
+    struct PopulatedRowSource {
+        dimensions: Vec<PaymentDimensions>,
+    }
+
+    /// Builds a row with the fields of the dimensions populated. The match is exhaustive, so a
+    /// new dimension can't be added without the harness populating its field.
+    fn populated_row(dimensions: &[PaymentDimensions]) -> PaymentMetricRow {
+        let mut row = PaymentMetricRow {
+            count: Some(1),
+            total: Some(bigdecimal::BigDecimal::from(1)),
+            ..Default::default()
+        };
+        for dim in dimensions {
+            match dim {
+                PaymentDimensions::Connector => row.connector = Some("stripe".to_string()),
+                PaymentDimensions::PaymentMethod => row.payment_method = Some("card".to_string()),
+                PaymentDimensions::Currency => {
+                    row.currency = Some(DBEnumWrapper(storage_enums::Currency::USD))
+                }
+                PaymentDimensions::AuthType => {
+                    row.authentication_type =
+                        Some(DBEnumWrapper(storage_enums::AuthenticationType::NoThreeDs))
+                }
+                PaymentDimensions::PaymentStatus => {
+                    row.status = Some(DBEnumWrapper(storage_enums::AttemptStatus::Charged))
+                }
+                PaymentDimensions::ProfileId => row.profile_id = Some("profile_1".to_string()),
+                PaymentDimensions::PaymentMethodType => {
+                    row.payment_method_type = Some("credit".to_string())
+                }
+                PaymentDimensions::CardNetwork => row.card_network = Some("Visa".to_string()),
+                PaymentDimensions::DayOfWeek => row.day_of_week = Some(1),
+                PaymentDimensions::HourOfDay => row.hour_of_day = Some(12),
+                PaymentDimensions::BillingCountry => {
+                    row.billing_country = Some(DBEnumWrapper(storage_enums::CountryAlpha2::US))
+                }
+                PaymentDimensions::CardBin => row.card_bin = Some("424242".to_string()),
+                PaymentDimensions::IssuerCountry => row.issuer_country = Some("US".to_string()),
+            }
+        }
+        row
+    }
+
+    #[async_trait::async_trait]
+    impl AnalyticsDataSource for PopulatedRowSource {
+        type Row = PaymentMetricRow;
+
+        async fn load_results<T>(&self, _query: &str) -> CustomResult<Vec<T>, QueryExecutionError>
+        where
+            Self: LoadRow<T>,
+        {
+            vec![populated_row(&self.dimensions)]
+                .into_iter()
+                .map(Self::load_row)
+                .collect()
+        }
+    }
+
+    impl LoadRow<PaymentMetricRow> for PopulatedRowSource {
+        fn load_row(row: PaymentMetricRow) -> CustomResult<PaymentMetricRow, QueryExecutionError> {
+            Ok(row)
+        }
+    }
+
+    impl PaymentMetricAnalytics for PopulatedRowSource {}
+
+    impl ToSql<PopulatedRowSource> for AnalyticsCollection {
+        fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+            <Self as ToSql<SqlxClient>>::to_sql(self)
+        }
+    }
+
+    impl ToSql<PopulatedRowSource> for PrimitiveDateTime {
+        fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+            <Self as ToSql<SqlxClient>>::to_sql(self)
+        }
+    }
+
+    impl<A> ToSql<PopulatedRowSource> for Aggregate<A>
+    where
+        Self: ToSql<SqlxClient>,
+    {
+        fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+            <Self as ToSql<SqlxClient>>::to_sql(self)
         }
     }
+
+    impl GroupByClause<PopulatedRowSource> for Granularity {
+        // The rows don't depend on the query, so time buckets are left out
+        fn set_group_by_clause(
+            &self,
+            _builder: &mut QueryBuilder<PopulatedRowSource>,
+        ) -> QueryResult<()> {
+            Ok(())
+        }
+    }
+
+    /// Metrics which split their buckets by status themselves and fold the status into the
+    /// metric value, so the status of their buckets is deliberately left out
+    const STATUS_FOLDING_METRICS: &[PaymentMetrics] = &[
+        PaymentMetrics::PaymentSuccessRate,
+        PaymentMetrics::PaymentSuccessCount,
+        PaymentMetrics::PaymentProcessedAmount,
+    ];
+
+    /// Asserts that the buckets loaded by the metric carry every selected dimension, i.e. that
+    /// the metric maps the fields of the row to the bucket identifier
+    async fn assert_dimensions_mapped(metric: &PaymentMetrics, dimensions: &[PaymentDimensions]) {
+        let source = PopulatedRowSource {
+            dimensions: dimensions.to_vec(),
+        };
+        let time_range = TimeRange {
+            start_time: datetime!(2023-11-01 00:00:00),
+            end_time: Some(datetime!(2023-11-02 00:00:00)),
+        };
+        let buckets = RequestedPaymentMetric {
+            metric,
+            params: &PaymentMetricParams::default(),
+        }
+        .load_metrics(
+            dimensions,
+            "merchant_1",
+            &PaymentFilters::default(),
+            &None,
+            &time_range,
+            &source,
+        )
+        .await
+        .unwrap();
+        assert!(!buckets.is_empty(), "{metric} loaded no buckets");
+
+        let populated = get_populated_payment_dimensions(&buckets);
+        for dim in dimensions {
+            if *dim == PaymentDimensions::PaymentStatus && STATUS_FOLDING_METRICS.contains(metric) {
+                continue;
+            }
+            assert!(
+                populated.contains(dim),
+                "{metric} doesn't map the {dim} dimension to its buckets"
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_payment_metrics_map_every_dimension() {
+        let dimensions = PaymentDimensions::iter().collect::<Vec<_>>();
+        for metric in PaymentMetrics::iter() {
+            assert_dimensions_mapped(&metric, &dimensions).await;
+        }
+    }
+
+    #[test]
+    fn test_payment_metric_select_columns_map_to_row_fields() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        for dim in PaymentDimensions::iter() {
+            builder.add_select_column(dim).unwrap();
+        }
+        builder
+            .add_select_column(Aggregate::Sum {
+                field: Column::Amount,
+                alias: Some("total"),
+            })
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Min {
+                field: Column::CreatedAt,
+                alias: Some("start_bucket"),
+            })
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Max {
+                field: Column::CreatedAt,
+                alias: Some("end_bucket"),
+            })
+            .unwrap();
+
+        assert!(PaymentMetricRow::unmapped_columns(&builder.get_select_column_names()).is_empty());
+    }
+
+    #[test]
+    fn test_payment_metric_select_column_mismatch_is_detected() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Sum {
+                field: Column::Amount,
+                alias: Some("amount_total"),
+            })
+            .unwrap();
+
+        assert_eq!(
+            PaymentMetricRow::unmapped_columns(&builder.get_select_column_names()),
+            vec!["amount_total"]
+        );
+    }
+
+    #[test]
+    fn test_profile_id_dimension_is_grouped() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::ProfileId)
+            .unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::ProfileId)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT (SELECT profile_id FROM payment_intent \
+             WHERE payment_intent.payment_id = payment_attempt.payment_id \
+             AND payment_intent.merchant_id = payment_attempt.merchant_id) \
+             FROM payment_attempt GROUP BY (SELECT profile_id FROM payment_intent \
+             WHERE payment_intent.payment_id = payment_attempt.payment_id \
+             AND payment_intent.merchant_id = payment_attempt.merchant_id)"
+        );
+    }
+
+    #[test]
+    fn test_metric_query_applies_request() {
+        let filters = PaymentFilters {
+            currency: vec![storage_enums::Currency::USD],
+            ..Default::default()
+        };
+        let time_range = TimeRange {
+            start_time: datetime!(2023-11-01 00:00:00),
+            end_time: Some(datetime!(2023-11-02 00:00:00)),
+        };
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        MetricQuery {
+            dimensions: &[PaymentDimensions::Connector],
+            merchant_id: "merchant_1",
+            filters: &filters,
+            granularity: &Some(Granularity::OneHour),
+            time_range: &time_range,
+        }
+        .apply_to(&mut builder)
+        .unwrap();
+
+        assert_query_snapshot(
+            &mut builder,
+            r#"
+            SELECT connector, min(created_at) as start_bucket, max(created_at) as end_bucket
+            FROM payment_attempt
+            WHERE currency IN ('USD') AND merchant_id = 'merchant_1'
+            AND created_at >= '2023-11-01 0:00:00.0' AND created_at <= '2023-11-02 0:00:00.0'
+            GROUP BY connector, DATE_TRUNC('hour', modified_at)
+            "#,
+        );
+    }
+
+    #[test]
+    fn test_bucket_identifier_time_range() {
+        let row = PaymentMetricRow {
+            connector: Some("stripe".to_string()),
+            start_bucket: Some(datetime!(2023-11-01 10:20:00)),
+            end_bucket: Some(datetime!(2023-11-01 10:40:00)),
+            ..Default::default()
+        };
+        let time_range = TimeRange {
+            start_time: datetime!(2023-11-01 00:00:00),
+            end_time: Some(datetime!(2023-11-02 00:00:00)),
+        };
+
+        let identifier = row
+            .bucket_identifier(
+                &Some(Granularity::OneHour),
+                &time_range,
+                time::UtcOffset::UTC,
+            )
+            .unwrap();
+        assert_eq!(identifier.connector.as_deref(), Some("stripe"));
+        assert_eq!(
+            identifier.time_bucket,
+            TimeRange {
+                start_time: datetime!(2023-11-01 10:00:00),
+                end_time: Some(datetime!(2023-11-01 10:59:00)),
+            }
+        );
+
+        let identifier = row
+            .bucket_identifier(&None, &time_range, time::UtcOffset::UTC)
+            .unwrap();
+        assert_eq!(identifier.time_bucket, time_range);
+    }
 }
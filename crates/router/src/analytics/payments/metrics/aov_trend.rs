@@ -0,0 +1,181 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use router_env::logger;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetric, PaymentMetricRow};
+use crate::analytics::{
+    query::{
+        Aggregate, Column, GroupByClause, QueryBuilder, QueryBuildingError, QueryResult, ToSql,
+        Window,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+    utils::requires_currency_grouping,
+};
+
+/// Selects the total amount and count of the previous bucket with the same dimensions as
+/// `previous_total` and `previous_count`, buckets being ordered by their first attempt. Buckets
+/// without any attempt are skipped, so the previous bucket is the latest one having attempts.
+pub(super) fn add_previous_bucket_columns<T>(
+    builder: &mut QueryBuilder<T>,
+    dimensions: &[PaymentDimensions],
+) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    let order_by = <Aggregate<Column> as ToSql<T>>::to_sql(&Aggregate::Min {
+        field: Column::CreatedAt,
+        alias: None,
+    })
+    .change_context(QueryBuildingError::SqlSerializeError)
+    .attach_printable("Error serializing bucket order")?;
+    let aggregates = [
+        (
+            Aggregate::Sum {
+                field: Column::Amount,
+                alias: None,
+            },
+            "previous_total",
+        ),
+        (
+            Aggregate::Count {
+                field: None,
+                alias: None,
+            },
+            "previous_count",
+        ),
+    ];
+    for (aggregate, alias) in aggregates {
+        let aggregate = <Aggregate<Column> as ToSql<T>>::to_sql(&aggregate)
+            .change_context(QueryBuildingError::SqlSerializeError)
+            .attach_printable_lazy(|| format!("Error serializing {alias}"))?;
+        builder
+            .add_select_column(Window {
+                function: format!("lag({aggregate})"),
+                partition_by: dimensions.to_vec(),
+                order_by: Some(order_by.clone()),
+                alias: Some(alias),
+            })
+            .attach_printable_lazy(|| format!("Error adding {alias}"))?;
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub(super) struct AovTrend;
+
+impl AovTrend {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if requires_currency_grouping(
+            &query.filters.currency,
+            dimensions.contains(&PaymentDimensions::Currency),
+        ) {
+            logger::warn!(
+                "Grouping amounts by currency as the request is not limited to one currency"
+            );
+            dimensions.push(PaymentDimensions::Currency);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        query_builder.add_select_column(Aggregate::Sum {
+            field: Column::Amount,
+            alias: Some("total"),
+        })?;
+        query_builder.add_select_column(Aggregate::Count {
+            field: None,
+            alias: Some("count"),
+        })?;
+        add_previous_bucket_columns(&mut query_builder, &dimensions)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> PaymentMetric<T> for AovTrend
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_aov_trend_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        add_previous_bucket_columns(&mut builder, &[PaymentDimensions::Connector]).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Connector)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, \
+             lag(sum(amount)) OVER (PARTITION BY connector ORDER BY min(created_at)) \
+             as previous_total, \
+             lag(count(*)) OVER (PARTITION BY connector ORDER BY min(created_at)) \
+             as previous_count \
+             FROM payment_attempt GROUP BY connector"
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{lifecycle_spread::LIFECYCLE_GAP_SECONDS, MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Attempts split by whether they went through a 3DS challenge, along with the aliases their
+/// count and average latency are selected under. The connector only returns authentication
+/// data from a challenge redirection, the other attempts were authorized without friction.
+const AUTHENTICATION_FLOWS: &[(&str, &str, &str)] = &[
+    (
+        "authentication_data IS NOT NULL",
+        "challenged_count",
+        "challenged_latency_avg",
+    ),
+    (
+        "authentication_data IS NULL",
+        "frictionless_count",
+        "frictionless_latency_avg",
+    ),
+];
+
+/// Selects the number of attempts and their average latency for both the challenged and the
+/// frictionless flows, in a single scan
+pub(super) fn add_authentication_latency_columns<T>(
+    builder: &mut QueryBuilder<T>,
+) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    for (condition, count_alias, latency_alias) in AUTHENTICATION_FLOWS {
+        builder
+            .add_select_column(Aggregate::CountIf {
+                condition: *condition,
+                alias: Some(*count_alias),
+            })
+            .attach_printable_lazy(|| format!("Error adding {count_alias}"))?;
+        builder
+            .add_select_column(format!(
+                "avg({LIFECYCLE_GAP_SECONDS}) FILTER (WHERE {condition}) as {latency_alias}"
+            ))
+            .attach_printable_lazy(|| format!("Error adding {latency_alias}"))?;
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub(super) struct AuthenticationLatency;
+
+impl AuthenticationLatency {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<&'static str>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if !dimensions.contains(&PaymentDimensions::AuthType) {
+            dimensions.push(PaymentDimensions::AuthType);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        add_authentication_latency_columns(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for AuthenticationLatency
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_authentication_latency_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::AuthType)
+            .unwrap();
+        add_authentication_latency_columns(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::AuthType)
+            .unwrap();
+
+        let latency = "date_part('epoch', modified_at - created_at)";
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT authentication_type, \
+                 count(*) FILTER (WHERE authentication_data IS NOT NULL) as challenged_count, \
+                 avg({latency}) FILTER (WHERE authentication_data IS NOT NULL) \
+                 as challenged_latency_avg, \
+                 count(*) FILTER (WHERE authentication_data IS NULL) as frictionless_count, \
+                 avg({latency}) FILTER (WHERE authentication_data IS NULL) \
+                 as frictionless_latency_avg \
+                 FROM payment_attempt GROUP BY authentication_type"
+            )
+        );
+    }
+}
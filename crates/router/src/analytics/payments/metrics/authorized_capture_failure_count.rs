@@ -0,0 +1,134 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Attempts which were authorized on a manual capture flow and failed in the subsequent capture
+pub(super) fn set_capture_failure_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_filter_clause(
+            PaymentDimensions::PaymentStatus,
+            storage_enums::AttemptStatus::CaptureFailed,
+        )
+        .attach_printable("Error adding capture failed status filter")?;
+    builder
+        .add_filter_in_range_clause(
+            Column::CaptureMethod,
+            &[
+                storage_enums::CaptureMethod::Manual,
+                storage_enums::CaptureMethod::ManualMultiple,
+            ],
+        )
+        .attach_printable("Error adding manual capture method filter")
+}
+
+#[derive(Default)]
+pub(super) struct AuthorizedCaptureFailureCount;
+
+impl AuthorizedCaptureFailureCount {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if !dimensions.contains(&PaymentDimensions::Connector) {
+            dimensions.push(PaymentDimensions::Connector);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        query_builder.add_select_column(Aggregate::Count {
+            field: None,
+            alias: Some("count"),
+        })?;
+
+        set_capture_failure_filter_clause(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for AuthorizedCaptureFailureCount
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_capture_failure_filter_clause() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        set_capture_failure_filter_clause(&mut builder).unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt \
+             WHERE status = 'capture_failed' AND capture_method IN ('manual', 'manual_multiple')"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use router_env::logger;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+    utils::requires_currency_grouping,
+};
+
+/// Attempts captured, fully or partially, on a manual capture flow
+pub(super) fn set_manual_capture_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_filter_in_range_clause(
+            PaymentDimensions::PaymentStatus,
+            &[
+                storage_enums::AttemptStatus::Charged,
+                storage_enums::AttemptStatus::PartialCharged,
+            ],
+        )
+        .attach_printable("Error adding captured status filter")?;
+    builder
+        .add_filter_in_range_clause(
+            Column::CaptureMethod,
+            &[
+                storage_enums::CaptureMethod::Manual,
+                storage_enums::CaptureMethod::ManualMultiple,
+            ],
+        )
+        .attach_printable("Error adding manual capture method filter")
+}
+
+#[derive(Default)]
+pub(super) struct AvgCaptureGap;
+
+impl AvgCaptureGap {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if requires_currency_grouping(
+            &query.filters.currency,
+            dimensions.contains(&PaymentDimensions::Currency),
+        ) {
+            logger::warn!(
+                "Grouping amounts by currency as the request is not limited to one currency"
+            );
+            dimensions.push(PaymentDimensions::Currency);
+        }
+
+        if !dimensions.contains(&PaymentDimensions::Connector) {
+            dimensions.push(PaymentDimensions::Connector);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        query_builder.add_select_column(Aggregate::Sum {
+            field: Column::CaptureGap,
+            alias: Some("total"),
+        })?;
+        query_builder.add_select_column(Aggregate::Count {
+            field: None,
+            alias: Some("count"),
+        })?;
+
+        set_manual_capture_filter_clause(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for AvgCaptureGap
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_avg_capture_gap_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Sum {
+                field: Column::CaptureGap,
+                alias: Some("total"),
+            })
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        set_manual_capture_filter_clause(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Connector)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, sum((amount - (SELECT amount_captured FROM payment_intent \
+             WHERE payment_intent.payment_id = payment_attempt.payment_id \
+             AND payment_intent.merchant_id = payment_attempt.merchant_id))) as total, \
+             count(*) as count FROM payment_attempt \
+             WHERE status IN ('charged', 'partial_charged') \
+             AND capture_method IN ('manual', 'manual_multiple') GROUP BY connector"
+        );
+    }
+}
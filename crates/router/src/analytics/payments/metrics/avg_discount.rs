@@ -0,0 +1,164 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use router_env::logger;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, FilterTypes, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+    utils::requires_currency_grouping,
+};
+
+/// Discount applied to the attempt through an offer, in minor units
+const DISCOUNT_AMOUNT: &str = "offer_amount";
+
+/// Selects the summed discounts as `discount_total`, the summed amount as `total` and the
+/// number of attempts as `count`
+pub(super) fn add_discount_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    builder
+        .add_select_column(Aggregate::Sum {
+            field: DISCOUNT_AMOUNT,
+            alias: Some("discount_total"),
+        })
+        .attach_printable("Error adding discount sum")?;
+    builder
+        .add_select_column(Aggregate::Sum {
+            field: Column::Amount,
+            alias: Some("total"),
+        })
+        .attach_printable("Error adding discounted amount sum")?;
+    builder
+        .add_select_column(Aggregate::Count {
+            field: None::<Column>,
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding discounted attempt count")
+}
+
+/// Keeps the attempts a discount was applied to
+pub(super) fn set_discounted_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_custom_filter_clause(DISCOUNT_AMOUNT, &0_u64, FilterTypes::Gt)
+        .attach_printable("Error adding discounted attempt filter")
+}
+
+#[derive(Default)]
+pub(super) struct AvgDiscount;
+
+impl AvgDiscount {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<&'static str>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if requires_currency_grouping(
+            &query.filters.currency,
+            dimensions.contains(&PaymentDimensions::Currency),
+        ) {
+            logger::warn!(
+                "Grouping amounts by currency as the request is not limited to one currency"
+            );
+            dimensions.push(PaymentDimensions::Currency);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        add_discount_columns(&mut query_builder)?;
+
+        set_discounted_filter_clause(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for AvgDiscount
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_avg_discount_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Currency)
+            .unwrap();
+        add_discount_columns(&mut builder).unwrap();
+        set_discounted_filter_clause(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Currency)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT currency, sum(offer_amount) as discount_total, sum(amount) as total, \
+             count(*) as count FROM payment_attempt WHERE offer_amount > 0 GROUP BY currency"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{
+    routing_fallback_depth::set_first_attempt_filter_clause, MetricQuery, PaymentMetricRow,
+};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Total quantity of the line items ordered with the payment. The order details are only stored
+/// on the payment intent, payments without any are `NULL`.
+const LINE_ITEM_QUANTITY: &str = "(SELECT sum((item ->> 'quantity')::bigint) \
+FROM payment_intent, unnest(payment_intent.order_details) AS item \
+WHERE payment_intent.payment_id = payment_attempt.payment_id \
+AND payment_intent.merchant_id = payment_attempt.merchant_id)";
+
+/// Selects the line items of the payments summed up as `total` and the number of payments with
+/// order details as `count`
+pub(super) fn add_line_item_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    builder
+        .add_select_column(Aggregate::Sum {
+            field: LINE_ITEM_QUANTITY,
+            alias: Some("total"),
+        })
+        .attach_printable("Error adding line item sum")?;
+    builder
+        .add_select_column(Aggregate::Count {
+            field: Some(LINE_ITEM_QUANTITY),
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding payment count")
+}
+
+#[derive(Default)]
+pub(super) struct AvgLineItems;
+
+impl AvgLineItems {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<&'static str>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        query.apply_to(&mut query_builder)?;
+
+        add_line_item_columns(&mut query_builder)?;
+
+        // Every payment is counted once, through its first attempt
+        set_first_attempt_filter_clause(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for AvgLineItems
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_avg_line_items_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        add_line_item_columns(&mut builder).unwrap();
+
+        let items = "(SELECT sum((item ->> 'quantity')::bigint) \
+                     FROM payment_intent, unnest(payment_intent.order_details) AS item \
+                     WHERE payment_intent.payment_id = payment_attempt.payment_id \
+                     AND payment_intent.merchant_id = payment_attempt.merchant_id)";
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!("SELECT sum({items}) as total, count({items}) as count FROM payment_attempt")
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Successful attempts, each being the last attempt of an eventually successful payment
+pub(super) fn set_successful_attempt_filter_clause<T>(
+    builder: &mut QueryBuilder<T>,
+) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_filter_clause(
+            PaymentDimensions::PaymentStatus,
+            storage_enums::AttemptStatus::Charged,
+        )
+        .attach_printable("Error adding successful attempt filter")
+}
+
+/// Selects the number of attempts it took every successful payment to succeed as `total`, with
+/// the number of payments as `count`. The position of the successful attempt among the attempts
+/// of its payment is the number of attempts made until then.
+pub(super) fn add_attempts_until_success_columns<T>(
+    builder: &mut QueryBuilder<T>,
+) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    builder
+        .add_select_column(Aggregate::Sum {
+            field: Column::AttemptNumber,
+            alias: Some("total"),
+        })
+        .attach_printable("Error adding attempts until success sum")?;
+    builder
+        .add_select_column(Aggregate::Count {
+            field: None::<Column>,
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding successful payment count")
+}
+
+#[derive(Default)]
+pub(super) struct AvgRetriesBeforeSuccess;
+
+impl AvgRetriesBeforeSuccess {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if !dimensions.contains(&PaymentDimensions::Connector) {
+            dimensions.push(PaymentDimensions::Connector);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        add_attempts_until_success_columns(&mut query_builder)?;
+
+        set_successful_attempt_filter_clause(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for AvgRetriesBeforeSuccess
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::{
+        payments::accumulator::{PaymentMetricAccumulator, RetriesBeforeSuccessAccumulator},
+        SqlxClient,
+    };
+
+    #[test]
+    fn test_avg_retries_before_success_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        add_attempts_until_success_columns(&mut builder).unwrap();
+        set_successful_attempt_filter_clause(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Connector)
+            .unwrap();
+
+        let attempt_number = <Column as ToSql<SqlxClient>>::to_sql(&Column::AttemptNumber).unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT connector, sum({attempt_number}) as total, count(*) as count \
+                 FROM payment_attempt WHERE status = 'charged' GROUP BY connector"
+            )
+        );
+    }
+
+    #[test]
+    fn test_retries_before_success_excludes_successful_attempt() {
+        let mut accumulator = RetriesBeforeSuccessAccumulator::default();
+        accumulator.add_metrics_bucket(&PaymentMetricRow {
+            total: Some(bigdecimal::BigDecimal::from(7)),
+            count: Some(4),
+            ..Default::default()
+        });
+        assert_eq!(accumulator.collect(), Some(0.75));
+    }
+}
@@ -0,0 +1,223 @@
+use api_models::analytics::{
+    payments::{
+        CustomerSegmentThresholds, PaymentDimensions, PaymentFilters,
+        PaymentMetricsBucketIdentifier,
+    },
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use router_env::logger;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+    utils::requires_currency_grouping,
+};
+
+pub(super) const CUSTOMER_SEGMENT_ALIAS: &str = "customer_segment";
+
+/// Classifies an attempt as `low`, `medium` or `high` value by the lifetime value of its
+/// customer, as exposed by [`AnalyticsCollection::PaymentWithCustomerSpend`]
+pub(super) fn customer_segment_classification(thresholds: &CustomerSegmentThresholds) -> String {
+    let CustomerSegmentThresholds { medium, high } = thresholds;
+    format!(
+        "CASE WHEN lifetime_value >= {high} THEN 'high' \
+         WHEN lifetime_value >= {medium} THEN 'medium' ELSE 'low' END"
+    )
+}
+
+pub(super) fn set_charged_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_filter_clause(
+            PaymentDimensions::PaymentStatus,
+            storage_enums::AttemptStatus::Charged,
+        )
+        .attach_printable("Error adding charged status filter")
+}
+
+/// Segments are computed with the thresholds given in the metric request
+pub(super) struct AvgValueByCustomerSegment {
+    pub thresholds: CustomerSegmentThresholds,
+}
+
+impl AvgValueByCustomerSegment {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> =
+            QueryBuilder::new(AnalyticsCollection::PaymentWithCustomerSpend);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if requires_currency_grouping(
+            &query.filters.currency,
+            dimensions.contains(&PaymentDimensions::Currency),
+        ) {
+            logger::warn!(
+                "Grouping amounts by currency as the request is not limited to one currency"
+            );
+            dimensions.push(PaymentDimensions::Currency);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        query_builder.add_select_column(format!(
+            "{} as {CUSTOMER_SEGMENT_ALIAS}",
+            customer_segment_classification(&self.thresholds)
+        ))?;
+        query_builder.add_select_column(Aggregate::Sum {
+            field: Column::Amount,
+            alias: Some("total"),
+        })?;
+        query_builder.add_select_column(Aggregate::Count {
+            field: None,
+            alias: Some("count"),
+        })?;
+
+        set_charged_filter_clause(&mut query_builder)?;
+
+        query_builder
+            .add_group_by_clause(CUSTOMER_SEGMENT_ALIAS)
+            .attach_printable("Error grouping by customer segment")?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for AvgValueByCustomerSegment
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_customer_segment_classification() {
+        let thresholds = CustomerSegmentThresholds {
+            medium: 500,
+            high: 5000,
+        };
+        assert_eq!(
+            customer_segment_classification(&thresholds),
+            "CASE WHEN lifetime_value >= 5000 THEN 'high' \
+             WHEN lifetime_value >= 500 THEN 'medium' ELSE 'low' END"
+        );
+    }
+
+    #[test]
+    fn test_avg_value_by_customer_segment_query() {
+        let mut builder: QueryBuilder<SqlxClient> =
+            QueryBuilder::new(AnalyticsCollection::PaymentWithCustomerSpend);
+        let segment = customer_segment_classification(&CustomerSegmentThresholds::default());
+        builder
+            .add_select_column(format!("{segment} as {CUSTOMER_SEGMENT_ALIAS}"))
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Sum {
+                field: Column::Amount,
+                alias: Some("total"),
+            })
+            .unwrap();
+        set_charged_filter_clause(&mut builder).unwrap();
+        builder.add_group_by_clause(CUSTOMER_SEGMENT_ALIAS).unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT {segment} as customer_segment, sum(amount) as total FROM {} \
+                 WHERE status = 'charged' GROUP BY customer_segment",
+                <AnalyticsCollection as ToSql<SqlxClient>>::to_sql(
+                    &AnalyticsCollection::PaymentWithCustomerSpend
+                )
+                .unwrap()
+            )
+        );
+        assert_eq!(
+            builder.get_select_column_names(),
+            vec!["customer_segment", "total"]
+        );
+    }
+
+    #[test]
+    fn test_requested_thresholds_segment_customers() {
+        let thresholds = CustomerSegmentThresholds {
+            medium: 500,
+            high: 5000,
+        };
+        let time_range = TimeRange {
+            start_time: time::macros::datetime!(2023-11-01 00:00:00),
+            end_time: None,
+        };
+        let mut builder = AvgValueByCustomerSegment { thresholds }
+            .build_query::<SqlxClient>(
+                &MetricQuery {
+                    dimensions: &[],
+                    merchant_id: "merchant_1",
+                    filters: &PaymentFilters::default(),
+                    granularity: &None,
+                    time_range: &time_range,
+                },
+                time::UtcOffset::UTC,
+            )
+            .unwrap();
+
+        assert!(builder
+            .build_query()
+            .unwrap()
+            .contains(&customer_segment_classification(&thresholds)));
+    }
+}
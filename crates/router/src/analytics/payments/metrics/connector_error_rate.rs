@@ -0,0 +1,149 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::{
+    analytics::{
+        query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+        types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+    },
+    consts,
+};
+
+/// Attempts which failed for technical reasons rather than being declined: the connector timed
+/// out, its response couldn't be parsed for an error code or the outcome was left unresolved
+pub(super) fn technical_error_condition() -> String {
+    format!(
+        "(status = '{}' OR error_code IN ('{}', '{}'))",
+        storage_enums::AttemptStatus::Unresolved,
+        consts::REQUEST_TIMEOUT_ERROR_CODE,
+        consts::NO_ERROR_CODE,
+    )
+}
+
+/// Selects the number of attempts as `count` and of technically failed ones as `error_count`
+pub(super) fn add_connector_error_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    builder
+        .add_select_column(Aggregate::Count {
+            field: None::<Column>,
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding attempt count")?;
+    builder
+        .add_select_column(Aggregate::CountIf {
+            condition: technical_error_condition(),
+            alias: Some("error_count"),
+        })
+        .attach_printable("Error adding technical error count")
+}
+
+#[derive(Default)]
+pub(super) struct ConnectorErrorRate;
+
+impl ConnectorErrorRate {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<String>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if !dimensions.contains(&PaymentDimensions::Connector) {
+            dimensions.push(PaymentDimensions::Connector);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        add_connector_error_columns(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for ConnectorErrorRate
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_connector_error_rate_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        add_connector_error_columns(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Connector)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, count(*) as count, \
+             count(*) FILTER (WHERE (status = 'unresolved' \
+             OR error_code IN ('TIMEOUT', 'No error code'))) as error_count \
+             FROM payment_attempt GROUP BY connector"
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use router_env::logger;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{
+        month_of, storage_time_to_utc, Aggregate, Column, GroupByClause, PostProcessingError,
+        QueryBuilder, QueryResult, ToSql,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsError, MetricsResult},
+    utils::requires_currency_grouping,
+};
+
+/// Customers are grouped into cohorts by the month of their first charged payment
+const COHORT_MONTH: &str = "date_trunc('month', created_at)";
+
+/// Selects the summed lifetime value of the customers of the cohort as `total`, over the number
+/// of customers as `count`
+fn add_cohort_lifetime_value_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    builder
+        .add_select_column(Aggregate::Sum {
+            field: "lifetime_value",
+            alias: Some("total"),
+        })
+        .attach_printable("Error adding lifetime value sum")?;
+    builder
+        .add_select_column(Aggregate::Count {
+            field: None,
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding customer count")
+}
+
+/// Measured over the customers whose first payment falls in the requested time range, their
+/// lifetime value covering all of their payments, including the ones made after the range. The
+/// value is always per cohort month; the currency is the only dimension and filter applying to
+/// customers.
+#[derive(Default)]
+pub(super) struct CustomerLifetimeValue;
+
+impl CustomerLifetimeValue {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<&'static str>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> =
+            QueryBuilder::new(AnalyticsCollection::CustomerSpend);
+        query_builder.set_storage_timezone(storage_timezone);
+        query_builder.set_bucket_column(Column::CreatedAt);
+
+        let grouped_by_currency = query.dimensions.contains(&PaymentDimensions::Currency);
+        let group_by_currency = grouped_by_currency
+            || requires_currency_grouping(&query.filters.currency, grouped_by_currency);
+        if !grouped_by_currency && group_by_currency {
+            logger::warn!(
+                "Grouping amounts by currency as the request is not limited to one currency"
+            );
+        }
+        let dimensions: &[PaymentDimensions] = if group_by_currency {
+            &[PaymentDimensions::Currency]
+        } else {
+            &[]
+        };
+        let filters = PaymentFilters {
+            currency: query.filters.currency.clone(),
+            ..Default::default()
+        };
+
+        MetricQuery {
+            dimensions,
+            filters: &filters,
+            granularity: &None,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        add_cohort_lifetime_value_columns(&mut query_builder)?;
+        query_builder.add_group_by_clause(COHORT_MONTH)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for CustomerLifetimeValue
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+
+        query_builder
+            .execute_query::<PaymentMetricRow, _>(pool)
+            .await
+            .change_context(MetricsError::QueryBuildingError)?
+            .change_context(MetricsError::QueryExecutionFailure)?
+            .into_iter()
+            .map(|i| {
+                Ok((
+                    PaymentMetricsBucketIdentifier {
+                        currency: i.currency.as_ref().map(|i| i.0),
+                        ..PaymentMetricsBucketIdentifier::new(match i.start_bucket {
+                            Some(st) => {
+                                let (start, end) = month_of(st)?;
+                                TimeRange {
+                                    start_time: storage_time_to_utc(
+                                        start,
+                                        pool.storage_timezone(),
+                                    )?,
+                                    end_time: Some(storage_time_to_utc(
+                                        end,
+                                        pool.storage_timezone(),
+                                    )?),
+                                }
+                            }
+                            None => *time_range,
+                        })
+                    },
+                    i,
+                ))
+            })
+            .collect::<error_stack::Result<Vec<_>, PostProcessingError>>()
+            .change_context(MetricsError::PostProcessingFailure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_customer_lifetime_value_query() {
+        let mut builder: QueryBuilder<SqlxClient> =
+            QueryBuilder::new(AnalyticsCollection::CustomerSpend);
+        builder
+            .add_select_column(PaymentDimensions::Currency)
+            .unwrap();
+        add_cohort_lifetime_value_columns(&mut builder).unwrap();
+        builder
+            .add_filter_clause("merchant_id", "merchant_1")
+            .unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Currency)
+            .unwrap();
+        builder.add_group_by_clause(COHORT_MONTH).unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT currency, sum(lifetime_value) as total, count(*) as count \
+             FROM (SELECT payment_attempt.merchant_id, payment_intent.customer_id, \
+             payment_attempt.currency, min(payment_attempt.created_at) AS created_at, \
+             max(payment_attempt.modified_at) AS modified_at, \
+             sum(payment_attempt.amount) AS lifetime_value FROM payment_attempt \
+             JOIN payment_intent ON payment_intent.payment_id = payment_attempt.payment_id \
+             AND payment_intent.merchant_id = payment_attempt.merchant_id \
+             WHERE payment_attempt.status = 'charged' AND payment_intent.customer_id IS NOT NULL \
+             GROUP BY payment_attempt.merchant_id, payment_intent.customer_id, \
+             payment_attempt.currency) AS customer_spend \
+             WHERE merchant_id = 'merchant_1' \
+             GROUP BY currency, date_trunc('month', created_at)"
+        );
+    }
+}
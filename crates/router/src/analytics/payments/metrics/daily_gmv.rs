@@ -0,0 +1,172 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use router_env::logger;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Daily GMV is always bucketed by day, any other requested granularity is overridden
+pub(super) fn daily_gmv_granularity(requested: &Option<Granularity>) -> Granularity {
+    if !matches!(requested, None | Some(Granularity::OneDay)) {
+        logger::warn!(?requested, "Ignoring requested granularity for daily GMV");
+    }
+    Granularity::OneDay
+}
+
+/// Daily GMV is always broken down by currency, as amounts in different currencies cannot be
+/// added up
+pub(super) fn daily_gmv_dimensions(dimensions: &[PaymentDimensions]) -> Vec<PaymentDimensions> {
+    let mut dimensions = dimensions.to_vec();
+    if !dimensions.contains(&PaymentDimensions::Currency) {
+        dimensions.push(PaymentDimensions::Currency);
+    }
+    dimensions
+}
+
+pub(super) fn set_captured_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_filter_clause(
+            PaymentDimensions::PaymentStatus,
+            storage_enums::AttemptStatus::Charged,
+        )
+        .attach_printable("Error adding charged status filter")
+}
+
+#[derive(Default)]
+pub(super) struct DailyGmv;
+
+impl DailyGmv {
+    /// Bucketed by day whatever the requested granularity, see [`daily_gmv_granularity`]
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let granularity = Some(daily_gmv_granularity(query.granularity));
+        let dimensions = daily_gmv_dimensions(query.dimensions);
+
+        MetricQuery {
+            dimensions: &dimensions,
+            granularity: &granularity,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        query_builder.add_select_column(Aggregate::Sum {
+            field: Column::Amount,
+            alias: Some("total"),
+        })?;
+
+        set_captured_filter_clause(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for DailyGmv
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let granularity = &Some(daily_gmv_granularity(granularity));
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_daily_gmv_is_daily_and_grouped_by_currency() {
+        assert!(matches!(
+            daily_gmv_granularity(&Some(Granularity::OneHour)),
+            Granularity::OneDay
+        ));
+        assert!(matches!(daily_gmv_granularity(&None), Granularity::OneDay));
+
+        let dimensions = daily_gmv_dimensions(&[PaymentDimensions::Connector]);
+        assert_eq!(
+            dimensions,
+            vec![PaymentDimensions::Connector, PaymentDimensions::Currency]
+        );
+        assert_eq!(
+            daily_gmv_dimensions(&[PaymentDimensions::Currency]),
+            vec![PaymentDimensions::Currency]
+        );
+
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        for dim in dimensions.iter() {
+            builder.add_select_column(dim).unwrap();
+        }
+        builder
+            .add_select_column(Aggregate::Sum {
+                field: Column::Amount,
+                alias: Some("total"),
+            })
+            .unwrap();
+        set_captured_filter_clause(&mut builder).unwrap();
+        for dim in dimensions.iter() {
+            builder.add_group_by_clause(dim).unwrap();
+        }
+        daily_gmv_granularity(&Some(Granularity::OneHour))
+            .set_group_by_clause(&mut builder)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, currency, sum(amount) as total FROM payment_attempt \
+             WHERE status = 'charged' \
+             GROUP BY connector, currency, DATE_TRUNC('day', modified_at)"
+        );
+    }
+}
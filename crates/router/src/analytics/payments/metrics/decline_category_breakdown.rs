@@ -0,0 +1,256 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+pub(super) const DECLINE_CATEGORY_ALIAS: &str = "decline_category";
+
+/// Category of the declines whose error code matches none of the mapped codes
+const UNMAPPED_DECLINE_CATEGORY: &str = "other";
+
+/// Statuses of attempts declined by the connector
+const DECLINED_STATUSES: &[storage_enums::AttemptStatus] = &[
+    storage_enums::AttemptStatus::Failure,
+    storage_enums::AttemptStatus::AuthorizationFailed,
+];
+
+/// Connector error codes grouped under a decline category
+#[derive(Debug, Clone)]
+pub(super) struct DeclineCategory {
+    pub name: String,
+    pub codes: Vec<String>,
+}
+
+/// Decline categories checked in order, the first one listing the error code of an attempt
+/// wins. The default mapping covers common ISO 8583 and connector specific codes.
+#[derive(Debug, Clone)]
+pub(super) struct DeclineCategoryMapping {
+    pub categories: Vec<DeclineCategory>,
+}
+
+impl Default for DeclineCategoryMapping {
+    fn default() -> Self {
+        let category = |name: &str, codes: &[&str]| DeclineCategory {
+            name: name.to_string(),
+            codes: codes.iter().map(|code| code.to_string()).collect(),
+        };
+        Self {
+            categories: vec![
+                category("insufficient_funds", &["51", "insufficient_funds"]),
+                category(
+                    "fraud",
+                    &["41", "43", "59", "fraudulent", "lost_card", "stolen_card"],
+                ),
+                category("expired_card", &["54", "expired_card"]),
+            ],
+        }
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Maps the error code of an attempt to its decline category, falling back to `other`
+pub(super) fn decline_category_classification(mapping: &DeclineCategoryMapping) -> String {
+    let cases = mapping
+        .categories
+        .iter()
+        .filter(|category| !category.codes.is_empty())
+        .map(|category| {
+            let codes = category
+                .codes
+                .iter()
+                .map(|code| quote(code))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                "WHEN error_code IN ({codes}) THEN {}",
+                quote(&category.name)
+            )
+        })
+        .collect::<Vec<_>>();
+    if cases.is_empty() {
+        quote(UNMAPPED_DECLINE_CATEGORY)
+    } else {
+        format!(
+            "CASE {} ELSE {} END",
+            cases.join(" "),
+            quote(UNMAPPED_DECLINE_CATEGORY)
+        )
+    }
+}
+
+pub(super) fn set_declined_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_filter_in_range_clause(PaymentDimensions::PaymentStatus, DECLINED_STATUSES)
+        .attach_printable("Error adding declined status filter")
+}
+
+/// Declines are categorized with the default mapping, see [`DeclineCategoryMapping`]
+#[derive(Default)]
+pub(super) struct DeclineCategoryBreakdown {
+    mapping: DeclineCategoryMapping,
+}
+
+impl DeclineCategoryBreakdown {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+
+        query.apply_to(&mut query_builder)?;
+
+        query_builder.add_select_column(format!(
+            "{} as {DECLINE_CATEGORY_ALIAS}",
+            decline_category_classification(&self.mapping)
+        ))?;
+        query_builder.add_select_column(Aggregate::Count {
+            field: None,
+            alias: Some("count"),
+        })?;
+
+        set_declined_filter_clause(&mut query_builder)?;
+
+        query_builder
+            .add_group_by_clause(DECLINE_CATEGORY_ALIAS)
+            .attach_printable("Error grouping by decline category")?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for DeclineCategoryBreakdown
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_decline_category_classification() {
+        assert_eq!(
+            decline_category_classification(&DeclineCategoryMapping::default()),
+            "CASE WHEN error_code IN ('51', 'insufficient_funds') THEN 'insufficient_funds' \
+             WHEN error_code IN ('41', '43', '59', 'fraudulent', 'lost_card', 'stolen_card') \
+             THEN 'fraud' WHEN error_code IN ('54', 'expired_card') THEN 'expired_card' \
+             ELSE 'other' END"
+        );
+
+        let mapping = DeclineCategoryMapping {
+            categories: vec![
+                DeclineCategory {
+                    name: "do_not_honor".to_string(),
+                    codes: vec!["05".to_string(), "it's".to_string()],
+                },
+                DeclineCategory {
+                    name: "unused".to_string(),
+                    codes: Vec::new(),
+                },
+            ],
+        };
+        assert_eq!(
+            decline_category_classification(&mapping),
+            "CASE WHEN error_code IN ('05', 'it''s') THEN 'do_not_honor' ELSE 'other' END"
+        );
+        assert_eq!(
+            decline_category_classification(&DeclineCategoryMapping {
+                categories: Vec::new()
+            }),
+            "'other'"
+        );
+    }
+
+    #[test]
+    fn test_decline_category_breakdown_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        let mapping = DeclineCategoryMapping {
+            categories: vec![DeclineCategory {
+                name: "expired_card".to_string(),
+                codes: vec!["54".to_string()],
+            }],
+        };
+        builder
+            .add_select_column(format!(
+                "{} as {DECLINE_CATEGORY_ALIAS}",
+                decline_category_classification(&mapping)
+            ))
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        set_declined_filter_clause(&mut builder).unwrap();
+        builder.add_group_by_clause(DECLINE_CATEGORY_ALIAS).unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT CASE WHEN error_code IN ('54') THEN 'expired_card' ELSE 'other' END \
+             as decline_category, count(*) as count FROM payment_attempt \
+             WHERE status IN ('failure', 'authorization_failed') GROUP BY decline_category"
+        );
+        assert_eq!(
+            builder.get_select_column_names(),
+            vec!["decline_category", "count"]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{
+    decline_category_breakdown::{
+        decline_category_classification, set_declined_filter_clause, DeclineCategoryMapping,
+        DECLINE_CATEGORY_ALIAS,
+    },
+    routing_fallback_depth::set_first_attempt_filter_clause,
+    MetricQuery, PaymentMetricRow,
+};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Whether the payment was attempted again after the declined attempt, with any outcome
+const RETRIED_CONDITION: &str = "EXISTS (SELECT 1 \
+FROM payment_attempt AS later_attempt \
+WHERE later_attempt.payment_id = payment_attempt.payment_id \
+AND later_attempt.merchant_id = payment_attempt.merchant_id \
+AND later_attempt.created_at > payment_attempt.created_at)";
+
+/// Selects the declined payments as `count` and the ones retried afterwards as `retried_count`.
+/// The others were abandoned after the decline.
+fn add_decline_recovery_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    builder
+        .add_select_column(Aggregate::Count {
+            field: None,
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding declined payment count")?;
+    builder
+        .add_select_column(Aggregate::CountIf {
+            condition: RETRIED_CONDITION,
+            alias: Some("retried_count"),
+        })
+        .attach_printable("Error adding retried payment count")
+}
+
+/// Measured over the payments whose first attempt was declined. Declines are categorized with
+/// the default mapping, see [`DeclineCategoryMapping`].
+#[derive(Default)]
+pub(super) struct DeclineRecoverySplit {
+    mapping: DeclineCategoryMapping,
+}
+
+impl DeclineRecoverySplit {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<&'static str>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+
+        query.apply_to(&mut query_builder)?;
+
+        query_builder.add_select_column(format!(
+            "{} as {DECLINE_CATEGORY_ALIAS}",
+            decline_category_classification(&self.mapping)
+        ))?;
+        add_decline_recovery_columns(&mut query_builder)?;
+
+        set_first_attempt_filter_clause(&mut query_builder)?;
+        set_declined_filter_clause(&mut query_builder)?;
+
+        query_builder
+            .add_group_by_clause(DECLINE_CATEGORY_ALIAS)
+            .attach_printable("Error grouping by decline category")?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for DeclineRecoverySplit
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::{
+        payments::metrics::decline_category_breakdown::DeclineCategory, SqlxClient,
+    };
+
+    #[test]
+    fn test_decline_recovery_split_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        let mapping = DeclineCategoryMapping {
+            categories: vec![DeclineCategory {
+                name: "expired_card".to_string(),
+                codes: vec!["54".to_string()],
+            }],
+        };
+        builder
+            .add_select_column(format!(
+                "{} as {DECLINE_CATEGORY_ALIAS}",
+                decline_category_classification(&mapping)
+            ))
+            .unwrap();
+        add_decline_recovery_columns(&mut builder).unwrap();
+        set_first_attempt_filter_clause(&mut builder).unwrap();
+        set_declined_filter_clause(&mut builder).unwrap();
+        builder.add_group_by_clause(DECLINE_CATEGORY_ALIAS).unwrap();
+
+        let attempt_number = <Column as ToSql<SqlxClient>>::to_sql(&Column::AttemptNumber).unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT CASE WHEN error_code IN ('54') THEN 'expired_card' ELSE 'other' END \
+                 as decline_category, count(*) as count, \
+                 count(*) FILTER (WHERE EXISTS (SELECT 1 FROM payment_attempt AS later_attempt \
+                 WHERE later_attempt.payment_id = payment_attempt.payment_id \
+                 AND later_attempt.merchant_id = payment_attempt.merchant_id \
+                 AND later_attempt.created_at > payment_attempt.created_at)) as retried_count \
+                 FROM payment_attempt \
+                 WHERE {attempt_number} = '1' AND status IN ('failure', 'authorization_failed') \
+                 GROUP BY decline_category"
+            )
+        );
+    }
+}
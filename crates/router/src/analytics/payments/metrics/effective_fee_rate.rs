@@ -0,0 +1,162 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{
+        percentage_expression, Aggregate, Column, GroupByClause, QueryBuilder, QueryBuildingError,
+        QueryResult, ToSql,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// The surcharge is the only fee recorded on payment attempts, processing fees charged by the
+/// connectors aren't stored
+const FEE_AMOUNT: &str = "surcharge_amount";
+
+/// Selects the summed fees as `fee_total`, the summed amount as `total` and the fees as a
+/// percentage of the amount as `effective_fee_rate`. The rate is `NULL` for buckets without any
+/// amount, rather than failing the query with a division by zero.
+pub(super) fn add_effective_fee_rate_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    builder
+        .add_select_column(Aggregate::Sum {
+            field: FEE_AMOUNT,
+            alias: Some("fee_total"),
+        })
+        .attach_printable("Error adding fee sum")?;
+    builder
+        .add_select_column(Aggregate::Sum {
+            field: Column::Amount,
+            alias: Some("total"),
+        })
+        .attach_printable("Error adding amount sum")?;
+
+    // Aliases cannot be used within expressions, so the aggregates are repeated for the rate
+    let fee_total = <Aggregate<&'static str> as ToSql<T>>::to_sql(&Aggregate::Sum {
+        field: FEE_AMOUNT,
+        alias: None,
+    })
+    .change_context(QueryBuildingError::SqlSerializeError)
+    .attach_printable("Error serializing fee sum")?;
+    let total = <Aggregate<Column> as ToSql<T>>::to_sql(&Aggregate::Sum {
+        field: Column::Amount,
+        alias: None,
+    })
+    .change_context(QueryBuildingError::SqlSerializeError)
+    .attach_printable("Error serializing amount sum")?;
+    builder
+        .add_select_column(format!(
+            "{} as effective_fee_rate",
+            percentage_expression(&fee_total, &total)
+        ))
+        .attach_printable("Error adding effective fee rate")
+}
+
+#[derive(Default)]
+pub(super) struct EffectiveFeeRate;
+
+impl EffectiveFeeRate {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<&'static str>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if !dimensions.contains(&PaymentDimensions::Connector) {
+            dimensions.push(PaymentDimensions::Connector);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        add_effective_fee_rate_columns(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for EffectiveFeeRate
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_effective_fee_rate_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        add_effective_fee_rate_columns(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Connector)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, sum(surcharge_amount) as fee_total, sum(amount) as total, \
+             sum(surcharge_amount) * 100.0 / NULLIF(sum(amount), 0) as effective_fee_rate \
+             FROM payment_attempt GROUP BY connector"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use api_models::analytics::{
+    payments::{ExchangeRates, PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+    utils::currency_decimal_places,
+};
+
+/// Converts the minor unit amount of an attempt into major units of the base currency. Amounts
+/// in currencies without a rate are `NULL`, so they are left out of both the sum and the count.
+pub(super) fn exchange_adjusted_amount(rates: &ExchangeRates) -> String {
+    let base_rate = (rates.base, bigdecimal::BigDecimal::from(1));
+    let conversions = std::iter::once(&base_rate)
+        .chain(
+            rates
+                .rates
+                .iter()
+                .filter(|(currency, _)| *currency != rates.base),
+        )
+        .map(|(currency, rate)| {
+            // Multiplying by 10^-decimal_places is exact, unlike dividing by 10^decimal_places
+            let minor_unit_scale = bigdecimal::BigDecimal::new(
+                1.into(),
+                i64::from(currency_decimal_places(*currency)),
+            );
+            format!(
+                "WHEN '{currency}' THEN amount * {}",
+                rate * minor_unit_scale
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("CASE currency {conversions} END")
+}
+
+/// Converts with the rates given in the metric request
+pub(super) struct ExchangeAdjustedTicketSize {
+    pub rates: ExchangeRates,
+}
+
+impl ExchangeAdjustedTicketSize {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<String>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+
+        query.apply_to(&mut query_builder)?;
+
+        let amount = exchange_adjusted_amount(&self.rates);
+        query_builder.add_select_column(Aggregate::Sum {
+            field: amount.clone(),
+            alias: Some("total"),
+        })?;
+        query_builder.add_select_column(Aggregate::Count {
+            field: Some(amount),
+            alias: Some("count"),
+        })?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for ExchangeAdjustedTicketSize
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    fn rates() -> ExchangeRates {
+        ExchangeRates {
+            base: storage_enums::Currency::USD,
+            rates: vec![
+                (
+                    storage_enums::Currency::EUR,
+                    bigdecimal::BigDecimal::from_str("1.1").unwrap(),
+                ),
+                (
+                    storage_enums::Currency::JPY,
+                    bigdecimal::BigDecimal::from_str("0.0067").unwrap(),
+                ),
+                (
+                    storage_enums::Currency::USD,
+                    bigdecimal::BigDecimal::from_str("2").unwrap(),
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_exchange_adjusted_amount() {
+        assert_eq!(
+            exchange_adjusted_amount(&rates()),
+            "CASE currency WHEN 'USD' THEN amount * 0.01 WHEN 'EUR' THEN amount * 0.011 \
+             WHEN 'JPY' THEN amount * 0.0067 END"
+        );
+        assert_eq!(
+            exchange_adjusted_amount(&ExchangeRates::default()),
+            "CASE currency WHEN 'USD' THEN amount * 0.01 END"
+        );
+    }
+
+    #[test]
+    fn test_exchange_adjusted_ticket_size_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        let amount = exchange_adjusted_amount(&rates());
+        builder
+            .add_select_column(Aggregate::Sum {
+                field: amount.clone(),
+                alias: Some("total"),
+            })
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: Some(amount.clone()),
+                alias: Some("count"),
+            })
+            .unwrap();
+
+        // Amounts are converted row by row, before they're summed up and averaged
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!("SELECT sum({amount}) as total, count({amount}) as count FROM payment_attempt")
+        );
+    }
+
+    #[test]
+    fn test_requested_rates_convert_amounts() {
+        let time_range = TimeRange {
+            start_time: time::macros::datetime!(2023-11-01 00:00:00),
+            end_time: None,
+        };
+        let mut builder = ExchangeAdjustedTicketSize { rates: rates() }
+            .build_query::<SqlxClient>(
+                &MetricQuery {
+                    dimensions: &[],
+                    merchant_id: "merchant_1",
+                    filters: &PaymentFilters::default(),
+                    granularity: &None,
+                    time_range: &time_range,
+                },
+                time::UtcOffset::UTC,
+            )
+            .unwrap();
+
+        assert!(builder.build_query().unwrap().contains(&format!(
+            "sum({}) as total",
+            exchange_adjusted_amount(&rates())
+        )));
+    }
+}
@@ -0,0 +1,180 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums::AttemptStatus;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{
+        Aggregate, Column, GroupByClause, QueryBuilder, QueryBuildingError, QueryResult, ToSql,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Seconds from the creation of an attempt to its latest status change, as a double precision
+/// value so the aggregates over it are too
+pub(super) const LIFECYCLE_GAP_SECONDS: &str = "date_part('epoch', modified_at - created_at)";
+
+/// Lifecycle transitions, by the status reached and the prefix of the aliases they're selected
+/// under. Attempts only keep the time of their latest status change, so every transition is
+/// measured from the creation of the attempt.
+const LIFECYCLE_TRANSITIONS: &[(AttemptStatus, &str, &str)] = &[
+    (
+        AttemptStatus::Authorized,
+        "created_to_authorized",
+        "authorized_count",
+    ),
+    (
+        AttemptStatus::Charged,
+        "created_to_captured",
+        "captured_count",
+    ),
+];
+
+/// Selects, for every lifecycle transition, the number of attempts which went through it along
+/// with the min, max and average time it took them, all in a single scan
+pub(super) fn add_lifecycle_spread_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    let status = <PaymentDimensions as ToSql<T>>::to_sql(&PaymentDimensions::PaymentStatus)
+        .change_context(QueryBuildingError::SqlSerializeError)
+        .attach_printable("Error serializing status column")?;
+    for (reached_status, transition, count_alias) in LIFECYCLE_TRANSITIONS {
+        let condition = format!("{status} = '{reached_status}'");
+        builder
+            .add_select_column(Aggregate::CountIf {
+                condition: condition.clone(),
+                alias: Some(*count_alias),
+            })
+            .attach_printable_lazy(|| format!("Error adding {transition} count"))?;
+        for aggregate in ["min", "max", "avg"] {
+            builder
+                .add_select_column(format!(
+                    "{aggregate}({LIFECYCLE_GAP_SECONDS}) FILTER (WHERE {condition}) \
+                     as {transition}_{aggregate}"
+                ))
+                .attach_printable_lazy(|| format!("Error adding {transition} {aggregate}"))?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub(super) struct LifecycleSpread;
+
+impl LifecycleSpread {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<String>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+
+        query.apply_to(&mut query_builder)?;
+
+        add_lifecycle_spread_columns(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for LifecycleSpread
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_lifecycle_spread_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        add_lifecycle_spread_columns(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Connector)
+            .unwrap();
+
+        let gap = LIFECYCLE_GAP_SECONDS;
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT connector, \
+                 count(*) FILTER (WHERE status = 'authorized') as authorized_count, \
+                 min({gap}) FILTER (WHERE status = 'authorized') as created_to_authorized_min, \
+                 max({gap}) FILTER (WHERE status = 'authorized') as created_to_authorized_max, \
+                 avg({gap}) FILTER (WHERE status = 'authorized') as created_to_authorized_avg, \
+                 count(*) FILTER (WHERE status = 'charged') as captured_count, \
+                 min({gap}) FILTER (WHERE status = 'charged') as created_to_captured_min, \
+                 max({gap}) FILTER (WHERE status = 'charged') as created_to_captured_max, \
+                 avg({gap}) FILTER (WHERE status = 'charged') as created_to_captured_avg \
+                 FROM payment_attempt GROUP BY connector"
+            )
+        );
+        assert_eq!(
+            builder.get_select_column_names(),
+            vec![
+                "connector",
+                "authorized_count",
+                "created_to_authorized_min",
+                "created_to_authorized_max",
+                "created_to_authorized_avg",
+                "captured_count",
+                "created_to_captured_min",
+                "created_to_captured_max",
+                "created_to_captured_avg",
+            ]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums::AttemptStatus;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{
+        Aggregate, Column, GroupByClause, QueryBuilder, QueryBuildingError, QueryResult, ToSql,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Selects the number of attempts as `count` and of successful ones as `success_count`
+pub(super) fn add_mandate_success_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    let status = <PaymentDimensions as ToSql<T>>::to_sql(&PaymentDimensions::PaymentStatus)
+        .change_context(QueryBuildingError::SqlSerializeError)
+        .attach_printable("Error serializing status column")?;
+
+    builder
+        .add_select_column(Aggregate::Count {
+            field: None::<Column>,
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding attempt count")?;
+    builder
+        .add_select_column(Aggregate::CountIf {
+            condition: format!("{status} = '{}'", AttemptStatus::Charged),
+            alias: Some("success_count"),
+        })
+        .attach_printable("Error adding successful attempt count")
+}
+
+/// Keeps the attempts of recurring payments charged through a mandate, off session
+pub(super) fn set_off_session_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_bool_filter_clause(Column::OffSession, &true)
+        .attach_printable("Error adding off session filter")
+}
+
+#[derive(Default)]
+pub(super) struct MandateSuccessRate;
+
+impl MandateSuccessRate {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<String>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if !dimensions.contains(&PaymentDimensions::Connector) {
+            dimensions.push(PaymentDimensions::Connector);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        add_mandate_success_columns(&mut query_builder)?;
+
+        set_off_session_filter_clause(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for MandateSuccessRate
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_mandate_success_rate_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        add_mandate_success_columns(&mut builder).unwrap();
+        set_off_session_filter_clause(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Connector)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, count(*) as count, \
+             count(*) FILTER (WHERE status = 'charged') as success_count \
+             FROM payment_attempt WHERE (SELECT off_session FROM payment_intent \
+             WHERE payment_intent.payment_id = payment_attempt.payment_id \
+             AND payment_intent.merchant_id = payment_attempt.merchant_id) = true \
+             GROUP BY connector"
+        );
+    }
+}
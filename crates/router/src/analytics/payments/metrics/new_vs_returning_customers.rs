@@ -0,0 +1,183 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Classifies an attempt as `returning` when the customer of its payment has an older payment
+/// with the same merchant, and as `new` otherwise.
+pub(super) const CUSTOMER_TYPE_CLASSIFICATION: &str = "CASE WHEN EXISTS (\
+SELECT 1 FROM payment_intent AS current_intent \
+JOIN payment_intent AS prior_intent \
+ON prior_intent.merchant_id = current_intent.merchant_id \
+AND prior_intent.customer_id = current_intent.customer_id \
+AND prior_intent.created_at < current_intent.created_at \
+WHERE current_intent.payment_id = payment_attempt.payment_id \
+AND current_intent.merchant_id = payment_attempt.merchant_id\
+) THEN 'returning' ELSE 'new' END";
+
+pub(super) const CUSTOMER_TYPE_ALIAS: &str = "customer_type";
+
+#[derive(Default)]
+pub(super) struct NewVsReturningCustomers;
+
+impl NewVsReturningCustomers {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+
+        query.apply_to(&mut query_builder)?;
+
+        query_builder.add_select_column(format!(
+            "{CUSTOMER_TYPE_CLASSIFICATION} as {CUSTOMER_TYPE_ALIAS}"
+        ))?;
+        query_builder.add_select_column(Aggregate::DistinctCount {
+            field: Column::PaymentId,
+            alias: Some("count"),
+        })?;
+
+        query_builder
+            .add_group_by_clause(CUSTOMER_TYPE_ALIAS)
+            .attach_printable("Error grouping by customer type")?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for NewVsReturningCustomers
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use common_enums::enums as storage_enums;
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_customer_type_classification_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(format!(
+                "{CUSTOMER_TYPE_CLASSIFICATION} as {CUSTOMER_TYPE_ALIAS}"
+            ))
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::DistinctCount {
+                field: Column::PaymentId,
+                alias: Some("count"),
+            })
+            .unwrap();
+        builder
+            .add_filter_clause(Column::MerchantId, "merchant_1")
+            .unwrap();
+        builder.add_group_by_clause(CUSTOMER_TYPE_ALIAS).unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT CASE WHEN EXISTS (SELECT 1 FROM payment_intent AS current_intent \
+             JOIN payment_intent AS prior_intent \
+             ON prior_intent.merchant_id = current_intent.merchant_id \
+             AND prior_intent.customer_id = current_intent.customer_id \
+             AND prior_intent.created_at < current_intent.created_at \
+             WHERE current_intent.payment_id = payment_attempt.payment_id \
+             AND current_intent.merchant_id = payment_attempt.merchant_id) \
+             THEN 'returning' ELSE 'new' END as customer_type, \
+             count(DISTINCT payment_id) as count \
+             FROM payment_attempt WHERE merchant_id = 'merchant_1' GROUP BY customer_type"
+        );
+    }
+
+    #[test]
+    fn test_new_vs_returning_customers_query() {
+        let filters = PaymentFilters {
+            currency: vec![storage_enums::Currency::USD],
+            ..Default::default()
+        };
+        let time_range = TimeRange {
+            start_time: datetime!(2023-11-01 00:00:00),
+            end_time: Some(datetime!(2023-11-02 00:00:00)),
+        };
+        let mut builder = NewVsReturningCustomers
+            .build_query::<SqlxClient>(
+                &MetricQuery {
+                    dimensions: &[PaymentDimensions::Connector],
+                    merchant_id: "merchant_1",
+                    filters: &filters,
+                    granularity: &Some(Granularity::OneDay),
+                    time_range: &time_range,
+                },
+                time::UtcOffset::UTC,
+            )
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, min(created_at) as start_bucket, max(created_at) as end_bucket, \
+             CASE WHEN EXISTS (SELECT 1 FROM payment_intent AS current_intent \
+             JOIN payment_intent AS prior_intent \
+             ON prior_intent.merchant_id = current_intent.merchant_id \
+             AND prior_intent.customer_id = current_intent.customer_id \
+             AND prior_intent.created_at < current_intent.created_at \
+             WHERE current_intent.payment_id = payment_attempt.payment_id \
+             AND current_intent.merchant_id = payment_attempt.merchant_id) \
+             THEN 'returning' ELSE 'new' END as customer_type, \
+             count(DISTINCT payment_id) as count FROM payment_attempt \
+             WHERE currency IN ('USD') AND merchant_id = 'merchant_1' \
+             AND created_at >= '2023-11-01 0:00:00.0' AND created_at <= '2023-11-02 0:00:00.0' \
+             GROUP BY connector, DATE_TRUNC('day', modified_at), customer_type"
+        );
+    }
+}
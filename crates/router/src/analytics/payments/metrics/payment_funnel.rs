@@ -0,0 +1,187 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums::AttemptStatus;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{
+        Aggregate, Column, GroupByClause, QueryBuilder, QueryBuildingError, QueryResult, ToSql,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Attempt statuses reached once the customer is authenticated, i.e. every later stage
+const AUTHENTICATED_STATUSES: &[AttemptStatus] = &[
+    AttemptStatus::AuthenticationSuccessful,
+    AttemptStatus::Authorizing,
+    AttemptStatus::AuthorizationFailed,
+    AttemptStatus::CodInitiated,
+    AttemptStatus::Authorized,
+    AttemptStatus::VoidInitiated,
+    AttemptStatus::Voided,
+    AttemptStatus::VoidFailed,
+    AttemptStatus::CaptureInitiated,
+    AttemptStatus::CaptureFailed,
+    AttemptStatus::Charged,
+    AttemptStatus::PartialCharged,
+    AttemptStatus::PartialChargedAndChargeable,
+    AttemptStatus::AutoRefunded,
+];
+
+/// Attempt statuses reached once the payment is authorized
+const AUTHORIZED_STATUSES: &[AttemptStatus] = &[
+    AttemptStatus::Authorized,
+    AttemptStatus::VoidInitiated,
+    AttemptStatus::Voided,
+    AttemptStatus::VoidFailed,
+    AttemptStatus::CaptureInitiated,
+    AttemptStatus::CaptureFailed,
+    AttemptStatus::Charged,
+    AttemptStatus::PartialCharged,
+    AttemptStatus::PartialChargedAndChargeable,
+    AttemptStatus::AutoRefunded,
+];
+
+/// Attempt statuses reached once the payment is captured, fully or partially
+const CAPTURED_STATUSES: &[AttemptStatus] = &[
+    AttemptStatus::Charged,
+    AttemptStatus::PartialCharged,
+    AttemptStatus::PartialChargedAndChargeable,
+    AttemptStatus::AutoRefunded,
+];
+
+/// Selects the number of attempts at every funnel stage. Only the latest status of an attempt
+/// is stored, so an attempt counts towards a stage if its status is that stage or a later one.
+/// Every created attempt is counted as `count`.
+pub(super) fn add_funnel_stage_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    builder
+        .add_select_column(Aggregate::Count {
+            field: None::<Column>,
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding created stage count")?;
+
+    let status = <PaymentDimensions as ToSql<T>>::to_sql(&PaymentDimensions::PaymentStatus)
+        .change_context(QueryBuildingError::SqlSerializeError)
+        .attach_printable("Error serializing status column")?;
+    for (statuses, alias) in [
+        (AUTHENTICATED_STATUSES, "authenticated_count"),
+        (AUTHORIZED_STATUSES, "authorized_count"),
+        (CAPTURED_STATUSES, "captured_count"),
+    ] {
+        let statuses = statuses
+            .iter()
+            .map(|status| format!("'{status}'"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        builder
+            .add_select_column(Aggregate::CountIf {
+                condition: format!("{status} IN ({statuses})"),
+                alias: Some(alias),
+            })
+            .attach_printable_lazy(|| format!("Error adding {alias} stage count"))?;
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub(super) struct PaymentFunnel;
+
+impl PaymentFunnel {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<String>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+
+        query.apply_to(&mut query_builder)?;
+
+        add_funnel_stage_columns(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for PaymentFunnel
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_payment_funnel_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        add_funnel_stage_columns(&mut builder).unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT count(*) as count, \
+             count(*) FILTER (WHERE status IN ('authentication_successful', 'authorizing', \
+             'authorization_failed', 'cod_initiated', 'authorized', 'void_initiated', 'voided', \
+             'void_failed', 'capture_initiated', 'capture_failed', 'charged', 'partial_charged', \
+             'partial_charged_and_chargeable', 'auto_refunded')) as authenticated_count, \
+             count(*) FILTER (WHERE status IN ('authorized', 'void_initiated', 'voided', \
+             'void_failed', 'capture_initiated', 'capture_failed', 'charged', 'partial_charged', \
+             'partial_charged_and_chargeable', 'auto_refunded')) as authorized_count, \
+             count(*) FILTER (WHERE status IN ('charged', 'partial_charged', \
+             'partial_charged_and_chargeable', 'auto_refunded')) as captured_count \
+             FROM payment_attempt"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums::AttemptStatus;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{
+        percentage_expression, Aggregate, Column, GroupByClause, Order, QueryBuilder,
+        QueryBuildingError, QueryResult, ToSql,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Selects the number of attempts as `count` and of successful ones as `success_count`, ordering
+/// the rows by their success rate, best performing first
+pub(super) fn add_success_ranking_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    let status = <PaymentDimensions as ToSql<T>>::to_sql(&PaymentDimensions::PaymentStatus)
+        .change_context(QueryBuildingError::SqlSerializeError)
+        .attach_printable("Error serializing status column")?;
+    let success_condition = format!("{status} = '{}'", AttemptStatus::Charged);
+
+    builder
+        .add_select_column(Aggregate::Count {
+            field: None::<Column>,
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding attempt count")?;
+    builder
+        .add_select_column(Aggregate::CountIf {
+            condition: success_condition.clone(),
+            alias: Some("success_count"),
+        })
+        .attach_printable("Error adding successful attempt count")?;
+
+    // Aliases cannot be used within expressions, so the aggregates are repeated to order by
+    let success_count = <Aggregate<String> as ToSql<T>>::to_sql(&Aggregate::CountIf {
+        condition: success_condition,
+        alias: None,
+    })
+    .change_context(QueryBuildingError::SqlSerializeError)
+    .attach_printable("Error serializing successful attempt count")?;
+    let count = <Aggregate<Column> as ToSql<T>>::to_sql(&Aggregate::Count {
+        field: None,
+        alias: None,
+    })
+    .change_context(QueryBuildingError::SqlSerializeError)
+    .attach_printable("Error serializing attempt count")?;
+    builder
+        .add_order_by_clause(
+            percentage_expression(&success_count, &count),
+            Order::Descending,
+        )
+        .attach_printable("Error ordering by success rate")
+}
+
+#[derive(Default)]
+pub(super) struct PaymentMethodSuccessRanking;
+
+impl PaymentMethodSuccessRanking {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<String>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if !dimensions.contains(&PaymentDimensions::PaymentMethod) {
+            dimensions.push(PaymentDimensions::PaymentMethod);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        add_success_ranking_columns(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for PaymentMethodSuccessRanking
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_payment_method_success_ranking_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::PaymentMethod)
+            .unwrap();
+        add_success_ranking_columns(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::PaymentMethod)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT payment_method, count(*) as count, \
+             count(*) FILTER (WHERE status = 'charged') as success_count \
+             FROM payment_attempt GROUP BY payment_method \
+             ORDER BY count(*) FILTER (WHERE status = 'charged') \
+             * 100.0 / NULLIF(count(*), 0) DESC"
+        );
+    }
+}
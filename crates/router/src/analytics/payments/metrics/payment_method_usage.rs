@@ -0,0 +1,137 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Selects the number of payments the payment method type was offered to as `offered_count` and
+/// the number of attempts made with it as `used_count`
+pub(super) fn add_payment_method_usage_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    builder
+        .add_select_column(PaymentDimensions::PaymentMethodType)
+        .attach_printable("Error adding payment method type")?;
+    builder
+        .add_select_column(Aggregate::Sum {
+            field: "offered",
+            alias: Some("offered_count"),
+        })
+        .attach_printable("Error adding offered count")?;
+    builder
+        .add_select_column(Aggregate::Sum {
+            field: "used",
+            alias: Some("used_count"),
+        })
+        .attach_printable("Error adding used count")?;
+    builder
+        .add_group_by_clause(PaymentDimensions::PaymentMethodType)
+        .attach_printable("Error grouping by payment method type")
+}
+
+/// Read from [`AnalyticsCollection::PaymentMethodOffer`], which only holds the payment method
+/// type. Other payment dimensions and filters don't apply and are ignored.
+#[derive(Default)]
+pub(super) struct PaymentMethodUsage;
+
+impl PaymentMethodUsage {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<&'static str>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> =
+            QueryBuilder::new(AnalyticsCollection::PaymentMethodOffer);
+        query_builder.set_storage_timezone(storage_timezone);
+
+        let filters = PaymentFilters::default();
+        MetricQuery {
+            dimensions: &[],
+            filters: &filters,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        add_payment_method_usage_columns(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for PaymentMethodUsage
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_payment_method_usage_query() {
+        let mut builder: QueryBuilder<SqlxClient> =
+            QueryBuilder::new(AnalyticsCollection::PaymentMethodOffer);
+        add_payment_method_usage_columns(&mut builder).unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT payment_method_type, sum(offered) as offered_count, sum(used) as used_count \
+             FROM (SELECT merchant_id, created_at, modified_at, \
+             json_array_elements_text(allowed_payment_method_types) AS payment_method_type, \
+             1 AS offered, 0 AS used FROM payment_intent \
+             UNION ALL SELECT merchant_id, created_at, modified_at, payment_method_type, 0, 1 \
+             FROM payment_attempt) AS payment_method_offer GROUP BY payment_method_type"
+        );
+    }
+}
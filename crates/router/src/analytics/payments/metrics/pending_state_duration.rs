@@ -0,0 +1,186 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{
+        Aggregate, Column, GroupByClause, QueryBuilder, QueryBuildingError, QueryResult, ToSql,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Statuses in which an attempt waits on the customer, the connector or a sync before reaching
+/// an outcome
+const PENDING_STATUSES: &[storage_enums::AttemptStatus] = &[
+    storage_enums::AttemptStatus::Started,
+    storage_enums::AttemptStatus::AuthenticationPending,
+    storage_enums::AttemptStatus::DeviceDataCollectionPending,
+    storage_enums::AttemptStatus::PaymentMethodAwaited,
+    storage_enums::AttemptStatus::ConfirmationAwaited,
+    storage_enums::AttemptStatus::Authorizing,
+    storage_enums::AttemptStatus::CodInitiated,
+    storage_enums::AttemptStatus::CaptureInitiated,
+    storage_enums::AttemptStatus::VoidInitiated,
+    storage_enums::AttemptStatus::Pending,
+    storage_enums::AttemptStatus::Unresolved,
+];
+
+/// Selects the number of attempts as `count` and the average number of seconds they have spent
+/// in their current status as of `as_of` as `pending_duration_avg`. Attempts are updated on every
+/// status change, so `modified_at` is the time they entered their current status.
+fn add_pending_state_duration_columns<T>(
+    builder: &mut QueryBuilder<T>,
+    as_of: PrimitiveDateTime,
+) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    let as_of = as_of
+        .to_sql()
+        .change_context(QueryBuildingError::SqlSerializeError)
+        .attach_printable("Error serializing reference time")?;
+    builder
+        .add_select_column(Aggregate::Count {
+            field: None::<Column>,
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding attempt count")?;
+    builder
+        .add_select_column(format!(
+            "avg(date_part('epoch', '{as_of}'::timestamp - modified_at)) as pending_duration_avg"
+        ))
+        .attach_printable("Error adding pending duration average")
+}
+
+/// Measured over the attempts still in a pending status, as there's no history of the statuses
+/// an attempt went through: the duration is the time spent so far in the status they're in.
+/// Always grouped by connector and status.
+#[derive(Default)]
+pub(super) struct PendingStateDuration;
+
+impl PendingStateDuration {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        for dim in [
+            PaymentDimensions::Connector,
+            PaymentDimensions::PaymentStatus,
+        ] {
+            if !dimensions.contains(&dim) {
+                dimensions.push(dim);
+            }
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        let as_of = query_builder.utc_to_storage_time(common_utils::date_time::now())?;
+        add_pending_state_duration_columns(&mut query_builder, as_of)?;
+
+        query_builder
+            .add_filter_in_range_clause(PaymentDimensions::PaymentStatus, PENDING_STATUSES)
+            .attach_printable("Error adding pending status filter")?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for PendingStateDuration
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_pending_state_duration_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        builder
+            .add_select_column(PaymentDimensions::PaymentStatus)
+            .unwrap();
+        add_pending_state_duration_columns(&mut builder, datetime!(2023-11-02 12:00:00)).unwrap();
+        builder
+            .add_filter_in_range_clause(PaymentDimensions::PaymentStatus, PENDING_STATUSES)
+            .unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Connector)
+            .unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::PaymentStatus)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, status, count(*) as count, \
+             avg(date_part('epoch', '2023-11-02 12:00:00.0'::timestamp - modified_at)) \
+             as pending_duration_avg FROM payment_attempt \
+             WHERE status IN ('started', 'authentication_pending', \
+             'device_data_collection_pending', 'payment_method_awaited', 'confirmation_awaited', \
+             'authorizing', 'cod_initiated', 'capture_initiated', 'void_initiated', 'pending', \
+             'unresolved') GROUP BY connector, status"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Statuses an attempt can fail with
+const FAILED_STATUSES: &[storage_enums::AttemptStatus] = &[
+    storage_enums::AttemptStatus::Failure,
+    storage_enums::AttemptStatus::AuthenticationFailed,
+    storage_enums::AttemptStatus::AuthorizationFailed,
+    storage_enums::AttemptStatus::RouterDeclined,
+];
+
+/// Whether a later attempt of the same payment was charged
+const RECOVERED_CONDITION: &str = "EXISTS (SELECT 1 \
+FROM payment_attempt AS later_attempt \
+WHERE later_attempt.payment_id = payment_attempt.payment_id \
+AND later_attempt.merchant_id = payment_attempt.merchant_id \
+AND later_attempt.created_at > payment_attempt.created_at \
+AND later_attempt.status = 'charged')";
+
+/// Keeps the first attempt of every payment, if it failed
+pub(super) fn set_initial_failure_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_filter_clause(Column::AttemptNumber, &1_u64)
+        .attach_printable("Error adding first attempt filter")?;
+    builder
+        .add_filter_in_range_clause(PaymentDimensions::PaymentStatus, FAILED_STATUSES)
+        .attach_printable("Error adding failed status filter")
+}
+
+/// Selects the initially failed payments as `count` and the ones recovered later on as
+/// `recovered_count`
+pub(super) fn add_recovery_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    builder
+        .add_select_column(Aggregate::Count {
+            field: None::<Column>,
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding initially failed payment count")?;
+    builder
+        .add_select_column(Aggregate::CountIf {
+            condition: RECOVERED_CONDITION,
+            alias: Some("recovered_count"),
+        })
+        .attach_printable("Error adding recovered payment count")
+}
+
+#[derive(Default)]
+pub(super) struct RecoveryRate;
+
+impl RecoveryRate {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<&'static str>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+
+        query.apply_to(&mut query_builder)?;
+
+        add_recovery_columns(&mut query_builder)?;
+
+        set_initial_failure_filter_clause(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for RecoveryRate
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_recovery_rate_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        add_recovery_columns(&mut builder).unwrap();
+        set_initial_failure_filter_clause(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Connector)
+            .unwrap();
+
+        let attempt_number = <Column as ToSql<SqlxClient>>::to_sql(&Column::AttemptNumber).unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT connector, count(*) as count, count(*) FILTER (WHERE EXISTS (SELECT 1 \
+                 FROM payment_attempt AS later_attempt \
+                 WHERE later_attempt.payment_id = payment_attempt.payment_id \
+                 AND later_attempt.merchant_id = payment_attempt.merchant_id \
+                 AND later_attempt.created_at > payment_attempt.created_at \
+                 AND later_attempt.status = 'charged')) as recovered_count \
+                 FROM payment_attempt WHERE {attempt_number} = '1' \
+                 AND status IN ('failure', 'authentication_failed', 'authorization_failed', \
+                 'router_declined') GROUP BY connector"
+            )
+        );
+        assert_eq!(
+            builder.get_select_column_names(),
+            vec!["connector", "count", "recovered_count"]
+        );
+    }
+}
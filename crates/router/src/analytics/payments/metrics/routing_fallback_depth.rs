@@ -0,0 +1,207 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+pub(super) const ROUTING_RULE_ALIAS: &str = "routing_rule";
+
+/// Routing rule of the payments which were not routed with a straight through algorithm, i.e.
+/// which went through the routing configured for the merchant
+const DEFAULT_ROUTING_RULE: &str = "default";
+
+/// Number of distinct connectors tried by all attempts of the payment
+const CONNECTORS_TRIED: &str = "(SELECT count(DISTINCT attempts.connector) \
+FROM payment_attempt AS attempts \
+WHERE attempts.payment_id = payment_attempt.payment_id \
+AND attempts.merchant_id = payment_attempt.merchant_id)";
+
+/// Kind of the straight through algorithm the attempt was routed with, as stored in the routing
+/// info of the attempt (`{"algorithm": {"algorithm": {"type": ..., "data": ...}}}`)
+pub(super) fn primary_routing_rule() -> String {
+    format!(
+        "coalesce(straight_through_algorithm -> 'algorithm' -> 'algorithm' ->> 'type', \
+         '{DEFAULT_ROUTING_RULE}')"
+    )
+}
+
+/// Selects the routing rule of the first attempt of every payment as `routing_rule`, the number of
+/// connectors tried per payment summed up as `total` and the number of payments as `count`
+pub(super) fn add_routing_fallback_depth_columns<T>(
+    builder: &mut QueryBuilder<T>,
+) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    builder
+        .add_select_column(format!(
+            "{} as {ROUTING_RULE_ALIAS}",
+            primary_routing_rule()
+        ))
+        .attach_printable("Error adding routing rule")?;
+    builder
+        .add_select_column(Aggregate::Sum {
+            field: CONNECTORS_TRIED.to_string(),
+            alias: Some("total"),
+        })
+        .attach_printable("Error adding connectors tried sum")?;
+    builder
+        .add_select_column(Aggregate::Count {
+            field: None::<Column>,
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding payment count")
+}
+
+/// Keeps the first attempt of every payment, the routing rule of which is the primary one
+pub(super) fn set_first_attempt_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_filter_clause(Column::AttemptNumber, &1_u64)
+        .attach_printable("Error adding first attempt filter")
+}
+
+#[derive(Default)]
+pub(super) struct RoutingFallbackDepth;
+
+impl RoutingFallbackDepth {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<String>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+
+        query.apply_to(&mut query_builder)?;
+
+        add_routing_fallback_depth_columns(&mut query_builder)?;
+
+        set_first_attempt_filter_clause(&mut query_builder)?;
+
+        query_builder
+            .add_group_by_clause(ROUTING_RULE_ALIAS)
+            .attach_printable("Error grouping by routing rule")?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for RoutingFallbackDepth
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::{
+        query::snapshot::{assert_query_snapshot, assert_query_snapshot_contains},
+        SqlxClient,
+    };
+
+    #[test]
+    fn test_routing_fallback_depth_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        add_routing_fallback_depth_columns(&mut builder).unwrap();
+        set_first_attempt_filter_clause(&mut builder).unwrap();
+        builder.add_group_by_clause(ROUTING_RULE_ALIAS).unwrap();
+
+        let attempt_number = <Column as ToSql<SqlxClient>>::to_sql(&Column::AttemptNumber).unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT coalesce(straight_through_algorithm -> 'algorithm' -> 'algorithm' \
+                 ->> 'type', 'default') as routing_rule, \
+                 sum((SELECT count(DISTINCT attempts.connector) FROM payment_attempt AS attempts \
+                 WHERE attempts.payment_id = payment_attempt.payment_id \
+                 AND attempts.merchant_id = payment_attempt.merchant_id)) as total, \
+                 count(*) as count FROM payment_attempt WHERE {attempt_number} = '1' \
+                 GROUP BY routing_rule"
+            )
+        );
+    }
+
+    #[test]
+    fn test_routing_fallback_depth_query_snapshot() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        add_routing_fallback_depth_columns(&mut builder).unwrap();
+        set_first_attempt_filter_clause(&mut builder).unwrap();
+        builder.add_group_by_clause(ROUTING_RULE_ALIAS).unwrap();
+
+        assert_query_snapshot(
+            &mut builder,
+            r#"
+            SELECT
+                coalesce(straight_through_algorithm -> 'algorithm' -> 'algorithm' ->> 'type',
+                    'default') as routing_rule,
+                sum((SELECT count(DISTINCT attempts.connector)
+                    FROM payment_attempt AS attempts
+                    WHERE attempts.payment_id = payment_attempt.payment_id
+                    AND attempts.merchant_id = payment_attempt.merchant_id)) as total,
+                count(*) as count
+            FROM payment_attempt
+            WHERE (SELECT count(*)
+                FROM payment_attempt AS earlier_attempt
+                WHERE earlier_attempt.payment_id = payment_attempt.payment_id
+                AND earlier_attempt.merchant_id = payment_attempt.merchant_id
+                AND earlier_attempt.created_at <= payment_attempt.created_at) = '1'
+            GROUP BY routing_rule
+            "#,
+        );
+        assert_query_snapshot_contains(&mut builder, "GROUP BY\n routing_rule");
+    }
+}
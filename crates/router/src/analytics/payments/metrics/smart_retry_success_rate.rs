@@ -0,0 +1,151 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, FilterTypes, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Restricts the query to attempts made after the first one of a payment
+pub(super) fn set_retried_attempt_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_custom_filter_clause(Column::AttemptNumber, &1_u64, FilterTypes::Gt)
+        .attach_printable("Error adding retried attempt filter")
+}
+
+#[derive(Default)]
+pub(super) struct SmartRetrySuccessRate;
+
+impl SmartRetrySuccessRate {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        dimensions.push(PaymentDimensions::PaymentStatus);
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        query_builder.add_select_column(Aggregate::Count {
+            field: None,
+            alias: Some("count"),
+        })?;
+
+        set_retried_attempt_filter_clause(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for SmartRetrySuccessRate
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use common_enums::enums::AttemptStatus;
+
+    use super::*;
+    use crate::analytics::{
+        payments::accumulator::{PaymentMetricAccumulator, SuccessRateAccumulator},
+        types::DBEnumWrapper,
+        SqlxClient,
+    };
+
+    #[test]
+    fn test_smart_retry_success_rate_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::PaymentStatus)
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        set_retried_attempt_filter_clause(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::PaymentStatus)
+            .unwrap();
+
+        let attempt_number = <Column as ToSql<SqlxClient>>::to_sql(&Column::AttemptNumber).unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT status, count(*) as count FROM payment_attempt \
+                 WHERE {attempt_number} > 1 GROUP BY status"
+            )
+        );
+    }
+
+    #[test]
+    fn test_smart_retry_success_rate_uses_retried_attempts_only() {
+        // Both the successful and the total count come from the same filtered status buckets
+        let row = |status, count| PaymentMetricRow {
+            status: Some(DBEnumWrapper(status)),
+            count: Some(count),
+            ..Default::default()
+        };
+        let mut accumulator = SuccessRateAccumulator::default();
+        accumulator.add_metrics_bucket(&row(AttemptStatus::Charged, 1));
+        accumulator.add_metrics_bucket(&row(AttemptStatus::Failure, 3));
+        assert_eq!(accumulator.collect(), Some(25.0));
+    }
+}
@@ -0,0 +1,126 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use time::PrimitiveDateTime;
+
+use super::{
+    payment_method_success_ranking::add_success_ranking_columns, MetricQuery, PaymentMetricRow,
+};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+#[derive(Default)]
+pub(super) struct SuccessRateByCountry;
+
+impl SuccessRateByCountry {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<String>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if !dimensions.contains(&PaymentDimensions::BillingCountry) {
+            dimensions.push(PaymentDimensions::BillingCountry);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        add_success_ranking_columns(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for SuccessRateByCountry
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<String>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_success_rate_by_country_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::BillingCountry)
+            .unwrap();
+        add_success_ranking_columns(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::BillingCountry)
+            .unwrap();
+
+        let billing_country = "(SELECT billing_country FROM (SELECT address.country \
+                               AS billing_country FROM payment_intent JOIN address \
+                               ON address.address_id = payment_intent.billing_address_id \
+                               WHERE payment_intent.payment_id = payment_attempt.payment_id \
+                               AND payment_intent.merchant_id = payment_attempt.merchant_id) \
+                               AS billing_address)";
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT {billing_country}, count(*) as count, \
+                 count(*) FILTER (WHERE status = 'charged') as success_count \
+                 FROM payment_attempt GROUP BY {billing_country} \
+                 ORDER BY count(*) FILTER (WHERE status = 'charged') \
+                 * 100.0 / NULLIF(count(*), 0) DESC"
+            )
+        );
+        assert_eq!(
+            builder.get_select_column_names(),
+            vec!["billing_country", "count", "success_count"]
+        );
+    }
+}
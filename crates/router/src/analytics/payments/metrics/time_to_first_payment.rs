@@ -0,0 +1,140 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Seconds from the creation of the merchant account to the first payment attempt of the
+/// merchant, whether or not the attempt falls in the requested time range
+const FIRST_PAYMENT_GAP_SECONDS: &str =
+    "date_part('epoch', (SELECT min(payment_attempt.created_at) \
+FROM payment_attempt WHERE payment_attempt.merchant_id = merchant_account.merchant_id) \
+- merchant_account.created_at)";
+
+/// Selects the average time to the first payment as `time_to_first_payment`, over the number of
+/// merchants which made one as `count`
+pub(super) fn add_time_to_first_payment_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    builder
+        .add_select_column(format!(
+            "avg({FIRST_PAYMENT_GAP_SECONDS}) as time_to_first_payment"
+        ))
+        .attach_printable("Error adding average time to first payment")?;
+    builder
+        .add_select_column(Aggregate::Count {
+            field: Some(FIRST_PAYMENT_GAP_SECONDS),
+            alias: Some("count"),
+        })
+        .attach_printable("Error adding merchant count")
+}
+
+/// Measured over the merchant accounts created in the requested time range, i.e. over new
+/// merchants. Payment dimensions and filters don't apply to merchant accounts and are ignored.
+#[derive(Default)]
+pub(super) struct TimeToFirstPayment;
+
+impl TimeToFirstPayment {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+        Aggregate<&'static str>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> =
+            QueryBuilder::new(AnalyticsCollection::MerchantAccount);
+        query_builder.set_storage_timezone(storage_timezone);
+        query_builder.set_bucket_column(Column::CreatedAt);
+
+        let filters = PaymentFilters::default();
+        MetricQuery {
+            dimensions: &[],
+            filters: &filters,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        add_time_to_first_payment_columns(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for TimeToFirstPayment
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_time_to_first_payment_query() {
+        let mut builder: QueryBuilder<SqlxClient> =
+            QueryBuilder::new(AnalyticsCollection::MerchantAccount);
+        add_time_to_first_payment_columns(&mut builder).unwrap();
+
+        let gap =
+            "date_part('epoch', (SELECT min(payment_attempt.created_at) FROM payment_attempt \
+                   WHERE payment_attempt.merchant_id = merchant_account.merchant_id) \
+                   - merchant_account.created_at)";
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT avg({gap}) as time_to_first_payment, count({gap}) as count \
+                 FROM merchant_account"
+            )
+        );
+    }
+}
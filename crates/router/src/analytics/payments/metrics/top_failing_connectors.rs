@@ -0,0 +1,213 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{
+        Aggregate, Column, GroupByClause, QueryBuilder, QueryBuildingError, QueryResult, ToSql,
+        Window,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Number of connectors returned by [`TopFailingConnectors`] for every bucket
+pub(super) const TOP_FAILING_CONNECTORS_LIMIT: i64 = 5;
+
+pub(super) fn set_failure_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_filter_clause(
+            PaymentDimensions::PaymentStatus,
+            storage_enums::AttemptStatus::Failure,
+        )
+        .attach_printable("Error adding failure status filter")
+}
+
+/// Selects the rank of every connector among the connectors of its bucket by failed attempts as
+/// `failure_rank`, 1 being the most failing one. Buckets are made of the other group by
+/// expressions, i.e. the time bucket and the other dimensions, so the query must be grouped
+/// before the column is added.
+pub(super) fn add_failure_rank_column<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    let connector = PaymentDimensions::Connector.to_string();
+    let failure_rank = <Window<_, _> as ToSql<T>>::to_sql(&Window {
+        function: "rank()",
+        partition_by: builder
+            .get_group_by_columns()
+            .into_iter()
+            .filter(|column| *column != connector)
+            .collect(),
+        order_by: Some("count(*) DESC"),
+        alias: Some("failure_rank"),
+    })
+    .change_context(QueryBuildingError::SqlSerializeError)
+    .attach_printable("Error serializing failure rank")?;
+    builder
+        .add_select_column(failure_rank)
+        .attach_printable("Error adding failure rank")
+}
+
+/// Connectors with the most failed attempts, ranked within every bucket of the granularity and
+/// the other dimensions, keeping the top [`TOP_FAILING_CONNECTORS_LIMIT`] of each bucket.
+#[derive(Default)]
+pub(super) struct TopFailingConnectors;
+
+impl TopFailingConnectors {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let mut dimensions = query.dimensions.to_vec();
+
+        if !dimensions.contains(&PaymentDimensions::Connector) {
+            dimensions.push(PaymentDimensions::Connector);
+        }
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        query_builder.add_select_column(Aggregate::Count {
+            field: None,
+            alias: Some("count"),
+        })?;
+
+        set_failure_filter_clause(&mut query_builder)?;
+
+        add_failure_rank_column(&mut query_builder)?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for TopFailingConnectors
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        let mut rows =
+            super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await?;
+        // The ranks are computed per bucket, so the top connectors are kept here rather than by
+        // limiting the query, which would pick them across all the buckets
+        rows.retain(|(_, row)| {
+            row.failure_rank
+                .map_or(false, |rank| rank <= TOP_FAILING_CONNECTORS_LIMIT)
+        });
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_top_failing_connectors_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(PaymentDimensions::Connector)
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        set_failure_filter_clause(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Connector)
+            .unwrap();
+        add_failure_rank_column(&mut builder).unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, count(*) as count, \
+             rank() OVER (ORDER BY count(*) DESC) as failure_rank FROM payment_attempt \
+             WHERE status = 'failure' GROUP BY connector"
+        );
+    }
+
+    #[test]
+    fn test_failure_rank_partitioned_by_bucket() {
+        let time_range = TimeRange {
+            start_time: datetime!(2023-11-01 00:00:00),
+            end_time: Some(datetime!(2023-11-02 00:00:00)),
+        };
+        let mut builder = TopFailingConnectors
+            .build_query::<SqlxClient>(
+                &MetricQuery {
+                    dimensions: &[PaymentDimensions::PaymentMethod],
+                    merchant_id: "merchant_1",
+                    filters: &PaymentFilters::default(),
+                    granularity: &Some(Granularity::OneHour),
+                    time_range: &time_range,
+                },
+                time::UtcOffset::UTC,
+            )
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT payment_method, connector, min(created_at) as start_bucket, \
+             max(created_at) as end_bucket, count(*) as count, \
+             rank() OVER (PARTITION BY payment_method, DATE_TRUNC('hour', modified_at) \
+             ORDER BY count(*) DESC) as failure_rank FROM payment_attempt \
+             WHERE merchant_id = 'merchant_1' AND created_at >= '2023-11-01 0:00:00.0' \
+             AND created_at <= '2023-11-02 0:00:00.0' AND status = 'failure' \
+             GROUP BY payment_method, connector, DATE_TRUNC('hour', modified_at)"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use api_models::analytics::{
+    payments::{PaymentDimensions, PaymentFilters, PaymentMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use time::PrimitiveDateTime;
+
+use super::{MetricQuery, PaymentMetricRow};
+use crate::analytics::{
+    query::{Aggregate, Column, GroupByClause, QueryBuilder, QueryResult, ToSql},
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsResult},
+};
+
+/// Every heatmap cell is a weekday and hour pair, on top of whatever dimensions were requested
+pub(super) fn heatmap_dimensions(dimensions: &[PaymentDimensions]) -> Vec<PaymentDimensions> {
+    let mut dimensions = dimensions.to_vec();
+    for cell in [PaymentDimensions::DayOfWeek, PaymentDimensions::HourOfDay] {
+        if !dimensions.contains(&cell) {
+            dimensions.push(cell);
+        }
+    }
+    dimensions
+}
+
+#[derive(Default)]
+pub(super) struct VolumeHeatmap;
+
+impl VolumeHeatmap {
+    fn build_query<T>(
+        &self,
+        query: &MetricQuery<'_>,
+        storage_timezone: time::UtcOffset,
+    ) -> QueryResult<QueryBuilder<T>>
+    where
+        T: AnalyticsDataSource,
+        PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+        Aggregate<Column>: ToSql<T>,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Payment);
+        query_builder.set_storage_timezone(storage_timezone);
+        let dimensions = heatmap_dimensions(query.dimensions);
+
+        MetricQuery {
+            dimensions: &dimensions,
+            ..*query
+        }
+        .apply_to(&mut query_builder)?;
+
+        query_builder.add_select_column(Aggregate::Count {
+            field: None,
+            alias: Some("count"),
+        })?;
+
+        Ok(query_builder)
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> super::PaymentMetric<T> for VolumeHeatmap
+where
+    T: AnalyticsDataSource + super::PaymentMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<Column>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[PaymentDimensions],
+        merchant_id: &str,
+        filters: &PaymentFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(PaymentMetricsBucketIdentifier, PaymentMetricRow)>> {
+        let mut query_builder = self
+            .build_query(
+                &MetricQuery {
+                    dimensions,
+                    merchant_id,
+                    filters,
+                    granularity,
+                    time_range,
+                },
+                pool.storage_timezone(),
+            )
+            .switch()?;
+        super::load_metric_rows(&mut query_builder, granularity, time_range, pool).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_heatmap_dimensions() {
+        assert_eq!(
+            heatmap_dimensions(&[PaymentDimensions::Connector]),
+            vec![
+                PaymentDimensions::Connector,
+                PaymentDimensions::DayOfWeek,
+                PaymentDimensions::HourOfDay
+            ]
+        );
+        assert_eq!(
+            heatmap_dimensions(&[PaymentDimensions::HourOfDay]),
+            vec![PaymentDimensions::HourOfDay, PaymentDimensions::DayOfWeek]
+        );
+    }
+
+    #[test]
+    fn test_volume_heatmap_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        let dimensions = heatmap_dimensions(&[]);
+        for dim in dimensions.iter() {
+            builder.add_select_column(dim).unwrap();
+        }
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        for dim in dimensions.iter() {
+            builder.add_group_by_clause(dim).unwrap();
+        }
+
+        let day_of_week = "(SELECT day_of_week \
+                           FROM int4(date_part('dow', payment_attempt.created_at)) \
+                           AS cell(day_of_week))";
+        let hour_of_day = "(SELECT hour_of_day \
+                           FROM int4(date_part('hour', payment_attempt.created_at)) \
+                           AS cell(hour_of_day))";
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT {day_of_week}, {hour_of_day}, count(*) as count FROM payment_attempt \
+                 GROUP BY {day_of_week}, {hour_of_day}"
+            )
+        );
+        assert_eq!(
+            builder.get_select_column_names(),
+            vec!["day_of_week", "hour_of_day", "count"]
+        );
+    }
+}
@@ -25,9 +25,7 @@ where
         }
 
         if !self.connector.is_empty() {
-            builder
-                .add_filter_in_range_clause(PaymentDimensions::Connector, &self.connector)
-                .attach_printable("Error adding connector filter")?;
+            builder.add_connector_filter(&self.connector)?;
         }
 
         if !self.auth_type.is_empty() {
@@ -41,6 +39,126 @@ where
                 .add_filter_in_range_clause(PaymentDimensions::PaymentMethod, &self.payment_method)
                 .attach_printable("Error adding payment method filter")?;
         }
+
+        if !self.profile_id.is_empty() {
+            builder
+                .add_filter_in_range_clause(PaymentDimensions::ProfileId, &self.profile_id)
+                .attach_printable("Error adding profile id filter")?;
+        }
+
+        if !self.payment_method_type.is_empty() {
+            builder
+                .add_filter_in_range_clause(
+                    PaymentDimensions::PaymentMethodType,
+                    &self.payment_method_type,
+                )
+                .attach_printable("Error adding payment method type filter")?;
+        }
+
+        if !self.card_network.is_empty() {
+            builder
+                .add_filter_in_range_clause(PaymentDimensions::CardNetwork, &self.card_network)
+                .attach_printable("Error adding card network filter")?;
+        }
+
+        if !self.day_of_week.is_empty() {
+            builder
+                .add_filter_in_range_clause(PaymentDimensions::DayOfWeek, &self.day_of_week)
+                .attach_printable("Error adding day of week filter")?;
+        }
+
+        if !self.hour_of_day.is_empty() {
+            builder
+                .add_filter_in_range_clause(PaymentDimensions::HourOfDay, &self.hour_of_day)
+                .attach_printable("Error adding hour of day filter")?;
+        }
+
+        if !self.billing_country.is_empty() {
+            builder
+                .add_filter_in_range_clause(
+                    PaymentDimensions::BillingCountry,
+                    &self.billing_country,
+                )
+                .attach_printable("Error adding billing country filter")?;
+        }
+
+        if !self.card_bin.is_empty() {
+            builder
+                .add_filter_in_range_clause(PaymentDimensions::CardBin, &self.card_bin)
+                .attach_printable("Error adding card bin filter")?;
+        }
+
+        if !self.issuer_country.is_empty() {
+            builder
+                .add_filter_in_range_clause(PaymentDimensions::IssuerCountry, &self.issuer_country)
+                .attach_printable("Error adding issuer country filter")?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use api_models::enums::Connector;
+    use common_enums::enums as storage_enums;
+
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_payment_filters_predicates() {
+        let filters = PaymentFilters {
+            currency: vec![storage_enums::Currency::USD],
+            status: vec![storage_enums::AttemptStatus::Charged],
+            connector: vec![Connector::Stripe],
+            auth_type: vec![storage_enums::AuthenticationType::ThreeDs],
+            payment_method: vec![storage_enums::PaymentMethod::Card],
+            profile_id: vec!["profile_1".to_string()],
+            payment_method_type: vec![storage_enums::PaymentMethodType::Credit],
+            card_network: vec![storage_enums::CardNetwork::Visa],
+            day_of_week: vec![1],
+            hour_of_day: vec![12],
+            billing_country: vec![storage_enums::CountryAlpha2::US],
+            card_bin: vec!["424242".to_string()],
+            issuer_country: vec!["US".to_string()],
+        };
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder.add_select_column("connector").unwrap();
+        filters.set_filter_clause(&mut builder).unwrap();
+
+        let expression =
+            |dimension: PaymentDimensions| ToSql::<SqlxClient>::to_sql(&dimension).unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT connector FROM payment_attempt WHERE currency IN ('USD') \
+                 AND status IN ('charged') AND connector IN ('stripe') \
+                 AND authentication_type IN ('three_ds') AND payment_method IN ('card') \
+                 AND {} IN ('profile_1') AND payment_method_type IN ('credit') \
+                 AND {} IN ('Visa') AND {} IN ('1') AND {} IN ('12') AND {} IN ('US') \
+                 AND {} IN ('424242') AND {} IN ('US')",
+                expression(PaymentDimensions::ProfileId),
+                expression(PaymentDimensions::CardNetwork),
+                expression(PaymentDimensions::DayOfWeek),
+                expression(PaymentDimensions::HourOfDay),
+                expression(PaymentDimensions::BillingCountry),
+                expression(PaymentDimensions::CardBin),
+                expression(PaymentDimensions::IssuerCountry),
+            )
+        );
+    }
+
+    #[test]
+    fn test_empty_payment_filters() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder.add_select_column("connector").unwrap();
+        PaymentFilters::default()
+            .set_filter_clause(&mut builder)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt"
+        );
+    }
+}
@@ -1,9 +1,15 @@
 #![allow(dead_code)]
-use std::marker::PhantomData;
+use std::{
+    collections::HashSet,
+    future::Future,
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
 
 use api_models::{
     analytics::{
         self as analytics_api,
+        disputes::DisputeDimensions,
         payments::PaymentDimensions,
         refunds::{RefundDimensions, RefundType},
         Granularity,
@@ -13,13 +19,19 @@ use api_models::{
 };
 use common_enums::{
     enums as storage_enums,
-    enums::{AttemptStatus, AuthenticationType, Currency, PaymentMethod},
+    enums::{
+        AttemptStatus, AuthenticationType, CaptureMethod, CardNetwork, CountryAlpha2, Currency,
+        DisputeStage, PaymentMethod, PaymentMethodType,
+    },
 };
 use common_utils::errors::{CustomResult, ParsingError};
 use error_stack::{IntoReport, ResultExt};
 use router_env::logger;
 
-use super::types::{AnalyticsCollection, AnalyticsDataSource, LoadRow};
+use super::types::{
+    AnalyticsCollection, AnalyticsDataSource, JsonAggregationStyle, LoadRow, QueryHintStyle,
+    SampleStyle,
+};
 use crate::analytics::types::QueryExecutionError;
 pub type QueryResult<T> = error_stack::Result<T, QueryBuildingError>;
 pub trait QueryFilter<T>
@@ -65,14 +77,63 @@ where
     Granularity: GroupByClause<T>,
 {
     fn set_filter_clause(&self, builder: &mut QueryBuilder<T>) -> QueryResult<()> {
-        builder.add_custom_filter_clause("created_at", self.start_time, FilterTypes::Gte)?;
+        if self
+            .end_time
+            .map_or(false, |end_time| end_time < self.start_time)
+        {
+            Err(QueryBuildingError::InvalidQuery(
+                "Time range end precedes its start",
+            ))
+            .into_report()?;
+        }
+        let start_time = builder.utc_to_storage_time(self.start_time)?;
+        builder.add_custom_filter_clause(Column::CreatedAt, start_time, FilterTypes::Gte)?;
         if let Some(end) = self.end_time {
-            builder.add_custom_filter_clause("created_at", end, FilterTypes::Lte)?;
+            let end_time = builder.utc_to_storage_time(end)?;
+            builder.add_custom_filter_clause(Column::CreatedAt, end_time, FilterTypes::Lte)?;
         }
         Ok(())
     }
 }
 
+/// Relative ranges are resolved against the current time when the filter is added
+impl<T> QueryFilter<T> for analytics_api::RelativeTimeRange
+where
+    T: AnalyticsDataSource,
+    time::PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+{
+    fn set_filter_clause(&self, builder: &mut QueryBuilder<T>) -> QueryResult<()> {
+        self.resolve().set_filter_clause(builder)
+    }
+}
+
+/// Filters every metric query is scoped by, i.e. the merchant the metrics are requested for and
+/// the time range they cover
+#[derive(Debug, Clone, Copy)]
+pub struct BaseFilters<'a> {
+    pub merchant_id: &'a str,
+    pub time_range: &'a analytics_api::TimeRange,
+}
+
+impl<'a> BaseFilters<'a> {
+    pub fn apply_to<T>(&self, builder: &mut QueryBuilder<T>) -> QueryResult<()>
+    where
+        T: AnalyticsDataSource,
+        time::PrimitiveDateTime: ToSql<T>,
+        AnalyticsCollection: ToSql<T>,
+        Granularity: GroupByClause<T>,
+    {
+        builder
+            .add_filter_clause(Column::MerchantId, self.merchant_id)
+            .attach_printable("Error adding merchant id filter")?;
+        self.time_range
+            .set_filter_clause(builder)
+            .attach_printable("Error filtering time range")
+    }
+}
+
 impl GroupByClause<super::SqlxClient> for Granularity {
     fn set_group_by_clause(
         &self,
@@ -88,13 +149,20 @@ impl GroupByClause<super::SqlxClient> for Granularity {
 
         let granularity_divisor = self.get_bucket_size();
 
+        // Buckets are computed on UTC timestamps to line up with the UTC bucket labels
+        let bucket_column = builder.get_bucket_column()?;
+        let bucket_column = match builder.storage_timezone.whole_seconds() {
+            0 => bucket_column,
+            offset => format!("({bucket_column} - INTERVAL '{offset} seconds')"),
+        };
+
         builder
-            .add_group_by_clause(format!("DATE_TRUNC('{trunc_scale}', modified_at)"))
+            .add_group_by_clause(format!("DATE_TRUNC('{trunc_scale}', {bucket_column})"))
             .attach_printable("Error adding time prune group by")?;
         if let Some(scale) = granularity_bucket_scale {
             builder
                 .add_group_by_clause(format!(
-                    "FLOOR(DATE_PART('{scale}', modified_at)/{granularity_divisor})"
+                    "FLOOR(DATE_PART('{scale}', {bucket_column})/{granularity_divisor})"
                 ))
                 .attach_printable("Error adding time binning group by")?;
         }
@@ -192,6 +260,281 @@ impl SeriesBucket for Granularity {
     }
 }
 
+/// Share of `numerator` in `denominator` as a percentage. The denominator is wrapped in `NULLIF`,
+/// so that buckets with a zero denominator yield `NULL` rather than a division by zero error.
+pub fn percentage_expression(numerator: &str, denominator: &str) -> String {
+    format!("{numerator} * 100.0 / NULLIF({denominator}, 0)")
+}
+
+/// Collapses every run of whitespace in the query into a single space, so that queries compare
+/// equal regardless of how their expressions were wrapped.
+pub fn normalize_query(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// First and last second of the calendar month `value` falls in
+pub fn month_of(
+    value: time::PrimitiveDateTime,
+) -> error_stack::Result<(time::PrimitiveDateTime, time::PrimitiveDateTime), PostProcessingError> {
+    let start = value
+        .replace_day(1)
+        .into_report()
+        .change_context(PostProcessingError::BucketClipping)?
+        .replace_time(time::Time::MIDNIGHT);
+    let (year, month) = match value.month() {
+        time::Month::December => (value.year() + 1, time::Month::January),
+        month => (value.year(), month.next()),
+    };
+    let end = time::Date::from_calendar_date(year, month, 1)
+        .into_report()
+        .change_context(PostProcessingError::BucketClipping)?
+        .midnight()
+        - time::Duration::SECOND;
+    Ok((start, end))
+}
+
+/// Wraps `name` in the identifier quote of the data source, doubling the quotes it contains
+fn quote_identifier<T: AnalyticsDataSource>(name: &str) -> String {
+    let quote = T::IDENTIFIER_QUOTE;
+    format!(
+        "{quote}{}{quote}",
+        name.replace(quote, &format!("{quote}{quote}"))
+    )
+}
+
+/// Quotes `expression` when it's a bare column name, expressions are left as is. See
+/// [`QueryBuilder::quote_identifiers`].
+fn quote_if_identifier<T: AnalyticsDataSource>(expression: &str) -> String {
+    let is_identifier = expression.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && expression
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier {
+        quote_identifier::<T>(expression)
+    } else {
+        expression.to_owned()
+    }
+}
+
+/// Converts a timestamp as held by the data source into UTC, so it can be clipped and labelled
+/// the same way as the buckets computed by the query.
+pub fn storage_time_to_utc(
+    value: time::PrimitiveDateTime,
+    storage_timezone: time::UtcOffset,
+) -> error_stack::Result<time::PrimitiveDateTime, PostProcessingError> {
+    value
+        .checked_sub(time::Duration::seconds(
+            storage_timezone.whole_seconds().into(),
+        ))
+        .ok_or(PostProcessingError::StorageTimezoneConversion)
+        .into_report()
+        .attach_printable_lazy(|| format!("Out of range timestamp: {value}"))
+}
+
+/// Expands the time range outwards so that it starts at the beginning of the first bucket and
+/// ends with the last bucket of the granularity, making the first and last buckets complete.
+///
+/// This is opt-in, metrics are loaded for the time range as requested by default.
+pub fn align_time_range_to_granularity(
+    time_range: &analytics_api::TimeRange,
+    granularity: &Granularity,
+) -> error_stack::Result<analytics_api::TimeRange, PostProcessingError> {
+    let smallest_unit = match granularity.get_lowest_common_granularity_level() {
+        TimeGranularityLevel::Minute => time::Duration::SECOND,
+        TimeGranularityLevel::Hour => time::Duration::MINUTE,
+        TimeGranularityLevel::Day => time::Duration::HOUR,
+    };
+
+    let end_time = time_range
+        .end_time
+        .map(|end_time| {
+            granularity.clip_to_end(end_time).and_then(|end_bucket| {
+                // The clipped end points at the start of the last unit of the bucket
+                end_bucket
+                    .checked_add(smallest_unit - time::Duration::MICROSECOND)
+                    .ok_or(PostProcessingError::BucketClipping)
+                    .into_report()
+            })
+        })
+        .transpose()?;
+
+    Ok(analytics_api::TimeRange {
+        start_time: granularity.clip_to_start(time_range.start_time)?,
+        end_time,
+    })
+}
+
+/// Iterator over the consecutive buckets of a granularity covering a time range, yielding the
+/// start and end of every bucket as they are labelled in metric results. Open ended time ranges
+/// are enumerated up to the current time.
+///
+/// Buckets are stepped through on UTC timestamps, so every bucket has the same length.
+#[derive(Debug)]
+pub struct GranularitySeries {
+    granularity: Granularity,
+    step: time::Duration,
+    next_start: Option<time::PrimitiveDateTime>,
+    end_time: time::PrimitiveDateTime,
+}
+
+/// Length of every bucket of the granularity
+fn bucket_length(granularity: Granularity) -> time::Duration {
+    match granularity {
+        Granularity::OneMin => time::Duration::MINUTE,
+        Granularity::FiveMin => time::Duration::minutes(5),
+        Granularity::FifteenMin => time::Duration::minutes(15),
+        Granularity::ThirtyMin => time::Duration::minutes(30),
+        Granularity::OneHour => time::Duration::HOUR,
+        Granularity::OneDay => time::Duration::DAY,
+    }
+}
+
+/// Finest granularity still splitting a time range of the given span into a usable number of
+/// buckets, from the longest span it applies to
+const DEFAULT_GRANULARITIES: &[(time::Duration, Granularity)] = &[
+    (time::Duration::days(30), Granularity::OneDay),
+    (time::Duration::days(3), Granularity::OneHour),
+    (time::Duration::DAY, Granularity::ThirtyMin),
+    (time::Duration::hours(12), Granularity::FifteenMin),
+    (time::Duration::hours(3), Granularity::FiveMin),
+];
+
+/// Granularity suited to the span of the time range, e.g. daily buckets beyond 30 days. Open
+/// ended time ranges span up to the current time.
+pub fn default_granularity(time_range: &analytics_api::TimeRange) -> Granularity {
+    let span = time_range
+        .end_time
+        .unwrap_or_else(common_utils::date_time::now)
+        - time_range.start_time;
+    DEFAULT_GRANULARITIES
+        .iter()
+        .find(|(longer_than, _)| span > *longer_than)
+        .map_or(Granularity::OneMin, |(_, granularity)| *granularity)
+}
+
+/// Granularity the metrics are bucketed by: the requested one, unless it is finer than the
+/// default for the span of the time range and the caller didn't ask for it to be kept as is
+pub fn resolve_granularity(
+    time_series: &Option<analytics_api::TimeSeries>,
+    time_range: &analytics_api::TimeRange,
+) -> Option<Granularity> {
+    time_series.map(|time_series| {
+        let default = default_granularity(time_range);
+        if time_series.exact_granularity
+            || bucket_length(time_series.granularity) >= bucket_length(default)
+        {
+            time_series.granularity
+        } else {
+            logger::debug!(
+                "Coarsening granularity {:?} to {default:?} for the requested time range",
+                time_series.granularity
+            );
+            default
+        }
+    })
+}
+
+impl GranularitySeries {
+    pub fn new(
+        granularity: Granularity,
+        time_range: &analytics_api::TimeRange,
+    ) -> error_stack::Result<Self, PostProcessingError> {
+        Ok(Self {
+            granularity,
+            step: bucket_length(granularity),
+            next_start: Some(granularity.clip_to_start(time_range.start_time)?),
+            end_time: time_range
+                .end_time
+                .unwrap_or_else(common_utils::date_time::now),
+        })
+    }
+}
+
+impl Iterator for GranularitySeries {
+    type Item = (time::PrimitiveDateTime, time::PrimitiveDateTime);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.next_start.filter(|start| *start <= self.end_time)?;
+        let end = self.granularity.clip_to_end(start).ok()?;
+        self.next_start = start.checked_add(self.step);
+        Some((start, end))
+    }
+}
+
+/// Buckets with boundaries given by the caller instead of derived from a granularity, for custom
+/// reporting periods. Every bucket starts at a boundary and runs up to, excluding, the next one,
+/// which is the end it's labelled with.
+#[derive(Debug, Clone)]
+pub struct ExpectedBuckets {
+    boundaries: Vec<time::PrimitiveDateTime>,
+}
+
+impl ExpectedBuckets {
+    pub fn new(
+        boundaries: Vec<time::PrimitiveDateTime>,
+    ) -> error_stack::Result<Self, PostProcessingError> {
+        if boundaries.len() < 2
+            || boundaries
+                .windows(2)
+                .any(|pair| pair.first() >= pair.last())
+        {
+            Err(PostProcessingError::InvalidBucketBoundaries)
+                .into_report()
+                .attach_printable("Bucket boundaries must be at least two strictly increasing")?;
+        }
+        Ok(Self { boundaries })
+    }
+
+    /// Time range the metrics have to be loaded for so that every bucket is covered
+    pub fn time_range(&self) -> error_stack::Result<analytics_api::TimeRange, PostProcessingError> {
+        match (self.boundaries.first(), self.boundaries.last()) {
+            (Some(start), Some(end)) => Ok(analytics_api::TimeRange {
+                start_time: *start,
+                end_time: Some(
+                    end.checked_sub(time::Duration::MICROSECOND)
+                        .ok_or(PostProcessingError::BucketClipping)
+                        .into_report()?,
+                ),
+            }),
+            _ => Err(PostProcessingError::InvalidBucketBoundaries).into_report(),
+        }
+    }
+
+    /// Groups the rows into the bucket their timestamp falls in, rows outside of every bucket
+    /// being dropped. All the buckets are returned in order, those without any row with an empty
+    /// list, so the series has no gaps.
+    pub fn align<R>(
+        &self,
+        rows: impl IntoIterator<Item = (time::PrimitiveDateTime, R)>,
+    ) -> Vec<(analytics_api::TimeRange, Vec<R>)> {
+        let mut buckets = self
+            .boundaries
+            .windows(2)
+            .filter_map(|pair| match pair {
+                [start, end] => Some((
+                    analytics_api::TimeRange {
+                        start_time: *start,
+                        end_time: Some(*end),
+                    },
+                    Vec::new(),
+                )),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for (timestamp, row) in rows {
+            let bucket = self
+                .boundaries
+                .partition_point(|boundary| *boundary <= timestamp)
+                .checked_sub(1)
+                .and_then(|index| buckets.get_mut(index));
+            if let Some((_, bucket_rows)) = bucket {
+                bucket_rows.push(row);
+            }
+        }
+        buckets
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum QueryBuildingError {
     #[allow(dead_code)]
@@ -207,6 +550,10 @@ pub enum QueryBuildingError {
 pub enum PostProcessingError {
     #[error("Error Clipping values to bucket sizes")]
     BucketClipping,
+    #[error("Error converting storage timestamps to UTC")]
+    StorageTimezoneConversion,
+    #[error("Invalid bucket boundaries")]
+    InvalidBucketBoundaries,
 }
 
 #[derive(Debug)]
@@ -215,6 +562,10 @@ pub enum Aggregate<R> {
         field: Option<R>,
         alias: Option<&'static str>,
     },
+    DistinctCount {
+        field: R,
+        alias: Option<&'static str>,
+    },
     Sum {
         field: R,
         alias: Option<&'static str>,
@@ -227,6 +578,98 @@ pub enum Aggregate<R> {
         field: R,
         alias: Option<&'static str>,
     },
+    /// Continuous percentile of the field, `percentile` being expressed out of 100
+    Percentile {
+        field: R,
+        alias: Option<&'static str>,
+        percentile: u8,
+    },
+    /// Counts the rows matching `condition`, letting a single query return several counts over
+    /// different subsets of the same rows
+    CountIf {
+        condition: R,
+        alias: Option<&'static str>,
+    },
+}
+
+/// Window function evaluated over the groups of the query, e.g. to compare every bucket with the
+/// previous one. Groups are numbered within every combination of `partition_by`, following
+/// `order_by`. Without `order_by` the function is evaluated over all the groups of the partition,
+/// e.g. to take the share of every group in the partition total.
+#[derive(Debug)]
+pub struct Window<R, P> {
+    pub function: R,
+    pub partition_by: Vec<P>,
+    pub order_by: Option<R>,
+    pub alias: Option<&'static str>,
+}
+
+/// Select expressions paired with the name of the row field they are loaded into.
+///
+/// Every expression is aliased to its field name, so the loader can pick it up by name
+/// irrespective of the position of the column in the select clause.
+pub struct ColumnMapping<T: AnalyticsDataSource> {
+    columns: Vec<(Box<dyn ToSql<T> + Send + Sync>, &'static str)>,
+}
+
+impl<T: AnalyticsDataSource> Default for ColumnMapping<T> {
+    fn default() -> Self {
+        Self {
+            columns: Vec::new(),
+        }
+    }
+}
+
+impl<T: AnalyticsDataSource> ColumnMapping<T> {
+    /// The expression is expected to be unaliased, the field name is used as its alias.
+    pub fn map(
+        mut self,
+        sql_expr: impl ToSql<T> + Send + Sync + 'static,
+        field_name: &'static str,
+    ) -> Self {
+        self.columns.push((Box::new(sql_expr), field_name));
+        self
+    }
+
+    pub fn field_names(&self) -> Vec<&'static str> {
+        self.columns.iter().map(|(_, field)| *field).collect()
+    }
+}
+
+/// Columns of a grouped query folded into one JSON object per bucket, see
+/// [`QueryBuilder::set_json_aggregation`]
+#[derive(Debug)]
+struct JsonAggregation {
+    style: JsonAggregationStyle,
+    buckets: Vec<String>,
+    key: String,
+    value: String,
+    alias: &'static str,
+}
+
+impl JsonAggregation {
+    fn wrap(&self, query: &str, clause_separator: &str) -> String {
+        let buckets = self.buckets.join(", ");
+        let (object, subquery_alias) = match self.style {
+            JsonAggregationStyle::MapFromArrays => (
+                format!(
+                    "toJSONString(mapFromArrays(groupArray({}), groupArray({})))",
+                    self.key, self.value
+                ),
+                "",
+            ),
+            // Postgres requires subqueries in FROM to be aliased
+            JsonAggregationStyle::ObjectAgg => (
+                format!("json_object_agg({}, {})", self.key, self.value),
+                " AS grouped",
+            ),
+        };
+        format!(
+            "SELECT {buckets}, {object} as {}{clause_separator}FROM ({query}){subquery_alias}\
+             {clause_separator}GROUP BY {buckets}",
+            self.alias
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -236,16 +679,66 @@ where
     AnalyticsCollection: ToSql<T>,
 {
     columns: Vec<String>,
-    filters: Vec<(String, FilterTypes, String)>,
+    filters: Vec<FilterExpr>,
     group_by: Vec<String>,
+    cube: Vec<String>,
     having: Option<Vec<(String, FilterTypes, String)>>,
+    order_by: Vec<String>,
+    limit: Option<u64>,
     table: AnalyticsCollection,
+    joins: Vec<(JoinType, String, String)>,
     distinct: bool,
+    distinct_on: Vec<String>,
+    group_by_ordinal: bool,
+    sample: Option<f64>,
+    json_aggregation: Option<JsonAggregation>,
+    query_hint: Option<String>,
+    redact_logged_query: bool,
+    quote_identifiers: bool,
+    bucket_column: Column,
+    storage_timezone: time::UtcOffset,
+    schema: Option<String>,
+    has_aggregates: bool,
     db_type: PhantomData<T>,
 }
 
+/// Table columns referenced by the analytics queries apart from the dimensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum Column {
+    PaymentId,
+    MerchantId,
+    Amount,
+    CaptureMethod,
+    CreatedAt,
+    ModifiedAt,
+    /// Position of the attempt among the attempts of its payment, starting at 1
+    AttemptNumber,
+    /// Authorized amount of the attempt which was not captured on its payment
+    CaptureGap,
+    /// Whether the payment was made off session, i.e. charged through a mandate without the
+    /// customer being present
+    OffSession,
+}
+
 pub trait ToSql<T: AnalyticsDataSource> {
     fn to_sql(&self) -> error_stack::Result<String, ParsingError>;
+
+    /// Whether the serialized expression aggregates over rows
+    fn is_aggregate(&self) -> bool {
+        false
+    }
+
+    /// Whether the value serializes to a numeric literal, which range comparisons leave unquoted
+    fn is_numeric(&self) -> bool {
+        false
+    }
+
+    /// Serializes a table with `schema` qualifying the tables it reads from. Plain tables are
+    /// prefixed as a whole, derived tables override this to qualify the tables they select from.
+    fn to_sql_in_schema(&self, schema: &str) -> error_stack::Result<String, ParsingError> {
+        Ok(format!("{schema}.{}", self.to_sql()?))
+    }
 }
 
 /// Implement `ToSql` on arrays of types that impl `ToString`.
@@ -264,32 +757,349 @@ macro_rules! impl_to_sql_for_to_string {
 impl_to_sql_for_to_string!(
     String,
     &str,
-    &PaymentDimensions,
     &RefundDimensions,
-    PaymentDimensions,
     RefundDimensions,
+    &DisputeDimensions,
+    DisputeDimensions,
+    DisputeStage,
     PaymentMethod,
+    PaymentMethodType,
+    CardNetwork,
     AuthenticationType,
     Connector,
     AttemptStatus,
+    CaptureMethod,
     RefundStatus,
     storage_enums::RefundStatus,
     Currency,
+    CountryAlpha2,
     RefundType,
     &String,
     &bool,
-    &u64
+    &u64,
+    u8
 );
 
+/// Payment attempts do not carry the business profile, it is looked up from the payment intent
+/// instead. The subquery is exposed as `profile_id` in the result set.
+const PAYMENT_PROFILE_ID_EXPRESSION: &str = "(SELECT profile_id FROM payment_intent \
+WHERE payment_intent.payment_id = payment_attempt.payment_id \
+AND payment_intent.merchant_id = payment_attempt.merchant_id)";
+
+/// The card network is only stored within the additional card details of the attempt's payment
+/// method data. Like the profile id it is wrapped in a subquery so it's exposed as `card_network`.
+const PAYMENT_CARD_NETWORK_EXPRESSION: &str = "(SELECT card_network \
+FROM jsonb_to_record(payment_attempt.payment_method_data -> 'card') AS card(card_network text))";
+
+/// Day of the week the attempt was created on, from 0 (Sunday) to 6 (Saturday). The value is
+/// taken in the storage timezone and exposed as `day_of_week`.
+const PAYMENT_DAY_OF_WEEK_EXPRESSION: &str = "(SELECT day_of_week \
+FROM int4(date_part('dow', payment_attempt.created_at)) AS cell(day_of_week))";
+
+/// Hour of the day the attempt was created in, from 0 to 23. Like the day of the week it is taken
+/// in the storage timezone and exposed as `hour_of_day`.
+const PAYMENT_HOUR_OF_DAY_EXPRESSION: &str = "(SELECT hour_of_day \
+FROM int4(date_part('hour', payment_attempt.created_at)) AS cell(hour_of_day))";
+
+/// The billing address is referenced by the payment intent. The address lookup is nested so the
+/// subquery is exposed as `billing_country`.
+const PAYMENT_BILLING_COUNTRY_EXPRESSION: &str = "(SELECT billing_country \
+FROM (SELECT address.country AS billing_country FROM payment_intent \
+JOIN address ON address.address_id = payment_intent.billing_address_id \
+WHERE payment_intent.payment_id = payment_attempt.payment_id \
+AND payment_intent.merchant_id = payment_attempt.merchant_id) AS billing_address)";
+
+/// The BIN is cut from the card ISIN stored with the payment method data, which never holds more
+/// than the leading digits of the card number, and is capped to 6 digits so no longer prefix of
+/// the PAN is ever selected. Wrapped in a subquery so it's exposed as `card_bin`.
+const PAYMENT_CARD_BIN_EXPRESSION: &str = "(SELECT card_bin \
+FROM substring(payment_attempt.payment_method_data -> 'card' ->> 'card_isin', 1, 6) \
+AS card(card_bin))";
+
+/// Country of the bank which issued the card, as looked up from the card BIN when the attempt
+/// was made and stored with the payment method data. Wrapped in a subquery so it's exposed as
+/// `issuer_country`.
+const PAYMENT_ISSUER_COUNTRY_EXPRESSION: &str = "(SELECT issuer_country \
+FROM jsonb_extract_path_text(payment_attempt.payment_method_data, 'card', 'card_issuing_country') \
+AS card(issuer_country))";
+
+/// Attempts only record their creation time, so the attempt number is the count of attempts of
+/// the same payment created up to and including this one.
+const PAYMENT_ATTEMPT_NUMBER_EXPRESSION: &str = "(SELECT count(*) \
+FROM payment_attempt AS earlier_attempt \
+WHERE earlier_attempt.payment_id = payment_attempt.payment_id \
+AND earlier_attempt.merchant_id = payment_attempt.merchant_id \
+AND earlier_attempt.created_at <= payment_attempt.created_at)";
+
+/// The captured amount is only tracked on the payment intent, see also
+/// [`PAYMENT_PROFILE_ID_EXPRESSION`].
+const PAYMENT_CAPTURE_GAP_EXPRESSION: &str = "(amount - (SELECT amount_captured \
+FROM payment_intent \
+WHERE payment_intent.payment_id = payment_attempt.payment_id \
+AND payment_intent.merchant_id = payment_attempt.merchant_id))";
+
+/// Whether a payment is off session is only stored on the payment intent, see also
+/// [`PAYMENT_PROFILE_ID_EXPRESSION`].
+const PAYMENT_OFF_SESSION_EXPRESSION: &str = "(SELECT off_session FROM payment_intent \
+WHERE payment_intent.payment_id = payment_attempt.payment_id \
+AND payment_intent.merchant_id = payment_attempt.merchant_id)";
+
+impl<T, R, P> ToSql<T> for Window<R, P>
+where
+    T: AnalyticsDataSource,
+    R: ToSql<T>,
+    P: ToSql<T>,
+{
+    fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+        let partition_by = self
+            .partition_by
+            .iter()
+            .map(|column| column.to_sql())
+            .collect::<error_stack::Result<Vec<_>, _>>()
+            .attach_printable("Failed to serialize window partition")?;
+        let partition_by =
+            (!partition_by.is_empty()).then(|| format!("PARTITION BY {}", partition_by.join(", ")));
+        let order_by = self
+            .order_by
+            .as_ref()
+            .map(|order_by| order_by.to_sql())
+            .transpose()
+            .attach_printable("Failed to serialize window order")?
+            .map(|order_by| format!("ORDER BY {order_by}"));
+        Ok(format!(
+            "{} OVER ({}){}",
+            self.function
+                .to_sql()
+                .attach_printable("Failed to serialize window function")?,
+            partition_by
+                .into_iter()
+                .chain(order_by)
+                .collect::<Vec<_>>()
+                .join(" "),
+            self.alias
+                .map_or_else(|| "".to_owned(), |alias| format!(" as {alias}"))
+        ))
+    }
+}
+
+/// Column emitted quoted, for a column named after a reserved word of the data source, e.g.
+/// `order` or `user`
+#[derive(Debug, Clone, Copy)]
+pub struct QuotedIdentifier<'a>(pub &'a str);
+
+impl<T: AnalyticsDataSource> ToSql<T> for QuotedIdentifier<'_> {
+    fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+        Ok(quote_identifier::<T>(self.0))
+    }
+}
+
+impl<T: AnalyticsDataSource> ToSql<T> for Column {
+    fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+        Ok(match self {
+            Self::AttemptNumber => PAYMENT_ATTEMPT_NUMBER_EXPRESSION.to_string(),
+            Self::CaptureGap => PAYMENT_CAPTURE_GAP_EXPRESSION.to_string(),
+            Self::OffSession => PAYMENT_OFF_SESSION_EXPRESSION.to_string(),
+            Self::PaymentId
+            | Self::MerchantId
+            | Self::Amount
+            | Self::CaptureMethod
+            | Self::CreatedAt
+            | Self::ModifiedAt => T::COLUMN_NAME_OVERRIDES
+                .iter()
+                .find(|(column, _)| column == self)
+                .map_or_else(|| self.to_string(), |(_, name)| name.to_string()),
+        })
+    }
+}
+
+impl<T: AnalyticsDataSource> ToSql<T> for PaymentDimensions {
+    fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+        Ok(match self {
+            Self::ProfileId => PAYMENT_PROFILE_ID_EXPRESSION.to_string(),
+            Self::CardNetwork => PAYMENT_CARD_NETWORK_EXPRESSION.to_string(),
+            Self::DayOfWeek => PAYMENT_DAY_OF_WEEK_EXPRESSION.to_string(),
+            Self::HourOfDay => PAYMENT_HOUR_OF_DAY_EXPRESSION.to_string(),
+            Self::BillingCountry => PAYMENT_BILLING_COUNTRY_EXPRESSION.to_string(),
+            Self::CardBin => PAYMENT_CARD_BIN_EXPRESSION.to_string(),
+            Self::IssuerCountry => PAYMENT_ISSUER_COUNTRY_EXPRESSION.to_string(),
+            Self::Connector
+            | Self::PaymentMethod
+            | Self::Currency
+            | Self::AuthType
+            | Self::PaymentStatus
+            | Self::PaymentMethodType => self.to_string(),
+        })
+    }
+}
+
+impl<T: AnalyticsDataSource> ToSql<T> for &PaymentDimensions {
+    fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+        <PaymentDimensions as ToSql<T>>::to_sql(self)
+    }
+}
+
+/// Types a filter key can be cast to before it's compared
+#[derive(Debug, Clone, Copy, strum::Display)]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum CastType {
+    Integer,
+    BigInt,
+    Numeric,
+    Text,
+}
+
+/// Direction rows are sorted in by an `ORDER BY` clause
+#[derive(Debug, Clone, Copy, strum::Display)]
+pub enum Order {
+    #[strum(serialize = "ASC")]
+    Ascending,
+    #[strum(serialize = "DESC")]
+    Descending,
+}
+
+/// Placement of null values in an `ORDER BY` clause, regardless of the direction rows are sorted
+/// in. Data sources differ on where they put nulls by default.
+#[derive(Debug, Clone, Copy, strum::Display)]
+pub enum NullsOrder {
+    #[strum(serialize = "NULLS FIRST")]
+    First,
+    #[strum(serialize = "NULLS LAST")]
+    Last,
+}
+
+/// Replaces filter values in logged queries which are redacted
+const REDACTED_FILTER_VALUE: &str = "***";
+
+/// Columns holding card data or credentials, which must never be selected by analytics queries
+const SENSITIVE_COLUMNS: &[&str] = &[
+    "card_number",
+    "card_cvc",
+    "enc_card_data",
+    "payment_token",
+    "client_secret",
+    "direct_debit_token",
+    "payout_token",
+];
+
+/// Rejects select expressions referencing any of [`SENSITIVE_COLUMNS`], as a column or as a key
+/// of a JSON column
+fn check_not_sensitive(expression: &str) -> QueryResult<()> {
+    let is_sensitive = expression
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .any(|identifier| SENSITIVE_COLUMNS.contains(&identifier.to_ascii_lowercase().as_str()));
+    if is_sensitive {
+        Err(QueryBuildingError::InvalidQuery(
+            "Sensitive columns cannot be selected",
+        ))
+        .into_report()?;
+    }
+    Ok(())
+}
+
+/// Kind of join adding another table to the query
+#[derive(Debug, Clone, Copy, strum::Display)]
+pub enum JoinType {
+    #[strum(serialize = "INNER JOIN")]
+    Inner,
+    #[strum(serialize = "LEFT JOIN")]
+    Left,
+}
+
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum FilterTypes {
     Equal,
     EqualBool,
     In,
     Gte,
     Lte,
+    /// `Gte` against a numeric literal, left unquoted. Used in place of `Gte` when the value is
+    /// numeric, see [`ToSql::is_numeric`].
+    NumericGte,
+    /// `Lte` against a numeric literal, left unquoted
+    NumericLte,
     Gt,
+    /// Matches rows where the key is false or null, the value is ignored
+    IsNotTrue,
+    /// Matches rows where the key is null, the value is ignored
+    IsNull,
+}
+
+/// Boolean expression over filter conditions. The filters of a query form an implicit `And` of
+/// expressions, the flat filter clauses adding a `Leaf` each.
+#[derive(Debug, Clone)]
+pub enum FilterExpr {
+    And(Vec<FilterExpr>),
+    Or(Vec<FilterExpr>),
+    Not(Box<FilterExpr>),
+    /// Serialized key, comparison and serialized value
+    Leaf(String, FilterTypes, String),
+}
+
+impl FilterExpr {
+    /// Renders the expression, parenthesizing compound operands so they keep their meaning
+    /// whatever the precedence of the enclosing operator
+    fn to_sql_string(&self) -> String {
+        match self {
+            Self::And(exprs) => Self::join(exprs, " AND ", "TRUE"),
+            Self::Or(exprs) => Self::join(exprs, " OR ", "FALSE"),
+            Self::Not(expr) => format!("NOT {}", expr.to_operand_string()),
+            Self::Leaf(l, op, r) => match op {
+                FilterTypes::EqualBool => format!("{l} = {r}"),
+                FilterTypes::Equal => format!("{l} = '{}'", r.replace('\'', "''")),
+                FilterTypes::In => format!("{l} IN ({r})"),
+                FilterTypes::Gte => format!("{l} >= '{}'", r.replace('\'', "''")),
+                FilterTypes::Gt => format!("{l} > {r}"),
+                FilterTypes::Lte => format!("{l} <= '{}'", r.replace('\'', "''")),
+                FilterTypes::NumericGte => format!("{l} >= {r}"),
+                FilterTypes::NumericLte => format!("{l} <= {r}"),
+                FilterTypes::IsNotTrue => format!("{l} IS NOT TRUE"),
+                FilterTypes::IsNull => format!("{l} IS NULL"),
+            },
+        }
+    }
+
+    fn to_operand_string(&self) -> String {
+        match self {
+            Self::Leaf(..) | Self::Not(_) => self.to_sql_string(),
+            Self::And(exprs) | Self::Or(exprs) if exprs.len() <= 1 => self.to_sql_string(),
+            Self::And(_) | Self::Or(_) => format!("({})", self.to_sql_string()),
+        }
+    }
+
+    /// An empty conjunction holds for every row and an empty disjunction for none
+    fn join(exprs: &[Self], separator: &str, empty: &str) -> String {
+        if exprs.is_empty() {
+            return empty.to_string();
+        }
+        exprs
+            .iter()
+            .map(Self::to_operand_string)
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    /// Same expression with every filter value replaced, see
+    /// [`QueryBuilder::redact_logged_query`]
+    fn redacted(&self) -> Self {
+        match self {
+            Self::And(exprs) => Self::And(exprs.iter().map(Self::redacted).collect()),
+            Self::Or(exprs) => Self::Or(exprs.iter().map(Self::redacted).collect()),
+            Self::Not(expr) => Self::Not(Box::new(expr.redacted())),
+            Self::Leaf(l, op, _) => Self::Leaf(l.clone(), *op, REDACTED_FILTER_VALUE.to_string()),
+        }
+    }
+
+    /// Same expression with every bare column name quoted, see
+    /// [`QueryBuilder::quote_identifiers`]
+    fn quoted<T: AnalyticsDataSource>(&self) -> Self {
+        match self {
+            Self::And(exprs) => Self::And(exprs.iter().map(Self::quoted::<T>).collect()),
+            Self::Or(exprs) => Self::Or(exprs.iter().map(Self::quoted::<T>).collect()),
+            Self::Not(expr) => Self::Not(Box::new(expr.quoted::<T>())),
+            Self::Leaf(l, op, r) => Self::Leaf(quote_if_identifier::<T>(l), *op, r.clone()),
+        }
+    }
 }
 
 impl<T> QueryBuilder<T>
@@ -302,20 +1112,52 @@ where
             columns: Default::default(),
             filters: Default::default(),
             group_by: Default::default(),
+            cube: Default::default(),
             having: Default::default(),
+            order_by: Default::default(),
+            limit: Default::default(),
             table,
+            joins: Default::default(),
             distinct: Default::default(),
+            distinct_on: Default::default(),
+            group_by_ordinal: Default::default(),
+            sample: Default::default(),
+            json_aggregation: Default::default(),
+            query_hint: Default::default(),
+            redact_logged_query: Default::default(),
+            quote_identifiers: Default::default(),
+            bucket_column: Column::ModifiedAt,
+            storage_timezone: time::UtcOffset::UTC,
+            schema: Default::default(),
+            has_aggregates: Default::default(),
             db_type: Default::default(),
         }
     }
 
     pub fn add_select_column(&mut self, column: impl ToSql<T>) -> QueryResult<()> {
-        self.columns.push(
-            column
+        let is_aggregate = column.is_aggregate();
+        let column = column
+            .to_sql()
+            .change_context(QueryBuildingError::SqlSerializeError)
+            .attach_printable("Error serializing select column")?;
+        check_not_sensitive(&column)?;
+        self.has_aggregates |= is_aggregate;
+        self.columns.push(column);
+        Ok(())
+    }
+
+    pub fn add_column_mapping(&mut self, mapping: ColumnMapping<T>) -> QueryResult<()> {
+        for (sql_expr, field_name) in mapping.columns {
+            self.has_aggregates |= sql_expr.is_aggregate();
+            let sql_expr = sql_expr
                 .to_sql()
                 .change_context(QueryBuildingError::SqlSerializeError)
-                .attach_printable("Error serializing select column")?,
-        );
+                .attach_printable_lazy(|| {
+                    format!("Error serializing mapped column for field {field_name}")
+                })?;
+            check_not_sensitive(&sql_expr)?;
+            self.columns.push(format!("{sql_expr} as {field_name}"));
+        }
         Ok(())
     }
 
@@ -323,52 +1165,302 @@ where
         self.distinct = true
     }
 
-    pub fn add_filter_clause(
-        &mut self,
-        key: impl ToSql<T>,
-        value: impl ToSql<T>,
-    ) -> QueryResult<()> {
-        self.add_custom_filter_clause(key, value, FilterTypes::Equal)
+    /// Keeps a single row per combination of values of `columns`, the first one in the order of
+    /// the query, e.g. the latest attempt of every payment. The `ORDER BY` clause has to start
+    /// with the same columns for the kept row to be deterministic.
+    pub fn set_distinct_on(&mut self, columns: &[impl ToSql<T>]) -> QueryResult<()> {
+        if !T::SUPPORTS_DISTINCT_ON {
+            Err(QueryBuildingError::InvalidQuery(
+                "DISTINCT ON is not supported by the data source",
+            ))
+            .into_report()?;
+        }
+        if columns.is_empty() {
+            Err(QueryBuildingError::InvalidQuery(
+                "DISTINCT ON requires at least one column",
+            ))
+            .into_report()?;
+        }
+        self.distinct_on = columns
+            .iter()
+            .map(|column| {
+                column
+                    .to_sql()
+                    .change_context(QueryBuildingError::SqlSerializeError)
+                    .attach_printable("Error serializing distinct on column")
+            })
+            .collect::<QueryResult<_>>()?;
+        Ok(())
     }
 
-    pub fn add_bool_filter_clause(
-        &mut self,
-        key: impl ToSql<T>,
-        value: impl ToSql<T>,
-    ) -> QueryResult<()> {
-        self.add_custom_filter_clause(key, value, FilterTypes::EqualBool)
+    /// Whether the `ORDER BY` clause starts with the `DISTINCT ON` columns, in any order
+    fn is_ordered_by_distinct_on(&self) -> bool {
+        let leading_order_by = self
+            .order_by
+            .iter()
+            .take(self.distinct_on.len())
+            .map(|order_by| {
+                let order_by = [NullsOrder::First, NullsOrder::Last]
+                    .iter()
+                    .find_map(|nulls| order_by.strip_suffix(&format!(" {nulls}")))
+                    .unwrap_or(order_by);
+                order_by
+                    .rsplit_once(' ')
+                    .map_or(order_by, |(expression, _)| expression)
+            })
+            .collect::<HashSet<_>>();
+        self.order_by.len() >= self.distinct_on.len()
+            && self
+                .distinct_on
+                .iter()
+                .all(|column| leading_order_by.contains(column.as_str()))
     }
 
-    pub fn add_custom_filter_clause(
-        &mut self,
-        lhs: impl ToSql<T>,
-        rhs: impl ToSql<T>,
-        comparison: FilterTypes,
-    ) -> QueryResult<()> {
-        self.filters.push((
-            lhs.to_sql()
-                .change_context(QueryBuildingError::SqlSerializeError)
-                .attach_printable("Error serializing filter key")?,
-            comparison,
-            rhs.to_sql()
-                .change_context(QueryBuildingError::SqlSerializeError)
-                .attach_printable("Error serializing filter value")?,
-        ));
-        Ok(())
+    /// Sets the timezone of the timestamps held by the data source, used to convert time range
+    /// filters and granularity buckets from and to UTC. Needs to be set before adding those.
+    pub fn set_storage_timezone(&mut self, storage_timezone: time::UtcOffset) {
+        self.storage_timezone = storage_timezone
     }
 
-    pub fn add_filter_in_range_clause(
-        &mut self,
-        key: impl ToSql<T>,
-        values: &[impl ToSql<T>],
-    ) -> QueryResult<()> {
-        let list = values
-            .iter()
-            .map(|i| {
-                // trimming whitespaces from the filter values received in request, to prevent a possibility of an SQL injection
-                i.to_sql().map(|s| {
-                    let trimmed_str = s.replace(' ', "");
-                    format!("'{trimmed_str}'")
+    /// Qualifies the tables the query reads from with a schema, see
+    /// [`AnalyticsDataSource::schema`]. Applies to the collection, including the tables a derived
+    /// collection selects from, and to joined tables. Subqueries written out within the selected
+    /// columns or filters name their tables as they are.
+    pub fn set_schema(&mut self, schema: Option<&str>) {
+        self.schema = schema.map(str::to_owned);
+    }
+
+    /// Sets the timestamp column rows are bucketed on by granularity, `modified_at` by default
+    pub fn set_bucket_column(&mut self, column: Column) {
+        self.bucket_column = column
+    }
+
+    fn get_bucket_column(&self) -> QueryResult<String> {
+        <Column as ToSql<T>>::to_sql(&self.bucket_column)
+            .change_context(QueryBuildingError::SqlSerializeError)
+            .attach_printable("Error serializing bucket column")
+    }
+
+    /// Converts a UTC timestamp into the storage timezone, for comparisons against the
+    /// timestamps held by the data source
+    pub fn utc_to_storage_time(
+        &self,
+        value: time::PrimitiveDateTime,
+    ) -> QueryResult<time::PrimitiveDateTime> {
+        value
+            .checked_add(time::Duration::seconds(
+                self.storage_timezone.whole_seconds().into(),
+            ))
+            .ok_or(QueryBuildingError::InvalidQuery(
+                "Time range is out of bounds in the storage timezone",
+            ))
+            .into_report()
+    }
+
+    /// Makes `GROUP BY` reference selected columns by their position instead of repeating their
+    /// expressions. Has no effect if the data source doesn't support ordinals.
+    pub fn set_group_by_ordinal(&mut self) {
+        self.group_by_ordinal = T::SUPPORTS_GROUP_BY_ORDINAL
+    }
+
+    /// Folds the rows of the grouped query into one row per combination of `buckets`, with the
+    /// `value` of every `key` in a JSON object aliased `alias`, for dashboards rendering arbitrary
+    /// aggregates without fixed columns. All of them are names of columns selected by the query.
+    pub fn set_json_aggregation(
+        &mut self,
+        buckets: &[&str],
+        key: &str,
+        value: &str,
+        alias: &'static str,
+    ) -> QueryResult<()> {
+        let style = T::JSON_AGGREGATION_STYLE
+            .ok_or(QueryBuildingError::InvalidQuery(
+                "JSON aggregation is not supported by the data source",
+            ))
+            .into_report()?;
+        if buckets.is_empty() {
+            Err(QueryBuildingError::InvalidQuery(
+                "JSON aggregation requires at least one bucket column",
+            ))
+            .into_report()?;
+        }
+        self.json_aggregation = Some(JsonAggregation {
+            style,
+            buckets: buckets.iter().map(|bucket| bucket.to_string()).collect(),
+            key: key.to_string(),
+            value: value.to_string(),
+            alias,
+        });
+        Ok(())
+    }
+
+    /// Masks the filter values of the query when it's logged, as they may hold personal data.
+    /// The query which is run keeps its values.
+    pub fn redact_logged_query(&mut self) {
+        self.redact_logged_query = true
+    }
+
+    /// Quotes every bare column name among the selected columns, the `GROUP BY` expressions and
+    /// the filtered columns. Expressions are left as is, a reserved-word column within one has
+    /// to be quoted with [`QuotedIdentifier`] instead.
+    pub fn quote_identifiers(&mut self) {
+        self.quote_identifiers = true
+    }
+
+    fn render_identifier(&self, expression: &str) -> String {
+        if self.quote_identifiers {
+            quote_if_identifier::<T>(expression)
+        } else {
+            expression.to_owned()
+        }
+    }
+
+    /// Adds a planner hint to the query, written as the data source expects it, e.g.
+    /// `IndexScan(payment_attempt)` on Postgres or `max_threads = 8` as settings. The hint is
+    /// inlined as is, so it must never be built from request input.
+    pub fn set_query_hint(&mut self, hint: impl Into<String>) {
+        self.query_hint = Some(hint.into())
+    }
+
+    /// Reads only `fraction` of the rows of the table, for approximate results over very large
+    /// tables. Has no effect if the data source doesn't support sampling. Only plain tables can
+    /// be sampled, not the collections derived from a subquery.
+    pub fn set_sample(&mut self, fraction: f64) -> QueryResult<()> {
+        if fraction.is_nan() || fraction <= 0.0 || fraction > 1.0 {
+            Err(QueryBuildingError::InvalidQuery(
+                "Sample fraction must be greater than 0 and at most 1",
+            ))
+            .into_report()?;
+        }
+        self.sample = Some(fraction).filter(|_| T::SAMPLE_STYLE.is_some());
+        Ok(())
+    }
+
+    /// Factor counts and sums have to be multiplied by to estimate their value over the whole
+    /// table when the query is sampled
+    pub fn get_sample_scale_factor(&self) -> f64 {
+        self.sample.map_or(1.0, |fraction| 1.0 / fraction)
+    }
+
+    pub fn add_filter_clause(
+        &mut self,
+        key: impl ToSql<T>,
+        value: impl ToSql<T>,
+    ) -> QueryResult<()> {
+        self.add_custom_filter_clause(key, value, FilterTypes::Equal)
+    }
+
+    pub fn add_optional_filter_clause(
+        &mut self,
+        key: impl ToSql<T>,
+        value: Option<impl ToSql<T>>,
+    ) -> QueryResult<()> {
+        value.map_or(Ok(()), |value| self.add_filter_clause(key, value))
+    }
+
+    pub fn add_bool_filter_clause(
+        &mut self,
+        key: impl ToSql<T>,
+        value: impl ToSql<T>,
+    ) -> QueryResult<()> {
+        self.add_custom_filter_clause(key, value, FilterTypes::EqualBool)
+    }
+
+    /// Excludes rows where the flag `column` is set, e.g. payments tagged as internal tests.
+    /// Rows where the flag is null are kept.
+    pub fn add_exclude_flag_filter_clause(&mut self, column: impl ToSql<T>) -> QueryResult<()> {
+        self.add_custom_filter_clause(column, "", FilterTypes::IsNotTrue)
+    }
+
+    /// Keeps rows where the nullable flag `key` is in one of `states`, `None` standing for null,
+    /// e.g. `[Some(true), None]` for a flag which older rows never set
+    pub fn add_tri_state_bool_filter_clause(
+        &mut self,
+        key: impl ToSql<T>,
+        states: &[Option<bool>],
+    ) -> QueryResult<()> {
+        let key = key
+            .to_sql()
+            .change_context(QueryBuildingError::SqlSerializeError)
+            .attach_printable("Error serializing filter key")?;
+        let mut states = states.to_vec();
+        states.sort();
+        states.dedup();
+        // every state allowed, the filter would keep all rows
+        if states.len() == 3 {
+            return Ok(());
+        }
+        self.filters.push(FilterExpr::Or(
+            states
+                .into_iter()
+                .map(|state| match state {
+                    Some(value) => {
+                        FilterExpr::Leaf(key.clone(), FilterTypes::EqualBool, value.to_string())
+                    }
+                    None => FilterExpr::Leaf(key.clone(), FilterTypes::IsNull, String::new()),
+                })
+                .collect(),
+        ));
+        Ok(())
+    }
+
+    pub fn add_custom_filter_clause(
+        &mut self,
+        lhs: impl ToSql<T>,
+        rhs: impl ToSql<T>,
+        comparison: FilterTypes,
+    ) -> QueryResult<()> {
+        let comparison = match comparison {
+            FilterTypes::Gte if rhs.is_numeric() => FilterTypes::NumericGte,
+            FilterTypes::Lte if rhs.is_numeric() => FilterTypes::NumericLte,
+            comparison => comparison,
+        };
+        self.filters.push(FilterExpr::Leaf(
+            lhs.to_sql()
+                .change_context(QueryBuildingError::SqlSerializeError)
+                .attach_printable("Error serializing filter key")?,
+            comparison,
+            rhs.to_sql()
+                .change_context(QueryBuildingError::SqlSerializeError)
+                .attach_printable("Error serializing filter value")?,
+        ));
+        Ok(())
+    }
+
+    /// Adds a filter expression, for conditions which can't be expressed as a conjunction of
+    /// comparisons
+    pub fn add_filter_expr(&mut self, expr: FilterExpr) {
+        self.filters.push(expr);
+    }
+
+    /// Adds a filter comparing the key cast to `cast_type` with the value, for columns stored
+    /// with a different type than the one they should be compared as, e.g. numbers held as text.
+    pub fn add_custom_filter_clause_cast(
+        &mut self,
+        lhs: impl ToSql<T>,
+        rhs: impl ToSql<T>,
+        cast_type: CastType,
+        comparison: FilterTypes,
+    ) -> QueryResult<()> {
+        let lhs = lhs
+            .to_sql()
+            .change_context(QueryBuildingError::SqlSerializeError)
+            .attach_printable("Error serializing filter key")?;
+        self.add_custom_filter_clause(format!("CAST({lhs} AS {cast_type})"), rhs, comparison)
+    }
+
+    pub fn add_filter_in_range_clause(
+        &mut self,
+        key: impl ToSql<T>,
+        values: &[impl ToSql<T>],
+    ) -> QueryResult<()> {
+        let list = values
+            .iter()
+            .map(|i| {
+                // trimming whitespaces from the filter values received in request, to prevent a possibility of an SQL injection
+                i.to_sql().map(|s| {
+                    let trimmed_str = s.replace(' ', "");
+                    format!("'{trimmed_str}'")
                 })
             })
             .collect::<error_stack::Result<Vec<String>, ParsingError>>()
@@ -378,6 +1470,13 @@ where
         self.add_custom_filter_clause(key, list, FilterTypes::In)
     }
 
+    /// Filters on the connector being any of `connectors`, serialized the same way as they're
+    /// stored in the `connector` column
+    pub fn add_connector_filter(&mut self, connectors: &[Connector]) -> QueryResult<()> {
+        self.add_filter_in_range_clause(PaymentDimensions::Connector, connectors)
+            .attach_printable("Error adding connector filter")
+    }
+
     pub fn add_group_by_clause(&mut self, column: impl ToSql<T>) -> QueryResult<()> {
         self.group_by.push(
             column
@@ -388,6 +1487,132 @@ where
         Ok(())
     }
 
+    /// Groups the rows by every combination of `columns`, for cross-tab reports: besides the
+    /// groups of all the columns, a single query returns the subtotals over every subset of them,
+    /// down to the grand total. Columns left out of a subtotal are `NULL` in its row.
+    pub fn set_cube(&mut self, columns: &[impl ToSql<T>]) -> QueryResult<()> {
+        if !T::SUPPORTS_CUBE {
+            Err(QueryBuildingError::InvalidQuery(
+                "GROUP BY CUBE is not supported by the data source",
+            ))
+            .into_report()?;
+        }
+        if columns.is_empty() {
+            Err(QueryBuildingError::InvalidQuery(
+                "GROUP BY CUBE requires at least one column",
+            ))
+            .into_report()?;
+        }
+        self.cube = columns
+            .iter()
+            .map(|column| {
+                column
+                    .to_sql()
+                    .change_context(QueryBuildingError::SqlSerializeError)
+                    .attach_printable("Error serializing cube column")
+            })
+            .collect::<QueryResult<_>>()?;
+        Ok(())
+    }
+
+    /// Groups by the date of a timestamp column, for one-off daily groupings which don't need
+    /// the buckets of a [`Granularity`]. Days are the ones of the timestamps as stored, unlike
+    /// granularity buckets they are not shifted from the storage timezone to UTC.
+    pub fn add_date_group_by(&mut self, column: impl ToSql<T>) -> QueryResult<()> {
+        let column = column
+            .to_sql()
+            .change_context(QueryBuildingError::SqlSerializeError)
+            .attach_printable("Error serializing date group by field")?;
+        self.group_by.push(format!("CAST({column} AS DATE)"));
+        Ok(())
+    }
+
+    /// Joins `table` on all of the `on` conditions. Joining without any condition is rejected as
+    /// it pairs every row with every row of the joined table.
+    pub fn add_join_clause(
+        &mut self,
+        join_type: JoinType,
+        table: impl ToSql<T>,
+        on: &[impl ToSql<T>],
+    ) -> QueryResult<()> {
+        if on.is_empty() {
+            Err(QueryBuildingError::InvalidQuery(
+                "Join without an ON condition results in a cartesian product",
+            ))
+            .into_report()?;
+        }
+        let table = table
+            .to_sql()
+            .change_context(QueryBuildingError::SqlSerializeError)
+            .attach_printable("Error serializing joined table")?;
+        let on = on
+            .iter()
+            .map(|condition| condition.to_sql())
+            .collect::<error_stack::Result<Vec<String>, ParsingError>>()
+            .change_context(QueryBuildingError::SqlSerializeError)
+            .attach_printable("Error serializing join condition")?
+            .join(" AND ");
+        self.joins.push((join_type, table, on));
+        Ok(())
+    }
+
+    pub fn add_order_by_clause(&mut self, column: impl ToSql<T>, order: Order) -> QueryResult<()> {
+        let column = column
+            .to_sql()
+            .change_context(QueryBuildingError::SqlSerializeError)
+            .attach_printable("Error serializing order by field")?;
+        self.order_by.push(format!("{column} {order}"));
+        Ok(())
+    }
+
+    /// Orders by the column with its null values placed first or last
+    pub fn add_order_by_clause_with_nulls(
+        &mut self,
+        column: impl ToSql<T>,
+        order: Order,
+        nulls: NullsOrder,
+    ) -> QueryResult<()> {
+        let column = column
+            .to_sql()
+            .change_context(QueryBuildingError::SqlSerializeError)
+            .attach_printable("Error serializing order by field")?;
+        if T::SUPPORTS_NULLS_ORDER {
+            self.order_by.push(format!("{column} {order} {nulls}"));
+        } else {
+            // false sorts before true, so nulls come first when sorting descending on `IS NULL`
+            let nulls_order = match nulls {
+                NullsOrder::First => Order::Descending,
+                NullsOrder::Last => Order::Ascending,
+            };
+            self.order_by
+                .push(format!("{column} IS NULL {nulls_order}"));
+            self.order_by.push(format!("{column} {order}"));
+        }
+        Ok(())
+    }
+
+    /// Orders by a column aliased in the select clause, emitting the aliased expression in its
+    /// place for data sources which can't resolve select aliases in `ORDER BY`.
+    pub fn add_order_by_alias(&mut self, alias: &str, order: Order) -> QueryResult<()> {
+        let expression = self
+            .get_aliased_expression(alias)
+            .ok_or(QueryBuildingError::InvalidQuery(
+                "ORDER BY references an alias not declared in the select clause",
+            ))
+            .into_report()
+            .attach_printable_lazy(|| format!("Undeclared alias {alias}"))?;
+        if T::SUPPORTS_ORDER_BY_ALIAS {
+            self.order_by.push(format!("{alias} {order}"));
+        } else {
+            self.order_by.push(format!("{expression} {order}"));
+        }
+        Ok(())
+    }
+
+    pub fn set_limit_by(&mut self, limit: u64) {
+        self.limit = Some(limit)
+    }
+
     pub fn add_granularity_in_mins(&mut self, granularity: &Granularity) -> QueryResult<()> {
         let interval = match granularity {
             Granularity::OneMin => "1",
@@ -397,33 +1622,96 @@ where
             Granularity::OneHour => "60",
             Granularity::OneDay => "1440",
         };
+        let bucket_column = self.get_bucket_column()?;
         let _ = self.add_select_column(format!(
-            "toStartOfInterval(created_at, INTERVAL {interval} MINUTE) as time_bucket"
+            "toStartOfInterval({bucket_column}, INTERVAL {interval} MINUTE) as time_bucket"
         ));
         Ok(())
     }
 
-    fn get_filter_clause(&self) -> String {
+    /// The filters are the operands of an implicit `And`, joined with `separator`
+    fn get_filter_clause(&self, separator: &str) -> String {
         self.filters
             .iter()
-            .map(|(l, op, r)| match op {
-                FilterTypes::EqualBool => format!("{l} = {r}"),
-                FilterTypes::Equal => format!("{l} = '{r}'"),
-                FilterTypes::In => format!("{l} IN ({r})"),
-                FilterTypes::Gte => format!("{l} >= '{r}'"),
-                FilterTypes::Gt => format!("{l} > {r}"),
-                FilterTypes::Lte => format!("{l} <= '{r}'"),
+            .map(|filter| {
+                if self.quote_identifiers {
+                    filter.quoted::<T>().to_operand_string()
+                } else {
+                    filter.to_operand_string()
+                }
             })
             .collect::<Vec<String>>()
-            .join(" AND ")
+            .join(separator)
     }
 
+    /// Select columns are emitted in insertion order.
+    ///
+    /// Rows are mapped onto domain structs by column name (see [`LoadRow`]) and not by position,
+    /// so the order here does not need to follow the field order of the row struct. Every
+    /// selected column must however be named (or aliased) after a field of that struct.
     fn get_select_clause(&self) -> String {
-        self.columns.join(", ")
+        self.columns
+            .iter()
+            .map(|column| self.render_identifier(column))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Returns the names the selected columns are exposed as in the result set, i.e. the alias
+    /// for aliased expressions, the selected column for scalar subqueries and the raw expression
+    /// otherwise.
+    pub fn get_select_column_names(&self) -> Vec<&str> {
+        self.columns
+            .iter()
+            .map(|column| match column.rsplit_once(" as ") {
+                Some((_, alias)) => alias.trim(),
+                None => column
+                    .strip_prefix("(SELECT ")
+                    .and_then(|subquery| subquery.split_whitespace().next())
+                    .unwrap_or(column.as_str())
+                    .trim_matches(T::IDENTIFIER_QUOTE),
+            })
+            .collect()
+    }
+
+    /// Returns the expressions the rows are grouped by, excluding the `CUBE` columns
+    pub fn get_group_by_columns(&self) -> Vec<&str> {
+        self.group_by.iter().map(String::as_str).collect()
     }
 
     fn get_group_by_clause(&self) -> String {
-        self.group_by.join(", ")
+        let mut group_by = if self.group_by_ordinal {
+            self.group_by
+                .iter()
+                .map(|group_by| {
+                    self.columns
+                        .iter()
+                        .position(|column| {
+                            column == group_by
+                                || column
+                                    .rsplit_once(" as ")
+                                    .map_or(false, |(expression, _)| expression == group_by)
+                        })
+                        .map_or_else(
+                            || self.render_identifier(group_by),
+                            |index| (index + 1).to_string(),
+                        )
+                })
+                .collect::<Vec<_>>()
+        } else {
+            self.group_by
+                .iter()
+                .map(|group_by| self.render_identifier(group_by))
+                .collect()
+        };
+        if !self.cube.is_empty() {
+            group_by.push(format!("CUBE({})", self.cube.join(", ")));
+        }
+        group_by.join(", ")
+    }
+
+    fn is_grouped(&self) -> bool {
+        !self.group_by.is_empty() || !self.cube.is_empty()
     }
 
     #[allow(dead_code)]
@@ -444,13 +1732,48 @@ where
             .to_sql()
             .change_context(QueryBuildingError::SqlSerializeError)
             .attach_printable("Error serializing having value")?;
-        let entry = (aggregate, filter_type, value);
+        self.push_having_entry((aggregate, filter_type, value));
+        Ok(())
+    }
+
+    /// Adds a `HAVING` condition on a column aliased in the select clause. Postgres doesn't
+    /// resolve select aliases in `HAVING`, so the aliased expression is emitted in its place.
+    pub fn add_having_on_alias(
+        &mut self,
+        alias: &str,
+        filter_type: FilterTypes,
+        value: impl ToSql<T>,
+    ) -> QueryResult<()> {
+        let expression = self
+            .get_aliased_expression(alias)
+            .ok_or(QueryBuildingError::InvalidQuery(
+                "HAVING references an alias not declared in the select clause",
+            ))
+            .into_report()
+            .attach_printable_lazy(|| format!("Undeclared alias {alias}"))?;
+        let value = value
+            .to_sql()
+            .change_context(QueryBuildingError::SqlSerializeError)
+            .attach_printable("Error serializing having value")?;
+        self.push_having_entry((expression, filter_type, value));
+        Ok(())
+    }
+
+    fn get_aliased_expression(&self, alias: &str) -> Option<String> {
+        self.columns.iter().find_map(|column| {
+            column
+                .rsplit_once(" as ")
+                .filter(|(_, column_alias)| column_alias.trim() == alias)
+                .map(|(expression, _)| expression.to_string())
+        })
+    }
+
+    fn push_having_entry(&mut self, entry: (String, FilterTypes, String)) {
         if let Some(having) = &mut self.having {
             having.push(entry);
         } else {
             self.having = Some(vec![entry]);
         }
-        Ok(())
     }
 
     pub fn get_filter_type_clause(&self) -> Option<String> {
@@ -459,75 +1782,2278 @@ where
                 .map(|(l, op, r)| match op {
                     FilterTypes::Equal | FilterTypes::EqualBool => format!("{l} = {r}"),
                     FilterTypes::In => format!("{l} IN ({r})"),
-                    FilterTypes::Gte => format!("{l} >= {r}"),
-                    FilterTypes::Lte => format!("{l} < {r}"),
+                    FilterTypes::Gte | FilterTypes::NumericGte => format!("{l} >= {r}"),
+                    FilterTypes::Lte | FilterTypes::NumericLte => format!("{l} <= {r}"),
                     FilterTypes::Gt => format!("{l} > {r}"),
+                    FilterTypes::IsNotTrue => format!("{l} IS NOT TRUE"),
+                    FilterTypes::IsNull => format!("{l} IS NULL"),
                 })
                 .collect::<Vec<String>>()
                 .join(" AND ")
         })
     }
 
-    pub fn build_query(&mut self) -> QueryResult<String>
-    where
-        Aggregate<&'static str>: ToSql<T>,
-    {
+    /// Reconciles the clauses added so far before the query is built, so that a misconfigured
+    /// builder fails here instead of emitting invalid SQL. Repeated `GROUP BY` expressions are
+    /// dropped, and a `HAVING` clause without `GROUP BY` is rejected unless the query selects
+    /// aggregates, in which case it filters the single group formed by the whole table.
+    pub fn finalize(&mut self) -> QueryResult<()> {
+        let mut seen_group_by = HashSet::new();
+        self.group_by
+            .retain(|group_by| seen_group_by.insert(group_by.clone()));
+
+        if self.having.is_some() && !self.is_grouped() && !self.has_aggregates {
+            Err(QueryBuildingError::InvalidQuery(
+                "HAVING requires a GROUP BY clause or aggregate columns",
+            ))
+            .into_report()?;
+        }
+        Ok(())
+    }
+
+    pub fn build_query(&mut self) -> QueryResult<String> {
+        self.finalize()?;
+        self.build_query_with_separators(" ", " AND ")
+    }
+
+    /// Builds the query with every clause on its own line and filter conditions indented below
+    /// the `WHERE` keyword. Only meant for logging, the query itself is the same as the one
+    /// returned by [`Self::build_query`].
+    pub fn build_query_pretty(&self) -> QueryResult<String> {
+        self.build_query_with_separators("\n", "\n    AND ")
+    }
+
+    /// Builds the query in a stable, whitespace normalized form, meant to be compared against
+    /// golden queries to catch unintended changes to the generated SQL.
+    pub fn snapshot(&mut self) -> QueryResult<String> {
+        self.build_query().map(|query| normalize_query(&query))
+    }
+
+    fn build_query_with_separators(
+        &self,
+        clause_separator: &str,
+        condition_separator: &str,
+    ) -> QueryResult<String> {
         if self.columns.is_empty() {
             Err(QueryBuildingError::InvalidQuery(
                 "No select fields provided",
             ))
             .into_report()?;
         }
-        let mut query = String::from("SELECT ");
+        if self.distinct && (self.has_aggregates || self.is_grouped()) {
+            Err(QueryBuildingError::InvalidQuery(
+                "DISTINCT cannot be combined with aggregate columns or GROUP BY",
+            ))
+            .into_report()?;
+        }
+        if !self.distinct_on.is_empty() {
+            if self.distinct {
+                Err(QueryBuildingError::InvalidQuery(
+                    "DISTINCT cannot be combined with DISTINCT ON",
+                ))
+                .into_report()?;
+            }
+            if !self.is_ordered_by_distinct_on() {
+                Err(QueryBuildingError::InvalidQuery(
+                    "DISTINCT ON requires an ORDER BY clause starting with its columns",
+                ))
+                .into_report()?;
+            }
+        }
+        let mut query = String::new();
+
+        query.push_str("SELECT ");
 
         if self.distinct {
             query.push_str("DISTINCT ");
         }
 
+        if !self.distinct_on.is_empty() {
+            query.push_str(&format!("DISTINCT ON ({}) ", self.distinct_on.join(", ")));
+        }
+
         query.push_str(&self.get_select_clause());
 
-        query.push_str(" FROM ");
+        query.push_str(clause_separator);
+        query.push_str("FROM ");
 
-        query.push_str(
-            &self
-                .table
-                .to_sql()
-                .change_context(QueryBuildingError::SqlSerializeError)
-                .attach_printable("Error serializing table value")?,
-        );
+        let table = match &self.schema {
+            Some(schema) => self.table.to_sql_in_schema(schema),
+            None => self.table.to_sql(),
+        }
+        .change_context(QueryBuildingError::SqlSerializeError)
+        .attach_printable("Error serializing table value")?;
+        query.push_str(&table);
+
+        if let (Some(fraction), Some(style)) = (self.sample, T::SAMPLE_STYLE) {
+            query.push_str(&match style {
+                SampleStyle::Sample => format!(" SAMPLE {fraction}"),
+                SampleStyle::TableSample => format!(" TABLESAMPLE BERNOULLI (100 * {fraction})"),
+            });
+        }
+
+        for (join_type, table, on) in self.joins.iter() {
+            query.push_str(clause_separator);
+            match &self.schema {
+                Some(schema) => query.push_str(&format!("{join_type} {schema}.{table} ON {on}")),
+                None => query.push_str(&format!("{join_type} {table} ON {on}")),
+            }
+        }
 
         if !self.filters.is_empty() {
-            query.push_str(" WHERE ");
-            query.push_str(&self.get_filter_clause());
+            query.push_str(clause_separator);
+            query.push_str("WHERE ");
+            query.push_str(&self.get_filter_clause(condition_separator));
         }
 
-        if !self.group_by.is_empty() {
-            query.push_str(" GROUP BY ");
+        if self.is_grouped() {
+            query.push_str(clause_separator);
+            query.push_str("GROUP BY ");
             query.push_str(&self.get_group_by_clause());
         }
 
         if self.having.is_some() {
             if let Some(condition) = self.get_filter_type_clause() {
-                query.push_str(" HAVING ");
+                query.push_str(clause_separator);
+                query.push_str("HAVING ");
                 query.push_str(condition.as_str());
             }
         }
-        Ok(query)
-    }
 
-    pub async fn execute_query<R, P: AnalyticsDataSource>(
-        &mut self,
-        store: &P,
-    ) -> CustomResult<CustomResult<Vec<R>, QueryExecutionError>, QueryBuildingError>
-    where
-        P: LoadRow<R>,
-        Aggregate<&'static str>: ToSql<T>,
-    {
+        if !self.order_by.is_empty() {
+            query.push_str(clause_separator);
+            query.push_str("ORDER BY ");
+            query.push_str(&self.order_by.join(", "));
+        }
+
+        if let Some(limit) = self.limit {
+            query.push_str(clause_separator);
+            query.push_str(&format!("LIMIT {limit}"));
+        }
+
+        if let Some(aggregation) = &self.json_aggregation {
+            query = aggregation.wrap(&query, clause_separator);
+        }
+
+        if let (Some(hint), QueryHintStyle::LeadingComment) =
+            (&self.query_hint, T::QUERY_HINT_STYLE)
+        {
+            query.insert_str(0, &format!("/*+ {hint} */ "));
+        }
+
+        if let (Some(hint), QueryHintStyle::Settings) = (&self.query_hint, T::QUERY_HINT_STYLE) {
+            query.push_str(clause_separator);
+            query.push_str(&format!("SETTINGS {hint}"));
+        }
+        Ok(query)
+    }
+
+    fn build_query_for_execution(&mut self) -> QueryResult<String> {
         let query = self
             .build_query()
             .change_context(QueryBuildingError::SqlSerializeError)
             .attach_printable("Failed to execute query")?;
-        logger::debug!(?query);
+        if let Ok(pretty_query) = self.build_query_for_logging() {
+            logger::debug!("Executing analytics query:\n{pretty_query}");
+        }
+        Ok(query)
+    }
+
+    /// Builds the query to be run on `store`, in the schema of the data source
+    fn build_query_for_store<P: AnalyticsDataSource>(&mut self, store: &P) -> QueryResult<String> {
+        self.set_schema(store.schema());
+        self.build_query_for_execution()
+    }
+
+    /// Builds the query as it's logged, see [`Self::redact_logged_query`]
+    fn build_query_for_logging(&mut self) -> QueryResult<String> {
+        if !self.redact_logged_query {
+            return self.build_query_pretty();
+        }
+        let redacted_filters = self.filters.iter().map(FilterExpr::redacted).collect();
+        let redacted_having = self.having.as_ref().map(|having| {
+            having
+                .iter()
+                .map(|(l, op, _)| (l.clone(), *op, REDACTED_FILTER_VALUE.to_string()))
+                .collect::<Vec<_>>()
+        });
+        let filters = std::mem::replace(&mut self.filters, redacted_filters);
+        let having = std::mem::replace(&mut self.having, redacted_having);
+        let query = self.build_query_pretty();
+        self.filters = filters;
+        self.having = having;
+        query
+    }
+
+    /// Builds the query prefixed with the `EXPLAIN` keyword of the data source it will run on
+    pub fn build_explain_query<P: AnalyticsDataSource>(&mut self) -> QueryResult<String> {
+        let query = self.build_query_for_execution()?;
+        Ok(format!("{} {query}", P::EXPLAIN_PREFIX))
+    }
+
+    /// Returns the execution plan of the query, one line per plan node, to help with tuning
+    /// slow queries. On Postgres the plan comes from `EXPLAIN ANALYZE`, which actually runs the
+    /// query to report real timings.
+    pub async fn explain_analyze<P: AnalyticsDataSource>(
+        &mut self,
+        store: &P,
+    ) -> CustomResult<CustomResult<String, QueryExecutionError>, QueryBuildingError>
+    where
+        P: LoadRow<QueryPlanRow>,
+    {
+        self.set_schema(store.schema());
+        let query = self.build_explain_query::<P>()?;
+        Ok(store
+            .load_results::<QueryPlanRow>(query.as_str())
+            .await
+            .map(|plan| {
+                plan.into_iter()
+                    .map(|row| row.line)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }))
+    }
+
+    pub async fn execute_query<R, P: AnalyticsDataSource>(
+        &mut self,
+        store: &P,
+    ) -> CustomResult<CustomResult<Vec<R>, QueryExecutionError>, QueryBuildingError>
+    where
+        P: LoadRow<R>,
+    {
+        let query = self.build_query_for_store(store)?;
         Ok(store.load_results(query.as_str()).await)
     }
+
+    /// Same as [`Self::execute_query`], but stops waiting for the results as soon as `cancelled`
+    /// completes, e.g. `CancellationToken::cancelled` once the client went away. The pending
+    /// query future is dropped, which only stops the client side: the database isn't asked to
+    /// cancel the statement and may keep running it until it completes or times out.
+    pub async fn execute_query_cancellable<R, P: AnalyticsDataSource>(
+        &mut self,
+        store: &P,
+        cancelled: impl Future<Output = ()>,
+    ) -> CustomResult<CustomResult<Vec<R>, QueryExecutionError>, QueryBuildingError>
+    where
+        P: LoadRow<R>,
+    {
+        let query = self.build_query_for_store(store)?;
+        Ok(tokio::select! {
+            biased;
+            result = store.load_results(query.as_str()) => result,
+            () = cancelled => {
+                logger::debug!("Analytics query was cancelled");
+                Err(QueryExecutionError::Cancelled).into_report()
+            }
+        })
+    }
+
+    /// Same as [`Self::execute_query`], but also returns the exact SQL which was run on the data
+    /// source, so it can be logged or attached to traces for auditing
+    pub async fn execute_query_with_sql<R, P: AnalyticsDataSource>(
+        &mut self,
+        store: &P,
+    ) -> CustomResult<CustomResult<(Vec<R>, String), QueryExecutionError>, QueryBuildingError>
+    where
+        P: LoadRow<R>,
+    {
+        let query = self.build_query_for_store(store)?;
+        let result = store.load_results(query.as_str()).await;
+        Ok(result.map(|rows| (rows, query)))
+    }
+
+    /// Same as [`Self::execute_query`], but also returns how long the query took to run, so slow
+    /// queries can be surfaced. Building the query is not part of the measured duration.
+    pub async fn execute_query_timed<R, P: AnalyticsDataSource>(
+        &mut self,
+        store: &P,
+    ) -> CustomResult<CustomResult<(Vec<R>, Duration), QueryExecutionError>, QueryBuildingError>
+    where
+        P: LoadRow<R>,
+    {
+        let query = self.build_query_for_store(store)?;
+        let start = Instant::now();
+        let result = store.load_results(query.as_str()).await;
+        let elapsed = start.elapsed();
+        logger::debug!("Analytics query took {}ms", elapsed.as_millis());
+        Ok(result.map(|rows| (rows, elapsed)))
+    }
+
+    /// Same as [`Self::execute_query`], but runs the query again with exponential backoff when
+    /// it fails with a transient error. Other errors are returned right away.
+    pub async fn execute_query_with_retry<R, P: AnalyticsDataSource>(
+        &mut self,
+        store: &P,
+        retry_config: &QueryRetryConfig,
+    ) -> CustomResult<CustomResult<Vec<R>, QueryExecutionError>, QueryBuildingError>
+    where
+        P: LoadRow<R>,
+    {
+        let query = self.build_query_for_store(store)?;
+        let mut backoff = retry_config.initial_backoff;
+        let mut retries = 0;
+        loop {
+            match store.load_results(query.as_str()).await {
+                Err(error)
+                    if retries < retry_config.max_retries
+                        && error.current_context().is_transient() =>
+                {
+                    retries += 1;
+                    logger::warn!(
+                        ?error,
+                        "Retrying analytics query in {backoff:?}, attempt {retries} of {}",
+                        retry_config.max_retries
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                }
+                result => return Ok(result),
+            }
+        }
+    }
+}
+
+/// Line of the execution plan returned by an `EXPLAIN` query
+#[derive(Debug)]
+pub struct QueryPlanRow {
+    pub line: String,
+}
+
+/// Bounds on re-running analytics queries that failed with a transient error
+#[derive(Debug, Clone, Copy)]
+pub struct QueryRetryConfig {
+    /// Number of times the query is run again after the first failure
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every subsequent one
+    pub initial_backoff: Duration,
+}
+
+impl Default for QueryRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Assertions on built queries for the tests of the metrics
+#[cfg(test)]
+pub(crate) mod snapshot {
+    #![allow(clippy::unwrap_used)]
+    use super::{normalize_query, QueryBuilder, ToSql};
+    use crate::analytics::types::{AnalyticsCollection, AnalyticsDataSource};
+
+    /// Asserts that the snapshot of the builder matches the golden query, which may be wrapped
+    /// freely.
+    #[track_caller]
+    pub(crate) fn assert_query_snapshot<T>(builder: &mut QueryBuilder<T>, golden: &str)
+    where
+        T: AnalyticsDataSource,
+        AnalyticsCollection: ToSql<T>,
+    {
+        let snapshot = builder.snapshot().unwrap();
+        let golden = normalize_query(golden);
+        assert!(
+            snapshot == golden,
+            "query snapshot mismatch\n  actual: {snapshot}\nexpected: {golden}"
+        );
+    }
+
+    /// Asserts that the snapshot of the builder contains the fragment, which may be wrapped
+    /// freely.
+    #[track_caller]
+    pub(crate) fn assert_query_snapshot_contains<T>(builder: &mut QueryBuilder<T>, fragment: &str)
+    where
+        T: AnalyticsDataSource,
+        AnalyticsCollection: ToSql<T>,
+    {
+        let snapshot = builder.snapshot().unwrap();
+        let fragment = normalize_query(fragment);
+        assert!(
+            snapshot.contains(&fragment),
+            "query snapshot is missing a fragment\n   query: {snapshot}\nfragment: {fragment}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use std::{
+        str::FromStr,
+        sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    };
+
+    use error_stack::report;
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::analytics::sqlx::SqlxClient;
+
+    /// Data source failing the first `failures` queries with a transient or permanent error
+    struct FlakySource {
+        failures: u32,
+        transient: bool,
+        attempts: AtomicU32,
+    }
+
+    impl FlakySource {
+        fn new(failures: u32, transient: bool) -> Self {
+            Self {
+                failures,
+                transient,
+                attempts: AtomicU32::new(0),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AnalyticsDataSource for FlakySource {
+        type Row = u64;
+
+        async fn load_results<T>(&self, _query: &str) -> CustomResult<Vec<T>, QueryExecutionError>
+        where
+            Self: LoadRow<T>,
+        {
+            if self.attempts.fetch_add(1, Ordering::SeqCst) < self.failures {
+                Err(report!(if self.transient {
+                    QueryExecutionError::TransientDatabaseError
+                } else {
+                    QueryExecutionError::DatabaseError
+                }))
+            } else {
+                vec![1].into_iter().map(Self::load_row).collect()
+            }
+        }
+    }
+
+    impl LoadRow<u64> for FlakySource {
+        fn load_row(row: u64) -> CustomResult<u64, QueryExecutionError> {
+            Ok(row)
+        }
+    }
+
+    /// Data source whose queries never complete, recording whether a query was started and
+    /// whether the caller stopped waiting for it
+    #[derive(Default)]
+    struct HangingSource {
+        started: AtomicBool,
+        stopped: AtomicBool,
+    }
+
+    /// Sets the flag when dropped along with the query future holding it
+    struct SetOnDrop<'a>(&'a AtomicBool);
+
+    impl Drop for SetOnDrop<'_> {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl AnalyticsDataSource for HangingSource {
+        type Row = u64;
+
+        async fn load_results<T>(&self, _query: &str) -> CustomResult<Vec<T>, QueryExecutionError>
+        where
+            Self: LoadRow<T>,
+        {
+            self.started.store(true, Ordering::SeqCst);
+            let _stopped = SetOnDrop(&self.stopped);
+            std::future::pending().await
+        }
+    }
+
+    impl LoadRow<u64> for HangingSource {
+        fn load_row(row: u64) -> CustomResult<u64, QueryExecutionError> {
+            Ok(row)
+        }
+    }
+
+    /// Data source taking `SAMPLE` clauses, as ClickHouse does
+    struct SamplingSource;
+
+    #[async_trait::async_trait]
+    impl AnalyticsDataSource for SamplingSource {
+        type Row = u64;
+
+        const SAMPLE_STYLE: Option<SampleStyle> = Some(SampleStyle::Sample);
+
+        async fn load_results<T>(&self, _query: &str) -> CustomResult<Vec<T>, QueryExecutionError>
+        where
+            Self: LoadRow<T>,
+        {
+            Ok(Vec::new())
+        }
+    }
+
+    impl ToSql<SamplingSource> for AnalyticsCollection {
+        fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+            Ok("payment_attempt".to_string())
+        }
+    }
+
+    /// Data source folding rows into JSON objects from arrays of keys and values
+    struct JsonAggregationSource;
+
+    #[async_trait::async_trait]
+    impl AnalyticsDataSource for JsonAggregationSource {
+        type Row = u64;
+
+        const JSON_AGGREGATION_STYLE: Option<JsonAggregationStyle> =
+            Some(JsonAggregationStyle::MapFromArrays);
+
+        async fn load_results<T>(&self, _query: &str) -> CustomResult<Vec<T>, QueryExecutionError>
+        where
+            Self: LoadRow<T>,
+        {
+            Ok(Vec::new())
+        }
+    }
+
+    impl ToSql<JsonAggregationSource> for AnalyticsCollection {
+        fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+            Ok("payment_attempt".to_string())
+        }
+    }
+
+    /// Data source taking hints as a `SETTINGS` clause
+    struct SettingsSource;
+
+    #[async_trait::async_trait]
+    impl AnalyticsDataSource for SettingsSource {
+        type Row = u64;
+
+        const QUERY_HINT_STYLE: QueryHintStyle = QueryHintStyle::Settings;
+
+        async fn load_results<T>(&self, _query: &str) -> CustomResult<Vec<T>, QueryExecutionError>
+        where
+            Self: LoadRow<T>,
+        {
+            Ok(Vec::new())
+        }
+    }
+
+    impl ToSql<SettingsSource> for AnalyticsCollection {
+        fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+            Ok("payment_attempt".to_string())
+        }
+    }
+
+    /// Data source storing the creation time and amount of attempts under other names
+    struct RenamedColumnSource;
+
+    #[async_trait::async_trait]
+    impl AnalyticsDataSource for RenamedColumnSource {
+        type Row = u64;
+
+        const COLUMN_NAME_OVERRIDES: &'static [(Column, &'static str)] = &[
+            (Column::CreatedAt, "inserted_at"),
+            (Column::Amount, "amount_minor"),
+        ];
+
+        async fn load_results<T>(&self, _query: &str) -> CustomResult<Vec<T>, QueryExecutionError>
+        where
+            Self: LoadRow<T>,
+        {
+            Ok(Vec::new())
+        }
+    }
+
+    impl ToSql<RenamedColumnSource> for AnalyticsCollection {
+        fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+            Ok("payment_attempt".to_string())
+        }
+    }
+
+    /// Data source quoting identifiers with backticks, as ClickHouse does
+    struct BacktickSource;
+
+    #[async_trait::async_trait]
+    impl AnalyticsDataSource for BacktickSource {
+        type Row = u64;
+
+        const IDENTIFIER_QUOTE: char = '`';
+
+        async fn load_results<T>(&self, _query: &str) -> CustomResult<Vec<T>, QueryExecutionError>
+        where
+            Self: LoadRow<T>,
+        {
+            Ok(Vec::new())
+        }
+    }
+
+    impl ToSql<BacktickSource> for AnalyticsCollection {
+        fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+            Ok("payment_attempt".to_string())
+        }
+    }
+
+    /// Data source keeping its tables in a tenant schema, recording the queries it runs
+    #[derive(Default)]
+    struct TenantSource {
+        queries: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AnalyticsDataSource for TenantSource {
+        type Row = u64;
+
+        fn schema(&self) -> Option<&str> {
+            Some("tenant_1")
+        }
+
+        async fn load_results<T>(&self, query: &str) -> CustomResult<Vec<T>, QueryExecutionError>
+        where
+            Self: LoadRow<T>,
+        {
+            self.queries.lock().unwrap().push(query.to_owned());
+            Ok(Vec::new())
+        }
+    }
+
+    impl LoadRow<u64> for TenantSource {
+        fn load_row(row: u64) -> CustomResult<u64, QueryExecutionError> {
+            Ok(row)
+        }
+    }
+
+    impl ToSql<TenantSource> for AnalyticsCollection {
+        fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+            ToSql::<SqlxClient>::to_sql(self)
+        }
+    }
+
+    fn immediate_retry_config() -> QueryRetryConfig {
+        QueryRetryConfig {
+            max_retries: 2,
+            initial_backoff: Duration::ZERO,
+        }
+    }
+
+    fn payment_query_builder() -> QueryBuilder<SqlxClient> {
+        let mut builder = QueryBuilder::new(AnalyticsCollection::Payment);
+        builder.add_select_column("connector").unwrap();
+        builder
+    }
+
+    #[test]
+    fn test_explain_prefix_per_data_source() {
+        let mut builder = payment_query_builder();
+        assert_eq!(
+            builder.build_explain_query::<SqlxClient>().unwrap(),
+            "EXPLAIN ANALYZE SELECT connector FROM payment_attempt"
+        );
+        assert_eq!(
+            builder.build_explain_query::<FlakySource>().unwrap(),
+            "EXPLAIN SELECT connector FROM payment_attempt"
+        );
+    }
+
+    #[test]
+    fn test_base_filters() {
+        let mut builder = payment_query_builder();
+        let time_range = analytics_api::TimeRange {
+            start_time: datetime!(2023-11-01 00:00:00),
+            end_time: Some(datetime!(2023-11-02 00:00:00)),
+        };
+        BaseFilters {
+            merchant_id: "merchant_1",
+            time_range: &time_range,
+        }
+        .apply_to(&mut builder)
+        .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt WHERE merchant_id = 'merchant_1' \
+             AND created_at >= '2023-11-01 0:00:00.0' AND created_at <= '2023-11-02 0:00:00.0'"
+        );
+    }
+
+    #[test]
+    fn test_relative_time_range() {
+        let relative_range = analytics_api::RelativeTimeRange::Last(time::Duration::DAY);
+        let time_range = relative_range.resolve_at(datetime!(2023-11-02 12:00:00));
+        assert_eq!(
+            time_range,
+            analytics_api::TimeRange {
+                start_time: datetime!(2023-11-01 12:00:00),
+                end_time: Some(datetime!(2023-11-02 12:00:00)),
+            }
+        );
+
+        let mut builder = payment_query_builder();
+        time_range.set_filter_clause(&mut builder).unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt \
+             WHERE created_at >= '2023-11-01 12:00:00.0' AND created_at <= '2023-11-02 12:00:00.0'"
+        );
+
+        let mut builder = payment_query_builder();
+        relative_range.set_filter_clause(&mut builder).unwrap();
+        assert_eq!(
+            builder.build_query().unwrap().matches("created_at").count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_filter_expr() {
+        let leaf = |key: &str, value: &str| {
+            FilterExpr::Leaf(key.to_string(), FilterTypes::Equal, value.to_string())
+        };
+
+        let mut builder = payment_query_builder();
+        builder
+            .add_filter_clause("merchant_id", "merchant_1")
+            .unwrap();
+        builder.add_filter_expr(FilterExpr::Or(vec![
+            leaf("status", "charged"),
+            FilterExpr::And(vec![
+                leaf("status", "authorized"),
+                FilterExpr::Not(Box::new(FilterExpr::Or(vec![
+                    leaf("connector", "stripe"),
+                    leaf("connector", "adyen"),
+                ]))),
+            ]),
+        ]));
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt WHERE merchant_id = 'merchant_1' \
+             AND (status = 'charged' OR (status = 'authorized' \
+             AND NOT (connector = 'stripe' OR connector = 'adyen')))"
+        );
+
+        let mut builder = payment_query_builder();
+        builder.add_filter_expr(FilterExpr::Not(Box::new(leaf("connector", "stripe"))));
+        builder.add_filter_expr(FilterExpr::And(vec![leaf("status", "charged")]));
+        builder.add_filter_expr(FilterExpr::Or(Vec::new()));
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt WHERE NOT connector = 'stripe' \
+             AND status = 'charged' AND FALSE"
+        );
+    }
+
+    #[test]
+    fn test_percentage_expression() {
+        assert_eq!(
+            percentage_expression("count(*) FILTER (WHERE status = 'charged')", "count(*)"),
+            "count(*) FILTER (WHERE status = 'charged') * 100.0 / NULLIF(count(*), 0)"
+        );
+    }
+
+    #[test]
+    fn test_latest_payment_attempt_collection() {
+        let mut builder =
+            QueryBuilder::<SqlxClient>::new(AnalyticsCollection::LatestPaymentAttempt);
+        builder
+            .add_select_column(PaymentDimensions::PaymentStatus)
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        builder
+            .add_filter_clause(Column::MerchantId, "merchant_1")
+            .unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::PaymentStatus)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT status, count(*) as count FROM (SELECT * FROM payment_attempt AS attempt \
+             WHERE attempt.created_at = (SELECT max(later_attempt.created_at) \
+             FROM payment_attempt AS later_attempt \
+             WHERE later_attempt.payment_id = attempt.payment_id \
+             AND later_attempt.merchant_id = attempt.merchant_id)) AS payment_attempt \
+             WHERE merchant_id = 'merchant_1' GROUP BY status"
+        );
+    }
+
+    #[test]
+    fn test_tri_state_bool_filter() {
+        let query_with_states = |states: &[Option<bool>]| {
+            let mut builder = payment_query_builder();
+            builder
+                .add_tri_state_bool_filter_clause("is_test", states)
+                .unwrap();
+            builder.build_query().unwrap()
+        };
+
+        assert_eq!(
+            query_with_states(&[None, Some(true)]),
+            "SELECT connector FROM payment_attempt WHERE (is_test IS NULL OR is_test = true)"
+        );
+        assert_eq!(
+            query_with_states(&[Some(false), Some(false)]),
+            "SELECT connector FROM payment_attempt WHERE is_test = false"
+        );
+        assert_eq!(
+            query_with_states(&[None]),
+            "SELECT connector FROM payment_attempt WHERE is_test IS NULL"
+        );
+        assert_eq!(
+            query_with_states(&[Some(true), None, Some(false)]),
+            "SELECT connector FROM payment_attempt"
+        );
+        assert_eq!(
+            query_with_states(&[]),
+            "SELECT connector FROM payment_attempt WHERE FALSE"
+        );
+    }
+
+    #[test]
+    fn test_join_clause() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_join_clause(
+                JoinType::Left,
+                "payment_intent",
+                &[
+                    "payment_intent.payment_id = payment_attempt.payment_id",
+                    "payment_intent.merchant_id = payment_attempt.merchant_id",
+                ],
+            )
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt LEFT JOIN payment_intent \
+             ON payment_intent.payment_id = payment_attempt.payment_id \
+             AND payment_intent.merchant_id = payment_attempt.merchant_id"
+        );
+
+        let mut builder = payment_query_builder();
+        for join_type in [JoinType::Inner, JoinType::Left] {
+            assert!(matches!(
+                builder
+                    .add_join_clause(join_type, "payment_intent", &[] as &[&str])
+                    .unwrap_err()
+                    .current_context(),
+                QueryBuildingError::InvalidQuery(_)
+            ));
+        }
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt"
+        );
+    }
+
+    #[test]
+    fn test_redacted_query_logging() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_filter_clause("customer_email", "jane@example.com")
+            .unwrap();
+        builder
+            .add_filter_in_range_clause("currency", &[Currency::USD, Currency::EUR])
+            .unwrap();
+        assert_eq!(
+            builder.build_query_for_logging().unwrap(),
+            "SELECT connector\n\
+             FROM payment_attempt\n\
+             WHERE customer_email = 'jane@example.com'\n    AND currency IN ('USD', 'EUR')"
+        );
+
+        builder.redact_logged_query();
+        assert_eq!(
+            builder.build_query_for_logging().unwrap(),
+            "SELECT connector\n\
+             FROM payment_attempt\n\
+             WHERE customer_email = '***'\n    AND currency IN (***)"
+        );
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt WHERE customer_email = 'jane@example.com' \
+             AND currency IN ('USD', 'EUR')"
+        );
+    }
+
+    #[test]
+    fn test_query_hint() {
+        let mut builder = payment_query_builder();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt"
+        );
+        builder.set_query_hint("IndexScan(payment_attempt)");
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "/*+ IndexScan(payment_attempt) */ SELECT connector FROM payment_attempt"
+        );
+
+        let mut builder = QueryBuilder::<SettingsSource>::new(AnalyticsCollection::Payment);
+        builder.add_select_column("connector").unwrap();
+        builder.set_limit_by(10);
+        builder.set_query_hint("max_threads = 8");
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt LIMIT 10 SETTINGS max_threads = 8"
+        );
+    }
+
+    #[test]
+    fn test_sensitive_columns_are_rejected() {
+        let mut builder = payment_query_builder();
+        assert!(builder.add_select_column("card_number").is_err());
+        assert!(builder.add_select_column("CARD_CVC").is_err());
+        assert!(builder
+            .add_select_column("payment_method_data -> 'card' ->> 'card_number'")
+            .is_err());
+        assert!(builder
+            .add_select_column(Aggregate::Count {
+                field: Some("payment_token"),
+                alias: Some("count"),
+            })
+            .is_err());
+        assert!(builder.add_select_column("card_network").is_ok());
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, card_network FROM payment_attempt"
+        );
+    }
+
+    #[test]
+    fn test_json_aggregation() {
+        let mut builder = QueryBuilder::<JsonAggregationSource>::new(AnalyticsCollection::Payment);
+        builder.add_select_column("created_at").unwrap();
+        builder.add_select_column("connector").unwrap();
+        builder.add_select_column("count(*) as count").unwrap();
+        builder.add_group_by_clause("created_at").unwrap();
+        builder.add_group_by_clause("connector").unwrap();
+        builder
+            .set_json_aggregation(&["created_at"], "connector", "count", "aggregates")
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT created_at, toJSONString(mapFromArrays(groupArray(connector), \
+             groupArray(count))) as aggregates \
+             FROM (SELECT created_at, connector, count(*) as count FROM payment_attempt \
+             GROUP BY created_at, connector) GROUP BY created_at"
+        );
+
+        assert!(builder
+            .set_json_aggregation(&[], "connector", "count", "aggregates")
+            .is_err());
+        assert!(
+            QueryBuilder::<SamplingSource>::new(AnalyticsCollection::Payment)
+                .set_json_aggregation(&["created_at"], "connector", "count", "aggregates")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_json_object_aggregation() {
+        let mut builder = payment_query_builder();
+        builder.add_select_column("created_at").unwrap();
+        builder.add_select_column("count(*) as count").unwrap();
+        builder.add_group_by_clause("created_at").unwrap();
+        builder.add_group_by_clause("connector").unwrap();
+        builder
+            .set_json_aggregation(&["created_at"], "connector", "count", "aggregates")
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT created_at, json_object_agg(connector, count) as aggregates \
+             FROM (SELECT connector, created_at, count(*) as count FROM payment_attempt \
+             GROUP BY created_at, connector) AS grouped GROUP BY created_at"
+        );
+    }
+
+    #[test]
+    fn test_sample_clause() {
+        let mut builder = QueryBuilder::<SamplingSource>::new(AnalyticsCollection::Payment);
+        builder.add_select_column("connector").unwrap();
+        builder
+            .add_filter_clause("currency", Currency::USD)
+            .unwrap();
+        builder.set_sample(0.1).unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt SAMPLE 0.1 WHERE currency = 'USD'"
+        );
+        assert_eq!(builder.get_sample_scale_factor(), 10.0);
+
+        for fraction in [0.0, -0.5, 1.5, f64::NAN] {
+            assert!(matches!(
+                builder.set_sample(fraction).unwrap_err().current_context(),
+                QueryBuildingError::InvalidQuery(_)
+            ));
+        }
+
+        let mut builder = payment_query_builder();
+        builder.set_sample(0.1).unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt TABLESAMPLE BERNOULLI (100 * 0.1)"
+        );
+        assert_eq!(builder.get_sample_scale_factor(), 10.0);
+    }
+
+    #[test]
+    fn test_inverted_time_range_is_rejected() {
+        let mut builder = payment_query_builder();
+        let time_range = analytics_api::TimeRange {
+            start_time: datetime!(2023-11-02 00:00:00),
+            end_time: Some(datetime!(2023-11-01 00:00:00)),
+        };
+        assert!(matches!(
+            time_range
+                .set_filter_clause(&mut builder)
+                .unwrap_err()
+                .current_context(),
+            QueryBuildingError::InvalidQuery(_)
+        ));
+    }
+
+    #[test]
+    fn test_exclude_flag_filter() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_filter_clause("currency", Currency::USD)
+            .unwrap();
+        builder
+            .add_exclude_flag_filter_clause("(metadata ->> 'is_test')::boolean")
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt WHERE currency = 'USD' \
+             AND (metadata ->> 'is_test')::boolean IS NOT TRUE"
+        );
+    }
+
+    #[test]
+    fn test_decimal_filter_is_unquoted() {
+        let mut builder = payment_query_builder();
+        let threshold = bigdecimal::BigDecimal::from_str("100.50").unwrap();
+        builder
+            .add_custom_filter_clause(Column::Amount, &threshold, FilterTypes::Gte)
+            .unwrap();
+        builder
+            .add_custom_filter_clause(
+                Column::Amount,
+                bigdecimal::BigDecimal::from_str("1E+3").unwrap(),
+                FilterTypes::Lte,
+            )
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt WHERE amount >= 100.50 AND amount <= 1000"
+        );
+    }
+
+    #[test]
+    fn test_string_range_filter_is_quoted() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_custom_filter_clause("connector", "stripe", FilterTypes::Gte)
+            .unwrap();
+        builder
+            .add_custom_filter_clause("connector", "x' OR '1' = '1", FilterTypes::Lte)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt WHERE connector >= 'stripe' \
+             AND connector <= 'x'' OR ''1'' = ''1'"
+        );
+    }
+
+    #[test]
+    fn test_equal_filter_is_escaped() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_filter_clause("connector", "x' OR '1' = '1")
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt WHERE connector = 'x'' OR ''1'' = ''1'"
+        );
+    }
+
+    #[test]
+    fn test_column_to_sql() {
+        let columns = [
+            (Column::PaymentId, "payment_id"),
+            (Column::MerchantId, "merchant_id"),
+            (Column::Amount, "amount"),
+            (Column::CaptureMethod, "capture_method"),
+            (Column::CreatedAt, "created_at"),
+            (Column::ModifiedAt, "modified_at"),
+            (Column::AttemptNumber, PAYMENT_ATTEMPT_NUMBER_EXPRESSION),
+            (Column::CaptureGap, PAYMENT_CAPTURE_GAP_EXPRESSION),
+            (Column::OffSession, PAYMENT_OFF_SESSION_EXPRESSION),
+        ];
+        for (column, expected) in columns {
+            assert_eq!(
+                <Column as ToSql<SqlxClient>>::to_sql(&column).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_column_name_overrides() {
+        let mut builder = QueryBuilder::<RenamedColumnSource>::new(AnalyticsCollection::Payment);
+        builder.add_select_column(Column::Amount).unwrap();
+        builder.add_select_column(Column::ModifiedAt).unwrap();
+        builder
+            .add_filter_clause(Column::MerchantId, "merchant_1")
+            .unwrap();
+        builder
+            .add_order_by_clause(Column::CreatedAt, Order::Descending)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT amount_minor, modified_at FROM payment_attempt \
+             WHERE merchant_id = 'merchant_1' ORDER BY inserted_at DESC"
+        );
+    }
+
+    #[test]
+    fn test_count_aggregate_to_sql() {
+        let count_all = Aggregate::Count {
+            field: None::<Column>,
+            alias: Some("count"),
+        };
+        assert_eq!(
+            <Aggregate<Column> as ToSql<SqlxClient>>::to_sql(&count_all).unwrap(),
+            "count(*) as count"
+        );
+
+        let count_field = Aggregate::Count {
+            field: Some(Column::PaymentId),
+            alias: None,
+        };
+        assert_eq!(
+            <Aggregate<Column> as ToSql<SqlxClient>>::to_sql(&count_field).unwrap(),
+            "count(payment_id)"
+        );
+    }
+
+    #[test]
+    fn test_build_query_pretty() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        builder
+            .add_filter_clause(Column::MerchantId, "merchant_1")
+            .unwrap();
+        builder
+            .add_filter_clause("currency", Currency::USD)
+            .unwrap();
+        builder.add_group_by_clause("connector").unwrap();
+
+        assert_eq!(
+            builder.build_query_pretty().unwrap(),
+            "SELECT connector, count(*) as count\n\
+             FROM payment_attempt\n\
+             WHERE merchant_id = 'merchant_1'\n    AND currency = 'USD'\n\
+             GROUP BY connector"
+        );
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, count(*) as count FROM payment_attempt \
+             WHERE merchant_id = 'merchant_1' AND currency = 'USD' GROUP BY connector"
+        );
+    }
+
+    #[test]
+    fn test_distinct_on() {
+        let latest_attempt_builder = || {
+            let mut builder = QueryBuilder::<SqlxClient>::new(AnalyticsCollection::Payment);
+            builder.add_select_column("payment_id").unwrap();
+            builder.add_select_column("status").unwrap();
+            builder
+                .set_distinct_on(&[Column::MerchantId, Column::PaymentId])
+                .unwrap();
+            builder
+        };
+
+        let mut builder = latest_attempt_builder();
+        builder
+            .add_order_by_clause(Column::PaymentId, Order::Ascending)
+            .unwrap();
+        builder
+            .add_order_by_clause(Column::MerchantId, Order::Ascending)
+            .unwrap();
+        builder
+            .add_order_by_clause(Column::CreatedAt, Order::Descending)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT DISTINCT ON (merchant_id, payment_id) payment_id, status \
+             FROM payment_attempt ORDER BY payment_id ASC, merchant_id ASC, created_at DESC"
+        );
+
+        let mut builder = latest_attempt_builder();
+        assert!(matches!(
+            builder.build_query().unwrap_err().current_context(),
+            QueryBuildingError::InvalidQuery(_)
+        ));
+
+        let mut builder = latest_attempt_builder();
+        builder
+            .add_order_by_clause(Column::CreatedAt, Order::Descending)
+            .unwrap();
+        builder
+            .add_order_by_clause(Column::PaymentId, Order::Ascending)
+            .unwrap();
+        assert!(matches!(
+            builder.build_query().unwrap_err().current_context(),
+            QueryBuildingError::InvalidQuery(_)
+        ));
+
+        let mut builder = QueryBuilder::<SqlxClient>::new(AnalyticsCollection::Payment);
+        assert!(matches!(
+            builder
+                .set_distinct_on(&[] as &[Column])
+                .unwrap_err()
+                .current_context(),
+            QueryBuildingError::InvalidQuery(_)
+        ));
+
+        let mut builder = QueryBuilder::<SamplingSource>::new(AnalyticsCollection::Payment);
+        assert!(matches!(
+            builder
+                .set_distinct_on(&[Column::PaymentId])
+                .unwrap_err()
+                .current_context(),
+            QueryBuildingError::InvalidQuery(_)
+        ));
+    }
+
+    #[test]
+    fn test_distinct_with_aggregates_is_rejected() {
+        let mut builder = payment_query_builder();
+        builder.set_distinct();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        assert!(matches!(
+            builder.build_query().unwrap_err().current_context(),
+            QueryBuildingError::InvalidQuery(_)
+        ));
+
+        let mut builder = payment_query_builder();
+        builder.set_distinct();
+        builder.add_group_by_clause("connector").unwrap();
+        assert!(matches!(
+            builder.build_query().unwrap_err().current_context(),
+            QueryBuildingError::InvalidQuery(_)
+        ));
+
+        let mut builder = payment_query_builder();
+        builder.set_distinct();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT DISTINCT connector FROM payment_attempt"
+        );
+    }
+
+    #[test]
+    fn test_having_on_alias() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        builder.add_group_by_clause("connector").unwrap();
+        builder
+            .add_having_on_alias("count", FilterTypes::Gt, &10_u64)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, count(*) as count FROM payment_attempt \
+             GROUP BY connector HAVING count(*) > 10"
+        );
+
+        let mut builder = payment_query_builder();
+        builder.add_group_by_clause("connector").unwrap();
+        builder
+            .add_having_clause(
+                Aggregate::Count {
+                    field: None::<Column>,
+                    alias: None,
+                },
+                FilterTypes::Lte,
+                &10_u64,
+            )
+            .unwrap();
+        builder
+            .add_having_clause(
+                Aggregate::Sum {
+                    field: Column::Amount,
+                    alias: None,
+                },
+                FilterTypes::NumericLte,
+                bigdecimal::BigDecimal::from(500),
+            )
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt \
+             GROUP BY connector HAVING count(*) <= 10 AND sum(amount) <= 500"
+        );
+
+        let mut builder = payment_query_builder();
+        assert!(matches!(
+            builder
+                .add_having_on_alias("success_rate", FilterTypes::Gt, &10_u64)
+                .unwrap_err()
+                .current_context(),
+            QueryBuildingError::InvalidQuery(_)
+        ));
+    }
+
+    #[test]
+    fn test_finalize_reconciles_clauses() {
+        let mut builder = payment_query_builder();
+        builder.add_group_by_clause("connector").unwrap();
+        builder.add_group_by_clause("connector").unwrap();
+        builder.finalize().unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt GROUP BY connector"
+        );
+
+        let mut builder = payment_query_builder();
+        builder
+            .add_having_clause(
+                Aggregate::Count {
+                    field: None::<Column>,
+                    alias: None,
+                },
+                FilterTypes::Gt,
+                &10_u64,
+            )
+            .unwrap();
+        assert!(matches!(
+            builder.finalize().unwrap_err().current_context(),
+            QueryBuildingError::InvalidQuery(_)
+        ));
+
+        let mut builder = QueryBuilder::<SqlxClient>::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        builder
+            .add_having_on_alias("count", FilterTypes::Gt, &10_u64)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT count(*) as count FROM payment_attempt HAVING count(*) > 10"
+        );
+    }
+
+    #[test]
+    fn test_order_by_and_limit() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        builder.add_group_by_clause("connector").unwrap();
+        builder
+            .add_order_by_clause("count", Order::Descending)
+            .unwrap();
+        builder
+            .add_order_by_clause("connector", Order::Ascending)
+            .unwrap();
+        builder.set_limit_by(5);
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, count(*) as count FROM payment_attempt \
+             GROUP BY connector ORDER BY count DESC, connector ASC LIMIT 5"
+        );
+    }
+
+    #[test]
+    fn test_order_by_with_nulls() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_order_by_clause_with_nulls("connector", Order::Ascending, NullsOrder::Last)
+            .unwrap();
+        builder
+            .add_order_by_clause_with_nulls("payment_method", Order::Descending, NullsOrder::First)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt \
+             ORDER BY connector ASC NULLS LAST, payment_method DESC NULLS FIRST"
+        );
+
+        let mut builder = QueryBuilder::<SamplingSource>::new(AnalyticsCollection::Payment);
+        builder.add_select_column("connector").unwrap();
+        builder
+            .add_order_by_clause_with_nulls("connector", Order::Ascending, NullsOrder::Last)
+            .unwrap();
+        builder
+            .add_order_by_clause_with_nulls("payment_method", Order::Descending, NullsOrder::First)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt ORDER BY connector IS NULL ASC, connector ASC, \
+             payment_method IS NULL DESC, payment_method DESC"
+        );
+    }
+
+    #[test]
+    fn test_distinct_on_with_nulls_order() {
+        let mut builder = QueryBuilder::<SqlxClient>::new(AnalyticsCollection::Payment);
+        builder.add_select_column("payment_id").unwrap();
+        builder.set_distinct_on(&[Column::PaymentId]).unwrap();
+        builder
+            .add_order_by_clause_with_nulls(Column::PaymentId, Order::Ascending, NullsOrder::Last)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT DISTINCT ON (payment_id) payment_id FROM payment_attempt \
+             ORDER BY payment_id ASC NULLS LAST"
+        );
+    }
+
+    #[test]
+    fn test_order_by_alias() {
+        let success_rate = "count(*) FILTER (WHERE status = 'charged') * 100.0 / count(*)";
+
+        let mut builder = payment_query_builder();
+        builder
+            .add_select_column(format!("{success_rate} as success_rate"))
+            .unwrap();
+        builder.add_group_by_clause("connector").unwrap();
+        builder
+            .add_order_by_alias("success_rate", Order::Descending)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT connector, {success_rate} as success_rate FROM payment_attempt \
+                 GROUP BY connector ORDER BY success_rate DESC"
+            )
+        );
+
+        let mut builder = QueryBuilder::<SamplingSource>::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(format!("{success_rate} as success_rate"))
+            .unwrap();
+        builder
+            .add_order_by_alias("success_rate", Order::Descending)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT {success_rate} as success_rate FROM payment_attempt \
+                 ORDER BY {success_rate} DESC"
+            )
+        );
+
+        let mut builder = payment_query_builder();
+        assert!(matches!(
+            builder
+                .add_order_by_alias("success_rate", Order::Descending)
+                .unwrap_err()
+                .current_context(),
+            QueryBuildingError::InvalidQuery(_)
+        ));
+    }
+
+    #[test]
+    fn test_date_group_by() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_select_column("CAST(created_at AS DATE) as day")
+            .unwrap();
+        builder.add_date_group_by(Column::CreatedAt).unwrap();
+        builder.add_group_by_clause("connector").unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, CAST(created_at AS DATE) as day FROM payment_attempt \
+             GROUP BY CAST(created_at AS DATE), connector"
+        );
+    }
+
+    #[test]
+    fn test_schema_prefix() {
+        let mut builder = payment_query_builder();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt"
+        );
+        builder.set_schema(Some("tenant_1"));
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM tenant_1.payment_attempt"
+        );
+
+        let mut builder: QueryBuilder<SqlxClient> =
+            QueryBuilder::new(AnalyticsCollection::PaymentMethodOffer);
+        builder.add_select_column("payment_method_type").unwrap();
+        builder.set_schema(Some("tenant_1"));
+        let query = builder.build_query().unwrap();
+        assert!(query.contains("FROM tenant_1.payment_intent UNION ALL"));
+        assert!(query.contains("FROM tenant_1.payment_attempt) AS payment_method_offer"));
+        assert!(!query.contains("FROM payment_"));
+
+        let mut builder = payment_query_builder();
+        builder
+            .add_join_clause(
+                JoinType::Left,
+                "payment_intent",
+                &["payment_intent.payment_id = payment_attempt.payment_id"],
+            )
+            .unwrap();
+        builder
+            .add_filter_clause("error_message", "declined FROM issuer")
+            .unwrap();
+        builder.set_schema(Some("tenant_1"));
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM tenant_1.payment_attempt LEFT JOIN tenant_1.payment_intent \
+             ON payment_intent.payment_id = payment_attempt.payment_id \
+             WHERE error_message = 'declined FROM issuer'"
+        );
+
+        let mut builder: QueryBuilder<SqlxClient> =
+            QueryBuilder::new(AnalyticsCollection::PaymentWithCustomerSpend);
+        builder.add_select_column("connector").unwrap();
+        builder.set_schema(Some("tenant_1"));
+        let query = builder.build_query().unwrap();
+        assert!(query.contains("FROM tenant_1.payment_attempt LEFT JOIN tenant_1.payment_intent"));
+        assert!(query.contains("FROM tenant_1.payment_attempt JOIN tenant_1.payment_intent"));
+        assert!(!query.contains("FROM payment_"));
+    }
+
+    #[test]
+    fn test_quoted_identifier() {
+        let mut builder = QueryBuilder::<SqlxClient>::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(QuotedIdentifier("order"))
+            .unwrap();
+        builder
+            .add_filter_clause(QuotedIdentifier("user"), "user_1")
+            .unwrap();
+        builder
+            .add_group_by_clause(QuotedIdentifier("order"))
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT \"order\" FROM payment_attempt WHERE \"user\" = 'user_1' GROUP BY \"order\""
+        );
+        assert_eq!(builder.get_select_column_names(), vec!["order"]);
+
+        let mut builder = QueryBuilder::<BacktickSource>::new(AnalyticsCollection::Payment);
+        builder
+            .add_select_column(QuotedIdentifier("order"))
+            .unwrap();
+        builder
+            .add_filter_clause(QuotedIdentifier("user"), "user_1")
+            .unwrap();
+        builder
+            .add_group_by_clause(QuotedIdentifier("order"))
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT `order` FROM payment_attempt WHERE `user` = 'user_1' GROUP BY `order`"
+        );
+
+        assert_eq!(
+            ToSql::<BacktickSource>::to_sql(&QuotedIdentifier("a`b")).unwrap(),
+            "`a``b`"
+        );
+    }
+
+    #[test]
+    fn test_quote_identifiers() {
+        let mut builder = payment_query_builder();
+        builder.add_select_column("order").unwrap();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None,
+                alias: Some("count"),
+            })
+            .unwrap();
+        builder.add_filter_clause("user", "user_1").unwrap();
+        builder.add_filter_clause("lower(currency)", "usd").unwrap();
+        builder.add_group_by_clause("connector").unwrap();
+        builder.add_group_by_clause("order").unwrap();
+        builder.quote_identifiers();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT \"connector\", \"order\", count(*) as count FROM payment_attempt \
+             WHERE \"user\" = 'user_1' AND lower(currency) = 'usd' \
+             GROUP BY \"connector\", \"order\""
+        );
+        assert_eq!(
+            builder.get_select_column_names(),
+            vec!["connector", "order", "count"]
+        );
+
+        let mut builder = QueryBuilder::<BacktickSource>::new(AnalyticsCollection::Payment);
+        builder.add_select_column("order").unwrap();
+        builder.add_filter_clause("user", "user_1").unwrap();
+        builder.add_group_by_clause("order").unwrap();
+        builder.quote_identifiers();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT `order` FROM payment_attempt WHERE `user` = 'user_1' GROUP BY `order`"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_schema_prefix_of_data_source() {
+        let source = TenantSource::default();
+        let mut builder = QueryBuilder::<TenantSource>::new(AnalyticsCollection::Payment);
+        builder.add_select_column("connector").unwrap();
+        builder
+            .execute_query::<u64, _>(&source)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            *source.queries.lock().unwrap(),
+            vec!["SELECT connector FROM tenant_1.payment_attempt".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_window_to_sql() {
+        let window = Window {
+            function: "lag(count(*))",
+            partition_by: vec![PaymentDimensions::Connector, PaymentDimensions::Currency],
+            order_by: Some("min(created_at)"),
+            alias: Some("previous_count"),
+        };
+        assert_eq!(
+            <Window<_, _> as ToSql<SqlxClient>>::to_sql(&window).unwrap(),
+            "lag(count(*)) OVER (PARTITION BY connector, currency ORDER BY min(created_at)) \
+             as previous_count"
+        );
+
+        let window = Window {
+            function: "lag(count(*))",
+            partition_by: Vec::<PaymentDimensions>::new(),
+            order_by: Some("min(created_at)"),
+            alias: None,
+        };
+        assert_eq!(
+            <Window<_, _> as ToSql<SqlxClient>>::to_sql(&window).unwrap(),
+            "lag(count(*)) OVER (ORDER BY min(created_at))"
+        );
+
+        let window = Window {
+            function: "sum(count(*))",
+            partition_by: vec![RefundDimensions::Connector],
+            order_by: None,
+            alias: Some("connector_total"),
+        };
+        assert_eq!(
+            <Window<_, _> as ToSql<SqlxClient>>::to_sql(&window).unwrap(),
+            "sum(count(*)) OVER (PARTITION BY connector) as connector_total"
+        );
+    }
+
+    #[test]
+    fn test_group_by_cube() {
+        let mut builder = payment_query_builder();
+        builder.add_select_column("currency").unwrap();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        builder.set_cube(&["connector", "currency"]).unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, currency, count(*) as count \
+             FROM payment_attempt GROUP BY CUBE(connector, currency)"
+        );
+
+        builder.add_group_by_clause("profile_id").unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, currency, count(*) as count \
+             FROM payment_attempt GROUP BY profile_id, CUBE(connector, currency)"
+        );
+
+        assert!(builder.set_cube(&[] as &[&str]).is_err());
+        let mut builder = QueryBuilder::<SamplingSource>::new(AnalyticsCollection::Payment);
+        assert!(builder.set_cube(&["connector"]).is_err());
+    }
+
+    #[test]
+    fn test_group_by_ordinal() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_select_column(PaymentDimensions::ProfileId)
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None::<Column>,
+                alias: Some("count"),
+            })
+            .unwrap();
+        builder.add_group_by_clause("connector").unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::ProfileId)
+            .unwrap();
+        builder.add_group_by_clause("currency").unwrap();
+        builder.set_group_by_ordinal();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT connector, {PAYMENT_PROFILE_ID_EXPRESSION}, count(*) as count \
+                 FROM payment_attempt GROUP BY 1, 2, currency"
+            )
+        );
+    }
+
+    #[test]
+    fn test_custom_filter_clause_cast() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_custom_filter_clause_cast(
+                "error_code",
+                &500_u64,
+                CastType::Integer,
+                FilterTypes::Gt,
+            )
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt WHERE CAST(error_code AS INTEGER) > 500"
+        );
+    }
+
+    #[test]
+    fn test_connector_filter() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_connector_filter(&[Connector::Stripe, Connector::Adyen, Connector::Checkout])
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt \
+             WHERE connector IN ('stripe', 'adyen', 'checkout')"
+        );
+    }
+
+    #[test]
+    fn test_column_mapping() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Payment);
+        let mapping = ColumnMapping::default()
+            .map(PaymentDimensions::Connector, "connector")
+            .map(
+                Aggregate::Sum {
+                    field: Column::Amount,
+                    alias: None,
+                },
+                "total",
+            );
+        assert_eq!(mapping.field_names(), vec!["connector", "total"]);
+
+        builder.add_column_mapping(mapping).unwrap();
+        builder
+            .add_group_by_clause(PaymentDimensions::Connector)
+            .unwrap();
+
+        assert_eq!(
+            builder.get_select_column_names(),
+            vec!["connector", "total"]
+        );
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector as connector, sum(amount) as total \
+             FROM payment_attempt GROUP BY connector"
+        );
+    }
+
+    #[test]
+    fn test_bucket_column_is_shared_by_granularity_clauses() {
+        for (bucket_column, expected) in [
+            (None, "modified_at"),
+            (Some(Column::CreatedAt), "created_at"),
+        ] {
+            let mut builder = payment_query_builder();
+            if let Some(column) = bucket_column {
+                builder.set_bucket_column(column);
+            }
+            Granularity::OneHour
+                .set_group_by_clause(&mut builder)
+                .unwrap();
+            builder
+                .add_granularity_in_mins(&Granularity::OneHour)
+                .unwrap();
+            assert_eq!(
+                builder.build_query().unwrap(),
+                format!(
+                    "SELECT connector, toStartOfInterval({expected}, INTERVAL 60 MINUTE) \
+                     as time_bucket FROM payment_attempt GROUP BY DATE_TRUNC('hour', {expected})"
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_non_utc_storage_timezone() {
+        let storage_timezone = time::UtcOffset::from_hms(5, 30, 0).unwrap();
+        let mut builder = payment_query_builder();
+        builder.set_storage_timezone(storage_timezone);
+        analytics_api::TimeRange {
+            start_time: datetime!(2023-11-01 00:00:00),
+            end_time: None,
+        }
+        .set_filter_clause(&mut builder)
+        .unwrap();
+        Granularity::FifteenMin
+            .set_group_by_clause(&mut builder)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt \
+             WHERE created_at >= '2023-11-01 5:30:00.0' \
+             GROUP BY DATE_TRUNC('hour', (modified_at - INTERVAL '19800 seconds')), \
+             FLOOR(DATE_PART('minute', (modified_at - INTERVAL '19800 seconds'))/15)"
+        );
+
+        // Labels are clipped on the UTC value of the stored timestamp, like the buckets above
+        let start_bucket = storage_time_to_utc(datetime!(2023-11-01 05:50:00), storage_timezone)
+            .and_then(|start_bucket| Granularity::FifteenMin.clip_to_start(start_bucket))
+            .unwrap();
+        assert_eq!(start_bucket, datetime!(2023-11-01 00:15:00));
+    }
+
+    #[test]
+    fn test_thirty_min_buckets_stay_within_the_hour() {
+        let cases = [
+            (
+                datetime!(2023-11-01 10:00:00),
+                datetime!(2023-11-01 10:00:00),
+                datetime!(2023-11-01 10:29:00),
+            ),
+            (
+                datetime!(2023-11-01 10:29:59),
+                datetime!(2023-11-01 10:00:00),
+                datetime!(2023-11-01 10:29:00),
+            ),
+            (
+                datetime!(2023-11-01 10:30:00),
+                datetime!(2023-11-01 10:30:00),
+                datetime!(2023-11-01 10:59:00),
+            ),
+            (
+                datetime!(2023-11-01 23:59:59),
+                datetime!(2023-11-01 23:30:00),
+                datetime!(2023-11-01 23:59:00),
+            ),
+        ];
+        for (value, start, end) in cases {
+            assert_eq!(Granularity::ThirtyMin.clip_to_start(value).unwrap(), start);
+            assert_eq!(Granularity::ThirtyMin.clip_to_end(value).unwrap(), end);
+        }
+    }
+
+    #[test]
+    fn test_granularity_series_one_hour() {
+        let time_range = analytics_api::TimeRange {
+            start_time: datetime!(2023-11-01 10:30:00),
+            end_time: Some(datetime!(2023-11-01 13:10:00)),
+        };
+        let series = GranularitySeries::new(Granularity::OneHour, &time_range)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            series,
+            vec![
+                (
+                    datetime!(2023-11-01 10:00:00),
+                    datetime!(2023-11-01 10:59:00)
+                ),
+                (
+                    datetime!(2023-11-01 11:00:00),
+                    datetime!(2023-11-01 11:59:00)
+                ),
+                (
+                    datetime!(2023-11-01 12:00:00),
+                    datetime!(2023-11-01 12:59:00)
+                ),
+                (
+                    datetime!(2023-11-01 13:00:00),
+                    datetime!(2023-11-01 13:59:00)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_granularity_series_one_day() {
+        let time_range = analytics_api::TimeRange {
+            start_time: datetime!(2023-10-31 18:00:00),
+            end_time: Some(datetime!(2023-11-02 00:00:00)),
+        };
+        let series = GranularitySeries::new(Granularity::OneDay, &time_range)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            series,
+            vec![
+                (
+                    datetime!(2023-10-31 00:00:00),
+                    datetime!(2023-10-31 23:00:00)
+                ),
+                (
+                    datetime!(2023-11-01 00:00:00),
+                    datetime!(2023-11-01 23:00:00)
+                ),
+                (
+                    datetime!(2023-11-02 00:00:00),
+                    datetime!(2023-11-02 23:00:00)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_month_of() {
+        assert_eq!(
+            month_of(datetime!(2023-11-14 10:30:00)).unwrap(),
+            (
+                datetime!(2023-11-01 00:00:00),
+                datetime!(2023-11-30 23:59:59)
+            )
+        );
+        assert_eq!(
+            month_of(datetime!(2023-12-31 23:59:59)).unwrap(),
+            (
+                datetime!(2023-12-01 00:00:00),
+                datetime!(2023-12-31 23:59:59)
+            )
+        );
+    }
+
+    #[test]
+    fn test_default_granularity() {
+        let cases = [
+            (datetime!(2023-11-01 10:00:00), Granularity::OneMin),
+            (datetime!(2023-11-01 12:00:00), Granularity::FiveMin),
+            (datetime!(2023-11-01 20:00:00), Granularity::FifteenMin),
+            (datetime!(2023-11-02 08:00:00), Granularity::ThirtyMin),
+            (datetime!(2023-11-03 09:00:00), Granularity::OneHour),
+            (datetime!(2023-11-08 00:00:00), Granularity::OneHour),
+            (datetime!(2023-12-15 00:00:00), Granularity::OneDay),
+        ];
+        for (end_time, granularity) in cases {
+            let time_range = analytics_api::TimeRange {
+                start_time: datetime!(2023-11-01 08:00:00),
+                end_time: Some(end_time),
+            };
+            assert_eq!(
+                bucket_length(default_granularity(&time_range)),
+                bucket_length(granularity),
+                "{end_time}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_granularity() {
+        let time_range = analytics_api::TimeRange {
+            start_time: datetime!(2023-10-01 00:00:00),
+            end_time: Some(datetime!(2023-12-01 00:00:00)),
+        };
+        let resolve = |granularity, exact_granularity| {
+            resolve_granularity(
+                &Some(analytics_api::TimeSeries {
+                    granularity,
+                    exact_granularity,
+                }),
+                &time_range,
+            )
+            .map(bucket_length)
+        };
+        assert_eq!(
+            resolve(Granularity::OneMin, false),
+            Some(time::Duration::DAY)
+        );
+        assert_eq!(
+            resolve(Granularity::OneMin, true),
+            Some(time::Duration::MINUTE)
+        );
+        assert_eq!(
+            resolve(Granularity::OneDay, false),
+            Some(time::Duration::DAY)
+        );
+        assert_eq!(resolve_granularity(&None, &time_range), None);
+    }
+
+    #[test]
+    fn test_expected_buckets_alignment() {
+        let buckets = ExpectedBuckets::new(vec![
+            datetime!(2023-11-01 00:00:00),
+            datetime!(2023-11-08 00:00:00),
+            datetime!(2023-11-15 00:00:00),
+            datetime!(2023-11-30 00:00:00),
+        ])
+        .unwrap();
+        assert_eq!(
+            buckets.time_range().unwrap(),
+            analytics_api::TimeRange {
+                start_time: datetime!(2023-11-01 00:00:00),
+                end_time: Some(datetime!(2023-11-29 23:59:59.999999)),
+            }
+        );
+
+        let aligned = buckets.align(vec![
+            (datetime!(2023-10-31 23:59:59), "before"),
+            (datetime!(2023-11-01 00:00:00), "first"),
+            (datetime!(2023-11-07 23:59:59), "first_end"),
+            (datetime!(2023-11-20 12:00:00), "third"),
+            (datetime!(2023-11-30 00:00:00), "after"),
+        ]);
+        assert_eq!(
+            aligned,
+            vec![
+                (
+                    analytics_api::TimeRange {
+                        start_time: datetime!(2023-11-01 00:00:00),
+                        end_time: Some(datetime!(2023-11-08 00:00:00)),
+                    },
+                    vec!["first", "first_end"]
+                ),
+                (
+                    analytics_api::TimeRange {
+                        start_time: datetime!(2023-11-08 00:00:00),
+                        end_time: Some(datetime!(2023-11-15 00:00:00)),
+                    },
+                    vec![]
+                ),
+                (
+                    analytics_api::TimeRange {
+                        start_time: datetime!(2023-11-15 00:00:00),
+                        end_time: Some(datetime!(2023-11-30 00:00:00)),
+                    },
+                    vec!["third"]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expected_buckets_validation() {
+        assert!(ExpectedBuckets::new(vec![]).is_err());
+        assert!(ExpectedBuckets::new(vec![datetime!(2023-11-01 00:00:00)]).is_err());
+        assert!(ExpectedBuckets::new(vec![
+            datetime!(2023-11-08 00:00:00),
+            datetime!(2023-11-01 00:00:00),
+        ])
+        .is_err());
+        assert!(ExpectedBuckets::new(vec![
+            datetime!(2023-11-01 00:00:00),
+            datetime!(2023-11-01 00:00:00),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_align_time_range_to_granularity() {
+        let time_range = analytics_api::TimeRange {
+            start_time: datetime!(2023-11-01 10:07:23),
+            end_time: Some(datetime!(2023-11-01 13:52:41)),
+        };
+        let expected = [
+            (
+                Granularity::OneMin,
+                datetime!(2023-11-01 10:07:00),
+                datetime!(2023-11-01 13:52:59.999999),
+            ),
+            (
+                Granularity::FiveMin,
+                datetime!(2023-11-01 10:05:00),
+                datetime!(2023-11-01 13:54:59.999999),
+            ),
+            (
+                Granularity::FifteenMin,
+                datetime!(2023-11-01 10:00:00),
+                datetime!(2023-11-01 13:59:59.999999),
+            ),
+            (
+                Granularity::ThirtyMin,
+                datetime!(2023-11-01 10:00:00),
+                datetime!(2023-11-01 13:59:59.999999),
+            ),
+            (
+                Granularity::OneHour,
+                datetime!(2023-11-01 10:00:00),
+                datetime!(2023-11-01 13:59:59.999999),
+            ),
+            (
+                Granularity::OneDay,
+                datetime!(2023-11-01 00:00:00),
+                datetime!(2023-11-01 23:59:59.999999),
+            ),
+        ];
+
+        for (granularity, start_time, end_time) in expected {
+            let aligned = align_time_range_to_granularity(&time_range, &granularity).unwrap();
+            assert_eq!(aligned.start_time, start_time, "{granularity:?}");
+            assert_eq!(aligned.end_time, Some(end_time), "{granularity:?}");
+        }
+
+        let open_ended = analytics_api::TimeRange {
+            start_time: datetime!(2023-11-01 10:07:23),
+            end_time: None,
+        };
+        let aligned = align_time_range_to_granularity(&open_ended, &Granularity::OneHour).unwrap();
+        assert_eq!(aligned.start_time, datetime!(2023-11-01 10:00:00));
+        assert_eq!(aligned.end_time, None);
+    }
+
+    #[test]
+    fn test_optional_filter_clause_present() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_optional_filter_clause("currency", Some(Currency::USD))
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt WHERE currency = 'USD'"
+        );
+    }
+
+    #[test]
+    fn test_optional_filter_clause_absent() {
+        let mut builder = payment_query_builder();
+        builder
+            .add_optional_filter_clause("currency", None::<Currency>)
+            .unwrap();
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector FROM payment_attempt"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_execute_query_cancellable() {
+        let source = HangingSource::default();
+        let error = payment_query_builder()
+            .execute_query_cancellable::<u64, _>(&source, std::future::ready(()))
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(
+            error.current_context(),
+            QueryExecutionError::Cancelled
+        ));
+        assert!(source.started.load(Ordering::SeqCst));
+        assert!(source.stopped.load(Ordering::SeqCst));
+
+        let source = FlakySource::new(0, true);
+        let rows: Vec<u64> = payment_query_builder()
+            .execute_query_cancellable(&source, std::future::pending())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rows, vec![1]);
+    }
+
+    #[actix_rt::test]
+    async fn test_execute_query_with_sql() {
+        let source = FlakySource::new(0, true);
+        let mut builder = payment_query_builder();
+        let expected_query = builder.build_query().unwrap();
+        let (rows, query): (Vec<u64>, _) = builder
+            .execute_query_with_sql(&source)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rows, vec![1]);
+        assert_eq!(query, expected_query);
+
+        let source = TenantSource::default();
+        let (_, query) = payment_query_builder()
+            .execute_query_with_sql::<u64, _>(&source)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(query, "SELECT connector FROM tenant_1.payment_attempt");
+        assert_eq!(*source.queries.lock().unwrap(), vec![query]);
+    }
+
+    #[actix_rt::test]
+    async fn test_execute_query_timed() {
+        let source = FlakySource::new(0, true);
+        let (rows, elapsed): (Vec<u64>, _) = payment_query_builder()
+            .execute_query_timed(&source)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rows, vec![1]);
+        assert!(elapsed < Duration::from_secs(60));
+        assert_eq!(source.attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_execute_query_timed_passes_errors_through() {
+        let source = FlakySource::new(1, false);
+        let error = payment_query_builder()
+            .execute_query_timed::<u64, _>(&source)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(!error.current_context().is_transient());
+    }
+
+    #[actix_rt::test]
+    async fn test_execute_query_retries_transient_errors() {
+        let source = FlakySource::new(1, true);
+        let rows: Vec<u64> = payment_query_builder()
+            .execute_query_with_retry(&source, &immediate_retry_config())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rows, vec![1]);
+        assert_eq!(source.attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_rt::test]
+    async fn test_execute_query_does_not_retry_permanent_errors() {
+        let source = FlakySource::new(1, false);
+        let error = payment_query_builder()
+            .execute_query_with_retry::<u64, _>(&source, &immediate_retry_config())
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(!error.current_context().is_transient());
+        assert_eq!(source.attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_execute_query_gives_up_after_max_retries() {
+        let source = FlakySource::new(5, true);
+        let error = payment_query_builder()
+            .execute_query_with_retry::<u64, _>(&source, &immediate_retry_config())
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(error.current_context().is_transient());
+        assert_eq!(source.attempts.load(Ordering::SeqCst), 3);
+    }
 }
@@ -2,12 +2,16 @@ use api_models::analytics::refunds::RefundMetricsBucketValue;
 use common_enums::enums as storage_enums;
 
 use super::metrics::RefundMetricRow;
+use crate::analytics::utils::percentage;
 #[derive(Debug, Default)]
 pub struct RefundMetricsAccumulator {
     pub refund_success_rate: SuccessRateAccumulator,
     pub refund_count: CountAccumulator,
     pub refund_success: CountAccumulator,
     pub processed_amount: SumAccumulator,
+    pub refund_amount_ratio: AmountRatioAccumulator,
+    pub settlement_percentiles: SettlementPercentilesAccumulator,
+    pub reason_distribution: ReasonDistributionAccumulator,
 }
 
 #[derive(Debug, Default)]
@@ -28,6 +32,26 @@ pub struct SumAccumulator {
     pub total: Option<i64>,
 }
 
+#[derive(Debug, Default)]
+pub struct AmountRatioAccumulator {
+    pub refund_total: f64,
+    pub payment_total: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct SettlementPercentilesAccumulator {
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub p99: Option<f64>,
+}
+
+/// Refunds of a reason and their share in the refunds of the bucket, as a percentage
+#[derive(Debug, Default)]
+pub struct ReasonDistributionAccumulator {
+    pub count: CountAccumulator,
+    pub share: Option<f64>,
+}
+
 pub trait RefundMetricAccumulator {
     type MetricOutput;
 
@@ -87,24 +111,95 @@ impl RefundMetricAccumulator for SuccessRateAccumulator {
     }
 
     fn collect(self) -> Self::MetricOutput {
-        if self.total <= 0 {
+        percentage(self.success, self.total)
+    }
+}
+
+impl RefundMetricAccumulator for AmountRatioAccumulator {
+    type MetricOutput = Option<f64>;
+
+    fn add_metrics_bucket(&mut self, metrics: &RefundMetricRow) {
+        self.refund_total += metrics
+            .total
+            .as_ref()
+            .and_then(bigdecimal::ToPrimitive::to_f64)
+            .unwrap_or_default();
+        self.payment_total += metrics
+            .payment_total
+            .as_ref()
+            .and_then(bigdecimal::ToPrimitive::to_f64)
+            .unwrap_or_default();
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        if self.payment_total <= 0.0 {
             None
         } else {
-            Some(
-                f64::from(u32::try_from(self.success).ok()?) * 100.0
-                    / f64::from(u32::try_from(self.total).ok()?),
-            )
+            Some(self.refund_total * 100.0 / self.payment_total)
         }
     }
 }
 
+impl RefundMetricAccumulator for SettlementPercentilesAccumulator {
+    type MetricOutput = (Option<f64>, Option<f64>, Option<f64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &RefundMetricRow) {
+        // Percentiles of separate rows cannot be combined, the highest one is reported instead
+        let max = |acc: Option<f64>, value: Option<f64>| match (acc, value) {
+            (None, None) => None,
+            (None, i @ Some(_)) | (i @ Some(_), None) => i,
+            (Some(a), Some(b)) => Some(a.max(b)),
+        };
+        self.p50 = max(self.p50, metrics.p50);
+        self.p90 = max(self.p90, metrics.p90);
+        self.p99 = max(self.p99, metrics.p99);
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        (self.p50, self.p90, self.p99)
+    }
+}
+
+impl RefundMetricAccumulator for ReasonDistributionAccumulator {
+    type MetricOutput = (Option<u64>, Option<f64>);
+
+    fn add_metrics_bucket(&mut self, metrics: &RefundMetricRow) {
+        self.count.add_metrics_bucket(metrics);
+        let share = metrics
+            .reason_share
+            .as_ref()
+            .and_then(bigdecimal::ToPrimitive::to_f64);
+        self.share = match (self.share, share) {
+            (None, None) => None,
+            (None, i @ Some(_)) | (i @ Some(_), None) => i,
+            (Some(a), Some(b)) => Some(a + b),
+        }
+    }
+
+    fn collect(self) -> Self::MetricOutput {
+        (self.count.collect(), self.share)
+    }
+}
+
 impl RefundMetricsAccumulator {
     pub fn collect(self) -> RefundMetricsBucketValue {
+        let (
+            refund_settlement_p50_seconds,
+            refund_settlement_p90_seconds,
+            refund_settlement_p99_seconds,
+        ) = self.settlement_percentiles.collect();
+        let (refund_reason_count, refund_reason_share) = self.reason_distribution.collect();
         RefundMetricsBucketValue {
             refund_success_rate: self.refund_success_rate.collect(),
             refund_count: self.refund_count.collect(),
             refund_success_count: self.refund_success.collect(),
             refund_processed_amount: self.processed_amount.collect(),
+            refund_amount_ratio: self.refund_amount_ratio.collect(),
+            refund_settlement_p50_seconds,
+            refund_settlement_p90_seconds,
+            refund_settlement_p99_seconds,
+            refund_reason_count,
+            refund_reason_share,
         }
     }
 }
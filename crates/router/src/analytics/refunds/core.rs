@@ -13,8 +13,8 @@ use router_env::{
 use super::RefundMetricsAccumulator;
 use crate::{
     analytics::{
-        core::AnalyticsApiResponse, errors::AnalyticsError, refunds::RefundMetricAccumulator,
-        AnalyticsProvider,
+        core::AnalyticsApiResponse, errors::AnalyticsError, query::resolve_granularity,
+        refunds::RefundMetricAccumulator, AnalyticsProvider,
     },
     services::ApplicationResponse,
     types::domain,
@@ -44,7 +44,7 @@ pub async fn get_metrics(
                         &req.group_by_names.clone(),
                         &merchant_id,
                         &req.filters,
-                        &req.time_series.map(|t| t.granularity),
+                        &resolve_granularity(&req.time_series, &req.time_range),
                         &req.time_range,
                     )
                     .await
@@ -78,6 +78,15 @@ pub async fn get_metrics(
                 RefundMetrics::RefundProcessedAmount => {
                     metrics_builder.processed_amount.add_metrics_bucket(&value)
                 }
+                RefundMetrics::RefundAmountRatio => metrics_builder
+                    .refund_amount_ratio
+                    .add_metrics_bucket(&value),
+                RefundMetrics::RefundSettlementPercentiles => metrics_builder
+                    .settlement_percentiles
+                    .add_metrics_bucket(&value),
+                RefundMetrics::RefundReasonDistribution => metrics_builder
+                    .reason_distribution
+                    .add_metrics_bucket(&value),
             }
         }
 
@@ -30,6 +30,7 @@ where
     Aggregate<&'static str>: ToSql<T>,
 {
     let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Refund);
+    query_builder.set_storage_timezone(pool.storage_timezone());
 
     query_builder.add_select_column(dimension).switch()?;
     time_range
@@ -56,4 +57,5 @@ pub struct RefundFilterRow {
     pub refund_status: Option<DBEnumWrapper<RefundStatus>>,
     pub connector: Option<String>,
     pub refund_type: Option<DBEnumWrapper<RefundType>>,
+    pub refund_reason: Option<String>,
 }
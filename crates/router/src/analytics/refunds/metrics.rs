@@ -6,12 +6,18 @@ use api_models::analytics::{
 };
 use common_enums::enums as storage_enums;
 use time::PrimitiveDateTime;
+mod refund_amount_ratio;
 mod refund_count;
 mod refund_processed_amount;
+mod refund_reason_distribution;
+mod refund_settlement_percentiles;
 mod refund_success_count;
 mod refund_success_rate;
+use refund_amount_ratio::RefundAmountRatio;
 use refund_count::RefundCount;
 use refund_processed_amount::RefundProcessedAmount;
+use refund_reason_distribution::RefundReasonDistribution;
+use refund_settlement_percentiles::RefundSettlementPercentiles;
 use refund_success_count::RefundSuccessCount;
 use refund_success_rate::RefundSuccessRate;
 
@@ -20,14 +26,20 @@ use crate::analytics::{
     types::{AnalyticsCollection, AnalyticsDataSource, DBEnumWrapper, LoadRow, MetricsResult},
 };
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub struct RefundMetricRow {
     pub currency: Option<DBEnumWrapper<storage_enums::Currency>>,
     pub refund_status: Option<DBEnumWrapper<storage_enums::RefundStatus>>,
     pub connector: Option<String>,
     pub refund_type: Option<DBEnumWrapper<RefundType>>,
+    pub refund_reason: Option<String>,
     pub total: Option<bigdecimal::BigDecimal>,
+    pub payment_total: Option<bigdecimal::BigDecimal>,
     pub count: Option<i64>,
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub p99: Option<f64>,
+    pub reason_share: Option<bigdecimal::BigDecimal>,
     pub start_bucket: Option<PrimitiveDateTime>,
     pub end_bucket: Option<PrimitiveDateTime>,
 }
@@ -121,6 +133,42 @@ where
                     )
                     .await
             }
+            Self::RefundAmountRatio => {
+                RefundAmountRatio::default()
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            Self::RefundSettlementPercentiles => {
+                RefundSettlementPercentiles::default()
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
+            Self::RefundReasonDistribution => {
+                RefundReasonDistribution
+                    .load_metrics(
+                        dimensions,
+                        merchant_id,
+                        filters,
+                        granularity,
+                        time_range,
+                        pool,
+                    )
+                    .await
+            }
         }
     }
 }
@@ -0,0 +1,197 @@
+use api_models::analytics::{
+    refunds::{RefundDimensions, RefundFilters, RefundMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use router_env::logger;
+use time::PrimitiveDateTime;
+
+use super::RefundMetricRow;
+use crate::analytics::{
+    query::{
+        storage_time_to_utc, Aggregate, BaseFilters, GroupByClause, QueryBuilder, QueryFilter,
+        SeriesBucket, ToSql,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsError, MetricsResult},
+    utils::requires_currency_grouping,
+};
+
+/// Sums successful refund amounts against charged payment amounts over
+/// [`AnalyticsCollection::RefundWithPayment`], which exposes both as `refund_amount` and
+/// `payment_amount` columns.
+#[derive(Default)]
+pub(super) struct RefundAmountRatio {}
+
+#[async_trait::async_trait]
+impl<T> super::RefundMetric<T> for RefundAmountRatio
+where
+    T: AnalyticsDataSource + super::RefundMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[RefundDimensions],
+        merchant_id: &str,
+        filters: &RefundFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(RefundMetricsBucketIdentifier, RefundMetricRow)>>
+    where
+        T: AnalyticsDataSource + super::RefundMetricAnalytics,
+    {
+        let mut query_builder: QueryBuilder<T> =
+            QueryBuilder::new(AnalyticsCollection::RefundWithPayment);
+        query_builder.set_storage_timezone(pool.storage_timezone());
+        let mut dimensions = dimensions.to_vec();
+
+        if requires_currency_grouping(
+            &filters.currency,
+            dimensions.contains(&RefundDimensions::Currency),
+        ) {
+            logger::warn!(
+                "Grouping amounts by currency as the request is not limited to one currency"
+            );
+            dimensions.push(RefundDimensions::Currency);
+        }
+
+        for dim in dimensions.iter() {
+            query_builder.add_select_column(dim).switch()?;
+        }
+
+        query_builder
+            .add_select_column(Aggregate::Sum {
+                field: "refund_amount",
+                alias: Some("total"),
+            })
+            .switch()?;
+        query_builder
+            .add_select_column(Aggregate::Sum {
+                field: "payment_amount",
+                alias: Some("payment_total"),
+            })
+            .switch()?;
+        query_builder
+            .add_select_column(Aggregate::Min {
+                field: "created_at",
+                alias: Some("start_bucket"),
+            })
+            .switch()?;
+        query_builder
+            .add_select_column(Aggregate::Max {
+                field: "created_at",
+                alias: Some("end_bucket"),
+            })
+            .switch()?;
+
+        filters.set_filter_clause(&mut query_builder).switch()?;
+
+        BaseFilters {
+            merchant_id,
+            time_range,
+        }
+        .apply_to(&mut query_builder)
+        .switch()?;
+
+        for dim in dimensions.iter() {
+            query_builder.add_group_by_clause(dim).switch()?;
+        }
+
+        if let Some(granularity) = granularity.as_ref() {
+            granularity
+                .set_group_by_clause(&mut query_builder)
+                .switch()?;
+        }
+
+        query_builder
+            .execute_query::<RefundMetricRow, _>(pool)
+            .await
+            .change_context(MetricsError::QueryBuildingError)?
+            .change_context(MetricsError::QueryExecutionFailure)?
+            .into_iter()
+            .map(|i| {
+                Ok((
+                    RefundMetricsBucketIdentifier::new(
+                        i.currency.as_ref().map(|i| i.0),
+                        None,
+                        i.connector.clone(),
+                        i.refund_type.as_ref().map(|i| i.0.to_string()),
+                        i.refund_reason.clone(),
+                        TimeRange {
+                            start_time: match (granularity, i.start_bucket) {
+                                (Some(g), Some(st)) => g.clip_to_start(storage_time_to_utc(
+                                    st,
+                                    pool.storage_timezone(),
+                                )?)?,
+                                _ => time_range.start_time,
+                            },
+                            end_time: granularity.as_ref().map_or_else(
+                                || Ok(time_range.end_time),
+                                |g| {
+                                    i.end_bucket
+                                        .map(|et| {
+                                            storage_time_to_utc(et, pool.storage_timezone())
+                                                .and_then(|et| g.clip_to_end(et))
+                                        })
+                                        .transpose()
+                                },
+                            )?,
+                        },
+                    ),
+                    i,
+                ))
+            })
+            .collect::<error_stack::Result<Vec<_>, crate::analytics::query::PostProcessingError>>()
+            .change_context(MetricsError::PostProcessingFailure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_refund_amount_ratio_query() {
+        let mut builder: QueryBuilder<SqlxClient> =
+            QueryBuilder::new(AnalyticsCollection::RefundWithPayment);
+        builder
+            .add_select_column(RefundDimensions::Currency)
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Sum {
+                field: "refund_amount",
+                alias: Some("total"),
+            })
+            .unwrap();
+        builder
+            .add_select_column(Aggregate::Sum {
+                field: "payment_amount",
+                alias: Some("payment_total"),
+            })
+            .unwrap();
+        builder
+            .add_filter_clause("merchant_id", "merchant_1")
+            .unwrap();
+        builder
+            .add_group_by_clause(RefundDimensions::Currency)
+            .unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT currency, sum(refund_amount) as total, sum(payment_amount) as payment_total \
+             FROM (SELECT merchant_id, currency, connector, refund_status, refund_type, \
+             refund_reason, refund_amount, 0 AS payment_amount, created_at, modified_at \
+             FROM refund WHERE refund_status = 'success' \
+             UNION ALL SELECT merchant_id, currency, connector, NULL, NULL, NULL, \
+             0, amount, created_at, modified_at FROM payment_attempt \
+             WHERE status = 'charged') AS refund_with_payment \
+             WHERE merchant_id = 'merchant_1' GROUP BY currency"
+        );
+    }
+}
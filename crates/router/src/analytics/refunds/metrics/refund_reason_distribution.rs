@@ -0,0 +1,207 @@
+use api_models::analytics::{
+    refunds::{RefundDimensions, RefundFilters, RefundMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::RefundMetricRow;
+use crate::analytics::{
+    query::{
+        percentage_expression, storage_time_to_utc, Aggregate, BaseFilters, GroupByClause,
+        QueryBuilder, QueryBuildingError, QueryFilter, QueryResult, SeriesBucket, ToSql, Window,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsError, MetricsResult},
+};
+
+/// Selects the share of every refund reason in the refunds of its bucket, as a percentage, as
+/// `reason_share`. The bucket total is summed over the groups of all the reasons sharing the other
+/// group by expressions, so the query must be grouped before the column is added.
+pub(super) fn add_reason_share_column<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    let reason = RefundDimensions::RefundReason.to_string();
+    let bucket_total = <Window<_, _> as ToSql<T>>::to_sql(&Window {
+        function: "sum(count(*))",
+        partition_by: builder
+            .get_group_by_columns()
+            .into_iter()
+            .filter(|column| *column != reason)
+            .collect(),
+        order_by: None,
+        alias: None,
+    })
+    .change_context(QueryBuildingError::SqlSerializeError)
+    .attach_printable("Error serializing bucket total")?;
+    builder
+        .add_select_column(format!(
+            "{} as reason_share",
+            percentage_expression("count(*)", &bucket_total)
+        ))
+        .attach_printable("Error adding reason share")
+}
+
+#[derive(Default)]
+pub(super) struct RefundReasonDistribution;
+
+#[async_trait::async_trait]
+impl<T> super::RefundMetric<T> for RefundReasonDistribution
+where
+    T: AnalyticsDataSource + super::RefundMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[RefundDimensions],
+        merchant_id: &str,
+        filters: &RefundFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(RefundMetricsBucketIdentifier, RefundMetricRow)>> {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Refund);
+        query_builder.set_storage_timezone(pool.storage_timezone());
+
+        // The distribution is always broken down by reason
+        let dimensions = dimensions
+            .iter()
+            .copied()
+            .filter(|dim| *dim != RefundDimensions::RefundReason)
+            .chain([RefundDimensions::RefundReason])
+            .collect::<Vec<_>>();
+
+        for dim in dimensions.iter() {
+            query_builder.add_select_column(dim).switch()?;
+        }
+
+        query_builder
+            .add_select_column(Aggregate::Count {
+                field: None,
+                alias: Some("count"),
+            })
+            .switch()?;
+        query_builder
+            .add_select_column(Aggregate::Min {
+                field: "created_at",
+                alias: Some("start_bucket"),
+            })
+            .switch()?;
+        query_builder
+            .add_select_column(Aggregate::Max {
+                field: "created_at",
+                alias: Some("end_bucket"),
+            })
+            .switch()?;
+
+        filters.set_filter_clause(&mut query_builder).switch()?;
+
+        BaseFilters {
+            merchant_id,
+            time_range,
+        }
+        .apply_to(&mut query_builder)
+        .switch()?;
+
+        for dim in dimensions.iter() {
+            query_builder
+                .add_group_by_clause(dim)
+                .attach_printable("Error grouping by dimensions")
+                .switch()?;
+        }
+
+        if let Some(granularity) = granularity.as_ref() {
+            granularity
+                .set_group_by_clause(&mut query_builder)
+                .attach_printable("Error adding granularity")
+                .switch()?;
+        }
+
+        add_reason_share_column(&mut query_builder).switch()?;
+
+        query_builder
+            .execute_query::<RefundMetricRow, _>(pool)
+            .await
+            .change_context(MetricsError::QueryBuildingError)?
+            .change_context(MetricsError::QueryExecutionFailure)?
+            .into_iter()
+            .map(|i| {
+                Ok((
+                    RefundMetricsBucketIdentifier::new(
+                        i.currency.as_ref().map(|i| i.0),
+                        i.refund_status.as_ref().map(|i| i.0),
+                        i.connector.clone(),
+                        i.refund_type.as_ref().map(|i| i.0.to_string()),
+                        i.refund_reason.clone(),
+                        TimeRange {
+                            start_time: match (granularity, i.start_bucket) {
+                                (Some(g), Some(st)) => g.clip_to_start(storage_time_to_utc(
+                                    st,
+                                    pool.storage_timezone(),
+                                )?)?,
+                                _ => time_range.start_time,
+                            },
+                            end_time: granularity.as_ref().map_or_else(
+                                || Ok(time_range.end_time),
+                                |g| {
+                                    i.end_bucket
+                                        .map(|et| {
+                                            storage_time_to_utc(et, pool.storage_timezone())
+                                                .and_then(|et| g.clip_to_end(et))
+                                        })
+                                        .transpose()
+                                },
+                            )?,
+                        },
+                    ),
+                    i,
+                ))
+            })
+            .collect::<error_stack::Result<Vec<_>, crate::analytics::query::PostProcessingError>>()
+            .change_context(MetricsError::PostProcessingFailure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_reason_share_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Refund);
+        for dim in [RefundDimensions::Connector, RefundDimensions::RefundReason] {
+            builder.add_select_column(dim).unwrap();
+        }
+        builder
+            .add_select_column(Aggregate::Count {
+                field: None,
+                alias: Some("count"),
+            })
+            .unwrap();
+        builder
+            .add_filter_clause("merchant_id", "merchant_1")
+            .unwrap();
+        for dim in [RefundDimensions::Connector, RefundDimensions::RefundReason] {
+            builder.add_group_by_clause(dim).unwrap();
+        }
+        builder
+            .add_group_by_clause("DATE_TRUNC('day', created_at)")
+            .unwrap();
+        add_reason_share_column(&mut builder).unwrap();
+
+        assert_eq!(
+            builder.build_query().unwrap(),
+            "SELECT connector, refund_reason, count(*) as count, count(*) * 100.0 / \
+             NULLIF(sum(count(*)) OVER (PARTITION BY connector, DATE_TRUNC('day', created_at)), \
+             0) as reason_share FROM refund WHERE merchant_id = 'merchant_1' \
+             GROUP BY connector, refund_reason, DATE_TRUNC('day', created_at)"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use api_models::analytics::{
+    refunds::{RefundDimensions, RefundFilters, RefundMetricsBucketIdentifier},
+    Granularity, TimeRange,
+};
+use common_enums::enums as storage_enums;
+use common_utils::errors::ReportSwitchExt;
+use error_stack::ResultExt;
+use time::PrimitiveDateTime;
+
+use super::RefundMetricRow;
+use crate::analytics::{
+    query::{
+        storage_time_to_utc, Aggregate, BaseFilters, GroupByClause, QueryBuilder, QueryFilter,
+        QueryResult, SeriesBucket, ToSql,
+    },
+    types::{AnalyticsCollection, AnalyticsDataSource, MetricsError, MetricsResult},
+};
+
+/// Time taken by a refund to settle, in seconds. Refunds are not updated after reaching the
+/// success status, so the time between their creation and last update is used.
+pub(super) const REFUND_SETTLEMENT_SECONDS: &str = "EXTRACT(EPOCH FROM (modified_at - created_at))";
+
+pub(super) fn set_settled_refund_filter_clause<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+{
+    builder
+        .add_filter_clause(
+            RefundDimensions::RefundStatus,
+            storage_enums::RefundStatus::Success,
+        )
+        .attach_printable("Error adding settled refund status filter")
+}
+
+/// Selects the p50, p90 and p99 settlement latencies as the `p50`, `p90` and `p99` columns
+pub(super) fn add_settlement_percentile_columns<T>(builder: &mut QueryBuilder<T>) -> QueryResult<()>
+where
+    T: AnalyticsDataSource,
+    AnalyticsCollection: ToSql<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    for (percentile, alias) in [(50, "p50"), (90, "p90"), (99, "p99")] {
+        builder
+            .add_select_column(Aggregate::Percentile {
+                field: REFUND_SETTLEMENT_SECONDS,
+                alias: Some(alias),
+                percentile,
+            })
+            .attach_printable_lazy(|| format!("Error adding {alias} settlement latency"))?;
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+pub(super) struct RefundSettlementPercentiles {}
+
+#[async_trait::async_trait]
+impl<T> super::RefundMetric<T> for RefundSettlementPercentiles
+where
+    T: AnalyticsDataSource + super::RefundMetricAnalytics,
+    PrimitiveDateTime: ToSql<T>,
+    AnalyticsCollection: ToSql<T>,
+    Granularity: GroupByClause<T>,
+    Aggregate<&'static str>: ToSql<T>,
+{
+    async fn load_metrics(
+        &self,
+        dimensions: &[RefundDimensions],
+        merchant_id: &str,
+        filters: &RefundFilters,
+        granularity: &Option<Granularity>,
+        time_range: &TimeRange,
+        pool: &T,
+    ) -> MetricsResult<Vec<(RefundMetricsBucketIdentifier, RefundMetricRow)>>
+    where
+        T: AnalyticsDataSource + super::RefundMetricAnalytics,
+    {
+        let mut query_builder: QueryBuilder<T> = QueryBuilder::new(AnalyticsCollection::Refund);
+        query_builder.set_storage_timezone(pool.storage_timezone());
+        let mut dimensions = dimensions.to_vec();
+
+        if !dimensions.contains(&RefundDimensions::Connector) {
+            dimensions.push(RefundDimensions::Connector);
+        }
+
+        for dim in dimensions.iter() {
+            query_builder.add_select_column(dim).switch()?;
+        }
+
+        add_settlement_percentile_columns(&mut query_builder).switch()?;
+        query_builder
+            .add_select_column(Aggregate::Min {
+                field: "created_at",
+                alias: Some("start_bucket"),
+            })
+            .switch()?;
+        query_builder
+            .add_select_column(Aggregate::Max {
+                field: "created_at",
+                alias: Some("end_bucket"),
+            })
+            .switch()?;
+
+        filters.set_filter_clause(&mut query_builder).switch()?;
+
+        BaseFilters {
+            merchant_id,
+            time_range,
+        }
+        .apply_to(&mut query_builder)
+        .switch()?;
+
+        set_settled_refund_filter_clause(&mut query_builder).switch()?;
+
+        for dim in dimensions.iter() {
+            query_builder.add_group_by_clause(dim).switch()?;
+        }
+
+        if let Some(granularity) = granularity.as_ref() {
+            granularity
+                .set_group_by_clause(&mut query_builder)
+                .switch()?;
+        }
+
+        query_builder
+            .execute_query::<RefundMetricRow, _>(pool)
+            .await
+            .change_context(MetricsError::QueryBuildingError)?
+            .change_context(MetricsError::QueryExecutionFailure)?
+            .into_iter()
+            .map(|i| {
+                Ok((
+                    RefundMetricsBucketIdentifier::new(
+                        i.currency.as_ref().map(|i| i.0),
+                        None,
+                        i.connector.clone(),
+                        i.refund_type.as_ref().map(|i| i.0.to_string()),
+                        i.refund_reason.clone(),
+                        TimeRange {
+                            start_time: match (granularity, i.start_bucket) {
+                                (Some(g), Some(st)) => g.clip_to_start(storage_time_to_utc(
+                                    st,
+                                    pool.storage_timezone(),
+                                )?)?,
+                                _ => time_range.start_time,
+                            },
+                            end_time: granularity.as_ref().map_or_else(
+                                || Ok(time_range.end_time),
+                                |g| {
+                                    i.end_bucket
+                                        .map(|et| {
+                                            storage_time_to_utc(et, pool.storage_timezone())
+                                                .and_then(|et| g.clip_to_end(et))
+                                        })
+                                        .transpose()
+                                },
+                            )?,
+                        },
+                    ),
+                    i,
+                ))
+            })
+            .collect::<error_stack::Result<Vec<_>, crate::analytics::query::PostProcessingError>>()
+            .change_context(MetricsError::PostProcessingFailure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+    use crate::analytics::SqlxClient;
+
+    #[test]
+    fn test_refund_settlement_percentiles_query() {
+        let mut builder: QueryBuilder<SqlxClient> = QueryBuilder::new(AnalyticsCollection::Refund);
+        builder
+            .add_select_column(RefundDimensions::Connector)
+            .unwrap();
+        add_settlement_percentile_columns(&mut builder).unwrap();
+        set_settled_refund_filter_clause(&mut builder).unwrap();
+        builder
+            .add_group_by_clause(RefundDimensions::Connector)
+            .unwrap();
+
+        let latency = REFUND_SETTLEMENT_SECONDS;
+        assert_eq!(
+            builder.build_query().unwrap(),
+            format!(
+                "SELECT connector, \
+                 percentile_cont(0.5) WITHIN GROUP (ORDER BY {latency}) as p50, \
+                 percentile_cont(0.9) WITHIN GROUP (ORDER BY {latency}) as p90, \
+                 percentile_cont(0.99) WITHIN GROUP (ORDER BY {latency}) as p99 \
+                 FROM refund WHERE refund_status = 'success' GROUP BY connector"
+            )
+        );
+    }
+}
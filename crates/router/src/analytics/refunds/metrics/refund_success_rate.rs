@@ -8,7 +8,10 @@ use time::PrimitiveDateTime;
 
 use super::RefundMetricRow;
 use crate::analytics::{
-    query::{Aggregate, GroupByClause, QueryBuilder, QueryFilter, SeriesBucket, ToSql},
+    query::{
+        storage_time_to_utc, Aggregate, BaseFilters, GroupByClause, QueryBuilder, QueryFilter,
+        SeriesBucket, ToSql,
+    },
     types::{AnalyticsCollection, AnalyticsDataSource, MetricsError, MetricsResult},
 };
 #[derive(Default)]
@@ -36,6 +39,7 @@ where
         T: AnalyticsDataSource + super::RefundMetricAnalytics,
     {
         let mut query_builder = QueryBuilder::new(AnalyticsCollection::Refund);
+        query_builder.set_storage_timezone(pool.storage_timezone());
         let mut dimensions = dimensions.to_vec();
 
         dimensions.push(RefundDimensions::RefundStatus);
@@ -65,11 +69,12 @@ where
 
         filters.set_filter_clause(&mut query_builder).switch()?;
 
-        query_builder
-            .add_filter_clause("merchant_id", merchant_id)
-            .switch()?;
-
-        time_range.set_filter_clause(&mut query_builder).switch()?;
+        BaseFilters {
+            merchant_id,
+            time_range,
+        }
+        .apply_to(&mut query_builder)
+        .switch()?;
 
         for dim in dimensions.iter() {
             query_builder.add_group_by_clause(dim).switch()?;
@@ -94,14 +99,25 @@ where
                         None,
                         i.connector.clone(),
                         i.refund_type.as_ref().map(|i| i.0.to_string()),
+                        i.refund_reason.clone(),
                         TimeRange {
                             start_time: match (granularity, i.start_bucket) {
-                                (Some(g), Some(st)) => g.clip_to_start(st)?,
+                                (Some(g), Some(st)) => g.clip_to_start(storage_time_to_utc(
+                                    st,
+                                    pool.storage_timezone(),
+                                )?)?,
                                 _ => time_range.start_time,
                             },
                             end_time: granularity.as_ref().map_or_else(
                                 || Ok(time_range.end_time),
-                                |g| i.end_bucket.map(|et| g.clip_to_end(et)).transpose(),
+                                |g| {
+                                    i.end_bucket
+                                        .map(|et| {
+                                            storage_time_to_utc(et, pool.storage_timezone())
+                                                .and_then(|et| g.clip_to_end(et))
+                                        })
+                                        .transpose()
+                                },
                             )?,
                         },
                     ),
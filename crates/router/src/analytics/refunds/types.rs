@@ -36,6 +36,12 @@ where
                 .attach_printable("Error adding auth type filter")?;
         }
 
+        if !self.refund_reason.is_empty() {
+            builder
+                .add_filter_in_range_clause(RefundDimensions::RefundReason, &self.refund_reason)
+                .attach_printable("Error adding refund reason filter")?;
+        }
+
         Ok(())
     }
 }
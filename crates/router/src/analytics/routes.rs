@@ -1,11 +1,11 @@
 use actix_web::{web, Responder, Scope};
 use api_models::analytics::{
-    GetPaymentFiltersRequest, GetPaymentMetricRequest, GetRefundFilterRequest,
-    GetRefundMetricRequest,
+    GetDisputeMetricRequest, GetPaymentFiltersRequest, GetPaymentMetricRequest,
+    GetRefundFilterRequest, GetRefundMetricRequest,
 };
 use router_env::AnalyticsFlow;
 
-use super::{core::*, payments, refunds, types::AnalyticsDomain};
+use super::{core::*, disputes, payments, refunds, types::AnalyticsDomain};
 use crate::{
     core::api_locking,
     services::{
@@ -23,6 +23,7 @@ impl Analytics {
         route
             .service(web::resource("metrics/payments").route(web::post().to(get_payment_metrics)))
             .service(web::resource("metrics/refunds").route(web::post().to(get_refunds_metrics)))
+            .service(web::resource("metrics/disputes").route(web::post().to(get_dispute_metrics)))
             .service(web::resource("filters/payments").route(web::post().to(get_payment_filters)))
             .service(web::resource("filters/refunds").route(web::post().to(get_refund_filters)))
             .service(web::resource("{domain}/info").route(web::get().to(get_info)))
@@ -115,6 +116,40 @@ pub async fn get_refunds_metrics(
     .await
 }
 
+/// # Panics
+///
+/// Panics if `json_payload` array does not contain one `GetDisputeMetricRequest` element.
+pub async fn get_dispute_metrics(
+    state: web::Data<AppState>,
+    req: actix_web::HttpRequest,
+    json_payload: web::Json<[GetDisputeMetricRequest; 1]>,
+) -> impl Responder {
+    // safety: This shouldn't panic owing to the data type
+    #[allow(clippy::expect_used)]
+    let payload = json_payload
+        .into_inner()
+        .to_vec()
+        .pop()
+        .expect("Couldn't get GetDisputeMetricRequest");
+    let flow = AnalyticsFlow::GetDisputeMetrics;
+    api::server_wrap(
+        flow,
+        state,
+        &req,
+        payload,
+        |state, auth: AuthenticationData, req| {
+            disputes::get_metrics(state.pool.clone(), auth.merchant_account, req)
+        },
+        auth::auth_type(
+            &auth::ApiKeyAuth,
+            &auth::JWTAuth(Permission::Analytics),
+            req.headers(),
+        ),
+        api_locking::LockAction::NotApplicable,
+    )
+    .await
+}
+
 pub async fn get_payment_filters(
     state: web::Data<AppState>,
     req: actix_web::HttpRequest,
@@ -2,10 +2,11 @@ use std::{fmt::Display, str::FromStr};
 
 use api_models::analytics::refunds::RefundType;
 use common_enums::enums::{
-    AttemptStatus, AuthenticationType, Currency, PaymentMethod, RefundStatus,
+    AttemptStatus, AuthenticationType, CountryAlpha2, Currency, DisputeStage, PaymentMethod,
+    RefundStatus,
 };
 use common_utils::errors::{CustomResult, ParsingError};
-use error_stack::{IntoReport, ResultExt};
+use error_stack::{report, IntoReport, ResultExt};
 #[cfg(feature = "kms")]
 use external_services::{kms, kms::decrypt::KmsDecrypt};
 #[cfg(not(feature = "kms"))]
@@ -16,12 +17,13 @@ use sqlx::{
     Error::ColumnNotFound,
     FromRow, Pool, Postgres, Row,
 };
-use time::PrimitiveDateTime;
+use time::{PrimitiveDateTime, UtcOffset};
 
 use super::{
-    query::{Aggregate, ToSql},
+    query::{Aggregate, QueryPlanRow, ToSql},
     types::{
-        AnalyticsCollection, AnalyticsDataSource, DBEnumWrapper, LoadRow, QueryExecutionError,
+        AnalyticsCollection, AnalyticsDataSource, DBEnumWrapper, JsonAggregationStyle, LoadRow,
+        QueryExecutionError, SampleStyle, StorageSchema, StorageTimezone,
     },
 };
 use crate::configs::settings::Database;
@@ -29,6 +31,8 @@ use crate::configs::settings::Database;
 #[derive(Debug, Clone)]
 pub struct SqlxClient {
     pool: Pool<Postgres>,
+    storage_timezone: UtcOffset,
+    schema: Option<StorageSchema>,
 }
 
 impl Default for SqlxClient {
@@ -42,6 +46,8 @@ impl Default for SqlxClient {
             pool: PgPoolOptions::new()
                 .connect_lazy(&database_url)
                 .expect("SQLX Pool Creation failed"),
+            storage_timezone: UtcOffset::UTC,
+            schema: None,
         }
     }
 }
@@ -49,6 +55,8 @@ impl Default for SqlxClient {
 impl SqlxClient {
     pub async fn from_conf(
         conf: &Database,
+        storage_timezone: StorageTimezone,
+        schema: Option<StorageSchema>,
         #[cfg(feature = "kms")] kms_client: &kms::KmsClient,
     ) -> Self {
         #[cfg(feature = "kms")]
@@ -71,7 +79,11 @@ impl SqlxClient {
             .acquire_timeout(std::time::Duration::from_secs(conf.connection_timeout))
             .connect_lazy(&database_url)
             .expect("SQLX Pool Creation failed");
-        Self { pool }
+        Self {
+            pool,
+            storage_timezone: storage_timezone.0,
+            schema,
+        }
     }
 }
 
@@ -97,11 +109,13 @@ macro_rules! db_type {
 }
 
 db_type!(Currency);
+db_type!(CountryAlpha2);
 db_type!(AuthenticationType);
 db_type!(AttemptStatus);
 db_type!(PaymentMethod, TEXT);
 db_type!(RefundStatus);
 db_type!(RefundType);
+db_type!(DisputeStage);
 
 impl<'q, Type> Encode<'q, Postgres> for DBEnumWrapper<Type>
 where
@@ -156,11 +170,37 @@ impl super::payments::filters::PaymentFilterAnalytics for SqlxClient {}
 impl super::payments::metrics::PaymentMetricAnalytics for SqlxClient {}
 impl super::refunds::metrics::RefundMetricAnalytics for SqlxClient {}
 impl super::refunds::filters::RefundFilterAnalytics for SqlxClient {}
+impl super::disputes::metrics::DisputeMetricAnalytics for SqlxClient {}
 
 #[async_trait::async_trait]
 impl AnalyticsDataSource for SqlxClient {
     type Row = PgRow;
 
+    const SUPPORTS_GROUP_BY_ORDINAL: bool = true;
+
+    const SUPPORTS_ORDER_BY_ALIAS: bool = true;
+
+    const SUPPORTS_NULLS_ORDER: bool = true;
+
+    const SUPPORTS_DISTINCT_ON: bool = true;
+
+    const SUPPORTS_CUBE: bool = true;
+
+    const SAMPLE_STYLE: Option<SampleStyle> = Some(SampleStyle::TableSample);
+
+    const JSON_AGGREGATION_STYLE: Option<JsonAggregationStyle> =
+        Some(JsonAggregationStyle::ObjectAgg);
+
+    const EXPLAIN_PREFIX: &'static str = "EXPLAIN ANALYZE";
+
+    fn storage_timezone(&self) -> UtcOffset {
+        self.storage_timezone
+    }
+
+    fn schema(&self) -> Option<&str> {
+        self.schema.as_ref().map(AsRef::as_ref)
+    }
+
     async fn load_results<T>(&self, query: &str) -> CustomResult<Vec<T>, QueryExecutionError>
     where
         Self: LoadRow<T>,
@@ -168,8 +208,15 @@ impl AnalyticsDataSource for SqlxClient {
         sqlx::query(&format!("{query};"))
             .fetch_all(&self.pool)
             .await
-            .into_report()
-            .change_context(QueryExecutionError::DatabaseError)
+            .map_err(|error| {
+                let context = match error {
+                    sqlx::Error::PoolTimedOut | sqlx::Error::Io(_) => {
+                        QueryExecutionError::TransientDatabaseError
+                    }
+                    _ => QueryExecutionError::DatabaseError,
+                };
+                report!(error).change_context(context)
+            })
             .attach_printable_lazy(|| format!("Failed to run query {query}"))?
             .into_iter()
             .map(Self::load_row)
@@ -199,14 +246,40 @@ impl<'a> FromRow<'a, PgRow> for super::refunds::metrics::RefundMetricRow {
                 ColumnNotFound(_) => Ok(Default::default()),
                 e => Err(e),
             })?;
+        let refund_reason: Option<String> = row.try_get("refund_reason").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
         let total: Option<bigdecimal::BigDecimal> = row.try_get("total").or_else(|e| match e {
             ColumnNotFound(_) => Ok(Default::default()),
             e => Err(e),
         })?;
+        let payment_total: Option<bigdecimal::BigDecimal> =
+            row.try_get("payment_total").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
         let count: Option<i64> = row.try_get("count").or_else(|e| match e {
             ColumnNotFound(_) => Ok(Default::default()),
             e => Err(e),
         })?;
+        let p50: Option<f64> = row.try_get("p50").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let p90: Option<f64> = row.try_get("p90").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let p99: Option<f64> = row.try_get("p99").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let reason_share: Option<bigdecimal::BigDecimal> =
+            row.try_get("reason_share").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
 
         let start_bucket: Option<PrimitiveDateTime> = row
             .try_get::<Option<PrimitiveDateTime>, _>("start_bucket")?
@@ -219,8 +292,62 @@ impl<'a> FromRow<'a, PgRow> for super::refunds::metrics::RefundMetricRow {
             refund_status,
             connector,
             refund_type,
+            refund_reason,
             total,
+            payment_total,
             count,
+            p50,
+            p90,
+            p99,
+            reason_share,
+            start_bucket,
+            end_bucket,
+        })
+    }
+}
+
+impl<'a> FromRow<'a, PgRow> for super::disputes::metrics::DisputeMetricRow {
+    fn from_row(row: &'a PgRow) -> sqlx::Result<Self> {
+        let connector: Option<String> = row.try_get("connector").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let dispute_stage: Option<DBEnumWrapper<DisputeStage>> =
+            row.try_get("dispute_stage").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let won_count: Option<i64> = row.try_get("won_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let lost_count: Option<i64> = row.try_get("lost_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let sales_count: Option<i64> = row.try_get("sales_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let chargeback_count: Option<i64> =
+            row.try_get("chargeback_count").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+
+        let start_bucket: Option<PrimitiveDateTime> = row
+            .try_get::<Option<PrimitiveDateTime>, _>("start_bucket")?
+            .and_then(|dt| dt.replace_millisecond(0).ok());
+        let end_bucket: Option<PrimitiveDateTime> = row
+            .try_get::<Option<PrimitiveDateTime>, _>("end_bucket")?
+            .and_then(|dt| dt.replace_millisecond(0).ok());
+        Ok(Self {
+            connector,
+            dispute_stage,
+            won_count,
+            lost_count,
+            sales_count,
+            chargeback_count,
             start_bucket,
             end_bucket,
         })
@@ -253,6 +380,210 @@ impl<'a> FromRow<'a, PgRow> for super::payments::metrics::PaymentMetricRow {
                 ColumnNotFound(_) => Ok(Default::default()),
                 e => Err(e),
             })?;
+        let profile_id: Option<String> = row.try_get("profile_id").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let payment_method_type: Option<String> =
+            row.try_get("payment_method_type").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let card_network: Option<String> = row.try_get("card_network").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let day_of_week: Option<i32> = row.try_get("day_of_week").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let hour_of_day: Option<i32> = row.try_get("hour_of_day").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let billing_country: Option<DBEnumWrapper<CountryAlpha2>> =
+            row.try_get("billing_country").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let card_bin: Option<String> = row.try_get("card_bin").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let issuer_country: Option<String> =
+            row.try_get("issuer_country").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let customer_type: Option<String> = row.try_get("customer_type").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let percentile: Option<f64> = row.try_get("percentile").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let distinct_count: Option<i64> = row.try_get("distinct_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let authenticated_count: Option<i64> =
+            row.try_get("authenticated_count").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let authorized_count: Option<i64> =
+            row.try_get("authorized_count").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let captured_count: Option<i64> = row.try_get("captured_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let success_count: Option<i64> = row.try_get("success_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let recovered_count: Option<i64> = row.try_get("recovered_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let customer_segment: Option<String> =
+            row.try_get("customer_segment").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let decline_category: Option<String> =
+            row.try_get("decline_category").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let created_to_authorized_min: Option<f64> = row
+            .try_get("created_to_authorized_min")
+            .or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let created_to_authorized_max: Option<f64> = row
+            .try_get("created_to_authorized_max")
+            .or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let created_to_authorized_avg: Option<f64> = row
+            .try_get("created_to_authorized_avg")
+            .or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let created_to_captured_min: Option<f64> =
+            row.try_get("created_to_captured_min")
+                .or_else(|e| match e {
+                    ColumnNotFound(_) => Ok(Default::default()),
+                    e => Err(e),
+                })?;
+        let created_to_captured_max: Option<f64> =
+            row.try_get("created_to_captured_max")
+                .or_else(|e| match e {
+                    ColumnNotFound(_) => Ok(Default::default()),
+                    e => Err(e),
+                })?;
+        let created_to_captured_avg: Option<f64> =
+            row.try_get("created_to_captured_avg")
+                .or_else(|e| match e {
+                    ColumnNotFound(_) => Ok(Default::default()),
+                    e => Err(e),
+                })?;
+        let routing_rule: Option<String> = row.try_get("routing_rule").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let zero_amount_count: Option<i64> =
+            row.try_get("zero_amount_count").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let time_to_first_payment: Option<f64> =
+            row.try_get("time_to_first_payment").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let surcharged_count: Option<i64> =
+            row.try_get("surcharged_count").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let fee_total: Option<bigdecimal::BigDecimal> =
+            row.try_get("fee_total").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let effective_fee_rate: Option<bigdecimal::BigDecimal> =
+            row.try_get("effective_fee_rate").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let error_count: Option<i64> = row.try_get("error_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let discount_total: Option<bigdecimal::BigDecimal> =
+            row.try_get("discount_total").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let offered_count: Option<i64> = row.try_get("offered_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let used_count: Option<i64> = row.try_get("used_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let challenged_count: Option<i64> =
+            row.try_get("challenged_count").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let challenged_latency_avg: Option<f64> =
+            row.try_get("challenged_latency_avg").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let frictionless_count: Option<i64> =
+            row.try_get("frictionless_count").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let frictionless_latency_avg: Option<f64> = row
+            .try_get("frictionless_latency_avg")
+            .or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let previous_total: Option<bigdecimal::BigDecimal> =
+            row.try_get("previous_total").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let previous_count: Option<i64> = row.try_get("previous_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let retried_count: Option<i64> = row.try_get("retried_count").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let pending_duration_avg: Option<f64> =
+            row.try_get("pending_duration_avg").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let failure_rank: Option<i64> = row.try_get("failure_rank").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
         let total: Option<bigdecimal::BigDecimal> = row.try_get("total").or_else(|e| match e {
             ColumnNotFound(_) => Ok(Default::default()),
             e => Err(e),
@@ -274,6 +605,49 @@ impl<'a> FromRow<'a, PgRow> for super::payments::metrics::PaymentMetricRow {
             connector,
             authentication_type,
             payment_method,
+            profile_id,
+            payment_method_type,
+            card_network,
+            day_of_week: day_of_week.and_then(|v| u8::try_from(v).ok()),
+            hour_of_day: hour_of_day.and_then(|v| u8::try_from(v).ok()),
+            billing_country,
+            card_bin,
+            issuer_country,
+            customer_type,
+            percentile,
+            distinct_count,
+            authenticated_count,
+            authorized_count,
+            captured_count,
+            success_count,
+            recovered_count,
+            customer_segment,
+            decline_category,
+            created_to_authorized_min,
+            created_to_authorized_max,
+            created_to_authorized_avg,
+            created_to_captured_min,
+            created_to_captured_max,
+            created_to_captured_avg,
+            routing_rule,
+            zero_amount_count,
+            time_to_first_payment,
+            surcharged_count,
+            fee_total,
+            effective_fee_rate,
+            error_count,
+            discount_total,
+            offered_count,
+            used_count,
+            challenged_count,
+            challenged_latency_avg,
+            frictionless_count,
+            frictionless_latency_avg,
+            previous_total,
+            previous_count,
+            retried_count,
+            pending_duration_avg,
+            failure_rank,
             total,
             count,
             start_bucket,
@@ -308,12 +682,55 @@ impl<'a> FromRow<'a, PgRow> for super::payments::filters::FilterRow {
                 ColumnNotFound(_) => Ok(Default::default()),
                 e => Err(e),
             })?;
+        let profile_id: Option<String> = row.try_get("profile_id").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let payment_method_type: Option<String> =
+            row.try_get("payment_method_type").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let card_network: Option<String> = row.try_get("card_network").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let day_of_week: Option<i32> = row.try_get("day_of_week").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let hour_of_day: Option<i32> = row.try_get("hour_of_day").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let billing_country: Option<DBEnumWrapper<CountryAlpha2>> =
+            row.try_get("billing_country").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
+        let card_bin: Option<String> = row.try_get("card_bin").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
+        let issuer_country: Option<String> =
+            row.try_get("issuer_country").or_else(|e| match e {
+                ColumnNotFound(_) => Ok(Default::default()),
+                e => Err(e),
+            })?;
         Ok(Self {
             currency,
             status,
             connector,
             authentication_type,
             payment_method,
+            profile_id,
+            payment_method_type,
+            card_network,
+            day_of_week: day_of_week.and_then(|v| u8::try_from(v).ok()),
+            hour_of_day: hour_of_day.and_then(|v| u8::try_from(v).ok()),
+            billing_country,
+            card_bin,
+            issuer_country,
         })
     }
 }
@@ -339,15 +756,50 @@ impl<'a> FromRow<'a, PgRow> for super::refunds::filters::RefundFilterRow {
                 ColumnNotFound(_) => Ok(Default::default()),
                 e => Err(e),
             })?;
+        let refund_reason: Option<String> = row.try_get("refund_reason").or_else(|e| match e {
+            ColumnNotFound(_) => Ok(Default::default()),
+            e => Err(e),
+        })?;
         Ok(Self {
             currency,
             refund_status,
             connector,
             refund_type,
+            refund_reason,
         })
     }
 }
 
+impl<'a> FromRow<'a, PgRow> for QueryPlanRow {
+    fn from_row(row: &'a PgRow) -> sqlx::Result<Self> {
+        let line: String = row.try_get(0)?;
+        Ok(Self { line })
+    }
+}
+
+/// Rendered as a plain numeric literal, never in scientific notation, so it can be compared
+/// against numeric columns
+impl ToSql<SqlxClient> for bigdecimal::BigDecimal {
+    fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+        let (_, scale) = self.as_bigint_and_exponent();
+        Ok(self.with_scale(scale.max(0)).to_string())
+    }
+
+    fn is_numeric(&self) -> bool {
+        true
+    }
+}
+
+impl ToSql<SqlxClient> for &bigdecimal::BigDecimal {
+    fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
+        ToSql::<SqlxClient>::to_sql(*self)
+    }
+
+    fn is_numeric(&self) -> bool {
+        true
+    }
+}
+
 impl ToSql<SqlxClient> for PrimitiveDateTime {
     fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
         Ok(self.to_string())
@@ -356,10 +808,95 @@ impl ToSql<SqlxClient> for PrimitiveDateTime {
 
 impl ToSql<SqlxClient> for AnalyticsCollection {
     fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
-        match self {
-            Self::Payment => Ok("payment_attempt".to_string()),
-            Self::Refund => Ok("refund".to_string()),
-        }
+        Ok(collection_sql(self, None))
+    }
+
+    fn to_sql_in_schema(&self, schema: &str) -> error_stack::Result<String, ParsingError> {
+        Ok(collection_sql(self, Some(schema)))
+    }
+}
+
+/// Serializes `collection` with the tables it reads from qualified by `schema`, if any
+fn collection_sql(collection: &AnalyticsCollection, schema: Option<&str>) -> String {
+    let table = |name: &str| match schema {
+        Some(schema) => format!("{schema}.{name}"),
+        None => name.to_string(),
+    };
+    match collection {
+        AnalyticsCollection::Payment => table("payment_attempt"),
+        AnalyticsCollection::Refund => table("refund"),
+        AnalyticsCollection::Dispute => table("dispute"),
+        AnalyticsCollection::MerchantAccount => table("merchant_account"),
+        AnalyticsCollection::LatestPaymentAttempt => format!(
+            "(SELECT * FROM {} AS attempt \
+             WHERE attempt.created_at = (SELECT max(later_attempt.created_at) \
+             FROM {} AS later_attempt \
+             WHERE later_attempt.payment_id = attempt.payment_id \
+             AND later_attempt.merchant_id = attempt.merchant_id)) AS payment_attempt",
+            table("payment_attempt"),
+            table("payment_attempt"),
+        ),
+        AnalyticsCollection::PaymentMethodOffer => format!(
+            "(SELECT merchant_id, created_at, modified_at, \
+             json_array_elements_text(allowed_payment_method_types) AS payment_method_type, \
+             1 AS offered, 0 AS used FROM {} \
+             UNION ALL SELECT merchant_id, created_at, modified_at, payment_method_type, 0, 1 \
+             FROM {}) AS payment_method_offer",
+            table("payment_intent"),
+            table("payment_attempt"),
+        ),
+        AnalyticsCollection::CustomerSpend => format!(
+            "(SELECT payment_attempt.merchant_id, payment_intent.customer_id, \
+             payment_attempt.currency, min(payment_attempt.created_at) AS created_at, \
+             max(payment_attempt.modified_at) AS modified_at, \
+             sum(payment_attempt.amount) AS lifetime_value FROM {} \
+             JOIN {} ON payment_intent.payment_id = payment_attempt.payment_id \
+             AND payment_intent.merchant_id = payment_attempt.merchant_id \
+             WHERE payment_attempt.status = '{}' AND payment_intent.customer_id IS NOT NULL \
+             GROUP BY payment_attempt.merchant_id, payment_intent.customer_id, \
+             payment_attempt.currency) AS customer_spend",
+            table("payment_attempt"),
+            table("payment_intent"),
+            AttemptStatus::Charged,
+        ),
+        AnalyticsCollection::PaymentWithChargeback => format!(
+            "(SELECT payment_attempt.merchant_id, payment_attempt.connector, \
+             payment_attempt.status, payment_attempt.created_at, payment_attempt.modified_at, \
+             chargeback.attempt_id IS NOT NULL AS charged_back FROM {} \
+             LEFT JOIN (SELECT DISTINCT merchant_id, attempt_id FROM {} \
+             WHERE dispute_stage = '{}') AS chargeback \
+             ON chargeback.attempt_id = payment_attempt.attempt_id \
+             AND chargeback.merchant_id = payment_attempt.merchant_id) \
+             AS payment_with_chargeback",
+            table("payment_attempt"),
+            table("dispute"),
+            DisputeStage::Dispute,
+        ),
+        AnalyticsCollection::PaymentWithCustomerSpend => format!(
+            "(SELECT payment_attempt.*, \
+             coalesce(customer_spend.lifetime_value, 0) AS lifetime_value \
+             FROM {} LEFT JOIN {} \
+             ON payment_intent.payment_id = payment_attempt.payment_id \
+             AND payment_intent.merchant_id = payment_attempt.merchant_id \
+             LEFT JOIN {} ON customer_spend.merchant_id = payment_attempt.merchant_id \
+             AND customer_spend.customer_id = payment_intent.customer_id \
+             AND customer_spend.currency = payment_attempt.currency) AS payment_attempt",
+            table("payment_attempt"),
+            table("payment_intent"),
+            collection_sql(&AnalyticsCollection::CustomerSpend, schema),
+        ),
+        AnalyticsCollection::RefundWithPayment => format!(
+            "(SELECT merchant_id, currency, connector, refund_status, refund_type, \
+             refund_reason, refund_amount, 0 AS payment_amount, created_at, modified_at \
+             FROM {} WHERE refund_status = '{}' \
+             UNION ALL SELECT merchant_id, currency, connector, NULL, NULL, NULL, \
+             0, amount, created_at, modified_at FROM {} \
+             WHERE status = '{}') AS refund_with_payment",
+            table("refund"),
+            RefundStatus::Success,
+            table("payment_attempt"),
+            AttemptStatus::Charged,
+        ),
     }
 }
 
@@ -369,9 +906,24 @@ where
 {
     fn to_sql(&self) -> error_stack::Result<String, ParsingError> {
         Ok(match self {
-            Self::Count { field: _, alias } => {
+            Self::Count { field, alias } => {
+                format!(
+                    "count({}){}",
+                    field
+                        .as_ref()
+                        .map(|field| field.to_sql())
+                        .transpose()
+                        .attach_printable("Failed to count aggregate")?
+                        .unwrap_or_else(|| "*".to_owned()),
+                    alias.map_or_else(|| "".to_owned(), |alias| format!(" as {}", alias))
+                )
+            }
+            Self::DistinctCount { field, alias } => {
                 format!(
-                    "count(*){}",
+                    "count(DISTINCT {}){}",
+                    field
+                        .to_sql()
+                        .attach_printable("Failed to distinct count aggregate")?,
                     alias.map_or_else(|| "".to_owned(), |alias| format!(" as {}", alias))
                 )
             }
@@ -396,6 +948,33 @@ where
                     alias.map_or_else(|| "".to_owned(), |alias| format!(" as {}", alias))
                 )
             }
+            Self::Percentile {
+                field,
+                alias,
+                percentile,
+            } => {
+                format!(
+                    "percentile_cont({}) WITHIN GROUP (ORDER BY {}){}",
+                    f64::from(*percentile) / 100.0,
+                    field
+                        .to_sql()
+                        .attach_printable("Failed to percentile aggregate")?,
+                    alias.map_or_else(|| "".to_owned(), |alias| format!(" as {}", alias))
+                )
+            }
+            Self::CountIf { condition, alias } => {
+                format!(
+                    "count(*) FILTER (WHERE {}){}",
+                    condition
+                        .to_sql()
+                        .attach_printable("Failed to conditional count aggregate")?,
+                    alias.map_or_else(|| "".to_owned(), |alias| format!(" as {}", alias))
+                )
+            }
         })
     }
+
+    fn is_aggregate(&self) -> bool {
+        true
+    }
 }
@@ -5,14 +5,16 @@ use common_utils::{
     events::ApiEventMetric,
 };
 use error_stack::{report, Report, ResultExt};
+use time::UtcOffset;
 
-use super::query::QueryBuildingError;
+use super::query::{Column, QueryBuildingError};
 
 #[derive(serde::Deserialize, Debug, masking::Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum AnalyticsDomain {
     Payments,
     Refunds,
+    Disputes,
 }
 
 impl ApiEventMetric for AnalyticsDomain {}
@@ -21,6 +23,31 @@ impl ApiEventMetric for AnalyticsDomain {}
 pub enum AnalyticsCollection {
     Payment,
     Refund,
+    /// Successful refunds alongside charged payments, for metrics relating refunded value to
+    /// processed value. Payment rows carry no refund status or type.
+    RefundWithPayment,
+    Dispute,
+    /// Merchant accounts, for metrics about the merchants themselves rather than their payments
+    MerchantAccount,
+    /// Latest attempt of every payment, for metrics about the final status of payments rather
+    /// than the intermediate statuses of their attempts. Exposes the columns of the attempts
+    /// table under the same name, so payment dimensions and filters apply as they are.
+    LatestPaymentAttempt,
+    /// Payment method types offered on payment intents alongside the ones attempts were made
+    /// with, flagged as `offered` and `used` respectively
+    PaymentMethodOffer,
+    /// Total charged amount of every customer of a merchant per currency, as `lifetime_value`.
+    /// `created_at` is the time of the customer's first charged attempt and `modified_at` the
+    /// last update of any of their charged attempts.
+    CustomerSpend,
+    /// Payment attempts flagged as `charged_back` when a chargeback was raised against them, i.e.
+    /// a dispute at the dispute stage as opposed to the inquiries before it and the arbitration
+    /// after it. Exposes the columns of the attempts table the chargeback ratio reads.
+    PaymentWithChargeback,
+    /// Payment attempts alongside the lifetime value of their customer in the currency of the
+    /// attempt, as `lifetime_value`, zero for attempts without a customer. Exposes the columns of
+    /// the attempts table under the same name, so payment dimensions and filters apply as they are.
+    PaymentWithCustomerSpend,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
@@ -47,8 +74,104 @@ where
     }
 }
 
+/// Offset of the timezone analytics timestamps are stored in, configured either as `UTC` or as
+/// `+HH:MM` / `-HH:MM`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct StorageTimezone(pub UtcOffset);
+
+impl Default for StorageTimezone {
+    fn default() -> Self {
+        Self(UtcOffset::UTC)
+    }
+}
+
+impl TryFrom<String> for StorageTimezone {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.eq_ignore_ascii_case("UTC") {
+            return Ok(Self::default());
+        }
+        let invalid = || format!("Invalid storage timezone {value}, expected UTC or +HH:MM");
+        let (sign, offset) = value
+            .strip_prefix('+')
+            .map(|offset| (1, offset))
+            .or_else(|| value.strip_prefix('-').map(|offset| (-1, offset)))
+            .ok_or_else(invalid)?;
+        let (hours, minutes) = offset.split_once(':').ok_or_else(invalid)?;
+        let hours: i8 = hours.parse().map_err(|_| invalid())?;
+        let minutes: i8 = minutes.parse().map_err(|_| invalid())?;
+        UtcOffset::from_hms(sign * hours, sign * minutes, 0)
+            .map(Self)
+            .map_err(|_| invalid())
+    }
+}
+
+/// Schema (or database) the analytics tables live in, for deployments keeping the tables of
+/// every tenant apart. Restricted to a plain identifier as it's written into the queries as is.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct StorageSchema(String);
+
+impl TryFrom<String> for StorageSchema {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let is_identifier = value
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+            && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if is_identifier {
+            Ok(Self(value))
+        } else {
+            Err(format!(
+                "Invalid storage schema {value}, expected letters, digits and underscores"
+            ))
+        }
+    }
+}
+
+impl AsRef<str> for StorageSchema {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
 // Analytics Framework
 
+/// How planner hints are written into the queries run on a data source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryHintStyle {
+    /// `/*+ hint */` comment heading the query, as read by `pg_hint_plan` on Postgres. Ignored
+    /// as a plain comment when the extension isn't installed.
+    LeadingComment,
+    /// `SETTINGS hint` clause at the end of the query
+    Settings,
+}
+
+/// How a query is restricted to a fraction of the rows of its table, see
+/// `QueryBuilder::set_sample`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleStyle {
+    /// `SAMPLE fraction` clause following the table, as read by ClickHouse on tables declaring a
+    /// sampling key
+    Sample,
+    /// `TABLESAMPLE BERNOULLI (percentage)` clause following the table, as read by Postgres
+    TableSample,
+}
+
+/// How the rows of a grouped query are folded into a JSON object per bucket, see
+/// `QueryBuilder::set_json_aggregation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonAggregationStyle {
+    /// `toJSONString(mapFromArrays(groupArray(key), groupArray(value)))`, as on ClickHouse
+    MapFromArrays,
+    /// `json_object_agg(key, value)` over an aliased subquery, as on Postgres
+    ObjectAgg,
+}
+
 pub trait RefundAnalytics {}
 
 #[async_trait::async_trait]
@@ -57,11 +180,64 @@ where
     Self: Sized + Sync + Send,
 {
     type Row;
+
+    /// Whether `GROUP BY` may reference select columns by their position
+    const SUPPORTS_GROUP_BY_ORDINAL: bool = false;
+
+    /// Whether `SELECT DISTINCT ON (...)` may keep a single row per combination of values
+    const SUPPORTS_DISTINCT_ON: bool = false;
+
+    /// Whether `ORDER BY` may reference a select column by its alias
+    const SUPPORTS_ORDER_BY_ALIAS: bool = false;
+
+    /// Whether `ORDER BY` accepts `NULLS FIRST` and `NULLS LAST`, otherwise the placement of nulls
+    /// is emulated by ordering on whether the column is null first
+    const SUPPORTS_NULLS_ORDER: bool = false;
+
+    /// Columns the data source stores under another name than the default one, e.g. where a
+    /// deployment names the amount column differently. Only applies to plain table columns.
+    const COLUMN_NAME_OVERRIDES: &'static [(Column, &'static str)] = &[];
+
+    /// Keyword prefixed to a query to get its execution plan instead of its results
+    const EXPLAIN_PREFIX: &'static str = "EXPLAIN";
+
+    /// Clause following the table to read only a fraction of its rows, if the data source can
+    /// sample tables
+    const SAMPLE_STYLE: Option<SampleStyle> = None;
+
+    /// Whether `GROUP BY CUBE(...)` may be used to group by every combination of a set of columns
+    const SUPPORTS_CUBE: bool = false;
+
+    /// Functions grouped rows are folded into a JSON object per bucket with, if the data source
+    /// can build JSON objects, see `QueryBuilder::set_json_aggregation`
+    const JSON_AGGREGATION_STYLE: Option<JsonAggregationStyle> = None;
+
+    /// How hints set with `QueryBuilder::set_query_hint` are added to the query
+    const QUERY_HINT_STYLE: QueryHintStyle = QueryHintStyle::LeadingComment;
+
+    /// Character identifiers are wrapped in when quoted, e.g. a backtick on ClickHouse, see
+    /// `QuotedIdentifier`
+    const IDENTIFIER_QUOTE: char = '"';
+
+    /// Timezone of the timestamps held by the data source
+    fn storage_timezone(&self) -> UtcOffset {
+        UtcOffset::UTC
+    }
+
+    /// Schema the tables queried are qualified with, tables are left unqualified without one
+    fn schema(&self) -> Option<&str> {
+        None
+    }
+
     async fn load_results<T>(&self, query: &str) -> CustomResult<Vec<T>, QueryExecutionError>
     where
         Self: LoadRow<T>;
 }
 
+/// Extracts a domain row from a raw database row.
+///
+/// Implementations look up values by column name, so the select clause of a query only needs to
+/// expose the expected column names and may list them in any order.
 pub trait LoadRow<T>
 where
     Self: AnalyticsDataSource,
@@ -89,6 +265,17 @@ pub enum QueryExecutionError {
     RowExtractionFailure,
     #[error("Database error")]
     DatabaseError,
+    #[error("Transient database error")]
+    TransientDatabaseError,
+    #[error("Query was cancelled")]
+    Cancelled,
+}
+
+impl QueryExecutionError {
+    /// Whether the failure is likely to go away when the query is run again, e.g. a timeout
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::TransientDatabaseError)
+    }
 }
 
 pub type MetricsResult<T> = CustomResult<T, MetricsError>;
@@ -117,3 +304,39 @@ impl ErrorSwitch<FiltersError> for QueryBuildingError {
         FiltersError::QueryBuildingError
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn test_storage_timezone_parsing() {
+        assert_eq!(
+            StorageTimezone::try_from("UTC".to_string()).unwrap(),
+            StorageTimezone::default()
+        );
+        assert_eq!(
+            StorageTimezone::try_from("+05:30".to_string()).unwrap(),
+            StorageTimezone(UtcOffset::from_hms(5, 30, 0).unwrap())
+        );
+        assert_eq!(
+            StorageTimezone::try_from("-03:00".to_string()).unwrap(),
+            StorageTimezone(UtcOffset::from_hms(-3, 0, 0).unwrap())
+        );
+        assert!(StorageTimezone::try_from("Asia/Kolkata".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_storage_schema_parsing() {
+        assert_eq!(
+            StorageSchema::try_from("tenant_1".to_string())
+                .unwrap()
+                .as_ref(),
+            "tenant_1"
+        );
+        assert!(StorageSchema::try_from(String::new()).is_err());
+        assert!(StorageSchema::try_from("1tenant".to_string()).is_err());
+        assert!(StorageSchema::try_from("public; DROP TABLE refund".to_string()).is_err());
+    }
+}
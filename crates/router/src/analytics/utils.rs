@@ -1,10 +1,16 @@
+use std::collections::BTreeSet;
+
 use api_models::analytics::{
-    payments::{PaymentDimensions, PaymentMetrics},
+    disputes::{DisputeDimensions, DisputeMetrics},
+    payments::{PaymentDimensions, PaymentMetrics, PaymentMetricsBucketIdentifier},
     refunds::{RefundDimensions, RefundMetrics},
     NameDescription,
 };
+use common_enums::enums::Currency;
 use strum::IntoEnumIterator;
 
+use super::payments::metrics::PaymentMetricRow;
+
 pub fn get_payment_dimensions() -> Vec<NameDescription> {
     PaymentDimensions::iter().map(Into::into).collect()
 }
@@ -13,6 +19,10 @@ pub fn get_refund_dimensions() -> Vec<NameDescription> {
     RefundDimensions::iter().map(Into::into).collect()
 }
 
+pub fn get_dispute_dimensions() -> Vec<NameDescription> {
+    DisputeDimensions::iter().map(Into::into).collect()
+}
+
 pub fn get_payment_metrics_info() -> Vec<NameDescription> {
     PaymentMetrics::iter().map(Into::into).collect()
 }
@@ -20,3 +30,143 @@ pub fn get_payment_metrics_info() -> Vec<NameDescription> {
 pub fn get_refund_metrics_info() -> Vec<NameDescription> {
     RefundMetrics::iter().map(Into::into).collect()
 }
+
+pub fn get_dispute_metrics_info() -> Vec<NameDescription> {
+    DisputeMetrics::iter().map(Into::into).collect()
+}
+
+/// Amounts in different currencies cannot be added up. Returns whether an amount metric has to be
+/// grouped by currency for every bucket to hold a single currency, i.e. unless the request is
+/// already grouped by currency or filtered on exactly one.
+pub fn requires_currency_grouping(currency_filter: &[Currency], grouped_by_currency: bool) -> bool {
+    !grouped_by_currency && currency_filter.len() != 1
+}
+
+/// Number of decimal places of the minor unit amounts stored for the currency
+pub fn currency_decimal_places(currency: Currency) -> u32 {
+    if currency.is_zero_decimal_currency() {
+        0
+    } else if currency.is_three_decimal_currency() {
+        3
+    } else {
+        2
+    }
+}
+
+/// Share of `part` in `total` as a percentage, `None` when there is no total to divide by. This
+/// is the accumulator side counterpart of [`super::query::percentage_expression`].
+pub fn percentage(part: i64, total: i64) -> Option<f64> {
+    if total <= 0 {
+        None
+    } else {
+        Some(f64::from(u32::try_from(part).ok()?) * 100.0 / f64::from(u32::try_from(total).ok()?))
+    }
+}
+
+/// Returns the dimensions which are populated in at least one bucket of the loaded result set
+pub fn get_populated_payment_dimensions(
+    data: &[(PaymentMetricsBucketIdentifier, PaymentMetricRow)],
+) -> BTreeSet<PaymentDimensions> {
+    data.iter()
+        .flat_map(|(id, _)| {
+            [
+                id.connector.as_ref().map(|_| PaymentDimensions::Connector),
+                id.payment_method
+                    .as_ref()
+                    .map(|_| PaymentDimensions::PaymentMethod),
+                id.currency.map(|_| PaymentDimensions::Currency),
+                id.auth_type.map(|_| PaymentDimensions::AuthType),
+                id.status.map(|_| PaymentDimensions::PaymentStatus),
+                id.profile_id.as_ref().map(|_| PaymentDimensions::ProfileId),
+                id.payment_method_type
+                    .as_ref()
+                    .map(|_| PaymentDimensions::PaymentMethodType),
+                id.card_network
+                    .as_ref()
+                    .map(|_| PaymentDimensions::CardNetwork),
+                id.day_of_week.map(|_| PaymentDimensions::DayOfWeek),
+                id.hour_of_day.map(|_| PaymentDimensions::HourOfDay),
+                id.billing_country
+                    .map(|_| PaymentDimensions::BillingCountry),
+                id.card_bin.as_ref().map(|_| PaymentDimensions::CardBin),
+                id.issuer_country
+                    .as_ref()
+                    .map(|_| PaymentDimensions::IssuerCountry),
+            ]
+        })
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use api_models::analytics::TimeRange;
+    use common_enums::enums::{AttemptStatus, Currency};
+    use time::macros::datetime;
+
+    use super::*;
+
+    fn empty_row() -> PaymentMetricRow {
+        PaymentMetricRow {
+            count: Some(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_requires_currency_grouping() {
+        assert!(requires_currency_grouping(&[], false));
+        assert!(requires_currency_grouping(
+            &[Currency::USD, Currency::EUR],
+            false
+        ));
+        assert!(!requires_currency_grouping(&[Currency::USD], false));
+        assert!(!requires_currency_grouping(&[], true));
+    }
+
+    #[test]
+    fn test_percentage() {
+        assert_eq!(percentage(1, 4), Some(25.0));
+        assert_eq!(percentage(0, 4), Some(0.0));
+        assert_eq!(percentage(0, 0), None);
+        assert_eq!(percentage(3, -1), None);
+    }
+
+    #[test]
+    fn test_populated_payment_dimensions_with_mixed_buckets() {
+        let time_range = TimeRange {
+            start_time: datetime!(2023-11-01 00:00:00),
+            end_time: None,
+        };
+        let data = vec![
+            (
+                PaymentMetricsBucketIdentifier {
+                    currency: Some(Currency::USD),
+                    ..PaymentMetricsBucketIdentifier::new(time_range)
+                },
+                empty_row(),
+            ),
+            (
+                PaymentMetricsBucketIdentifier {
+                    status: Some(AttemptStatus::Charged),
+                    connector: Some("stripe".to_string()),
+                    profile_id: Some("pro_1".to_string()),
+                    ..PaymentMetricsBucketIdentifier::new(time_range)
+                },
+                empty_row(),
+            ),
+        ];
+
+        let dimensions = get_populated_payment_dimensions(&data);
+        assert_eq!(
+            dimensions.into_iter().collect::<Vec<_>>(),
+            vec![
+                PaymentDimensions::Connector,
+                PaymentDimensions::Currency,
+                PaymentDimensions::PaymentStatus,
+                PaymentDimensions::ProfileId,
+            ]
+        );
+        assert!(get_populated_payment_dimensions(&[]).is_empty());
+    }
+}
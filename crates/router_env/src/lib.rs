@@ -42,6 +42,7 @@ pub enum AnalyticsFlow {
     GetPaymentFilters,
     GetRefundFilters,
     GetRefundsMetrics,
+    GetDisputeMetrics,
     GetPaymentMetrics,
 }
 